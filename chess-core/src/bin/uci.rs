@@ -0,0 +1,144 @@
+use std::io::{self, BufRead};
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use chess_core::{
+    board::Board, engine::{Engine, DEFAULT_HASH_MB}, uci::{info_line, parse_position, GoOptions},
+};
+
+// 用 chess-core 的搜索作为 UCI 引擎，在标准输入输出上通信，不打开窗口
+
+const MAX_HASH_MB: usize = 1024;
+const MAX_MULTIPV: usize = 5;
+
+struct Server {
+    engine: Option<Engine>,                 // 搜索时交给搜索线程，搜索完再取回
+    search: Option<JoinHandle<Engine>>,
+    infinite: bool,                         // 正在进行的搜索是 go infinite
+    stop: Arc<AtomicBool>,
+    board: Board,
+    history: Vec<Board>,
+    multipv: usize,
+    chess960: bool,                         // UCI_Chess960 选项
+}
+
+impl Server {
+    fn new() -> Self {
+        let engine = Engine::new();
+        Server {
+            stop: engine.stop_flag(),
+            engine: Some(engine),
+            search: None,
+            infinite: false,
+            board: Board::default(),
+            history: Vec::new(),
+            multipv: 1,
+            chess960: false,
+        }
+    }
+
+    // 等待正在进行的搜索结束并取回引擎。go infinite 不会自己结束，直接停止
+    fn finish_search(&mut self) -> &mut Engine {
+        if let Some(search) = self.search.take() {
+            if self.infinite {
+                self.stop.store(true, Ordering::Relaxed);
+            }
+            self.engine = Some(search.join().expect("search thread panicked"));
+        }
+        self.engine.as_mut().unwrap()
+    }
+
+    // 处理一行命令，收到 quit 时返回 false
+    fn handle(&mut self, line: &str) -> bool {
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "uci" => {
+                println!("id name bevy_chess");
+                println!("id author bevy_chess developers");
+                println!("option name Hash type spin default {} min 1 max {}", DEFAULT_HASH_MB, MAX_HASH_MB);
+                println!("option name MultiPV type spin default 1 min 1 max {}", MAX_MULTIPV);
+                println!("option name UCI_Chess960 type check default false");
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "ucinewgame" => {
+                self.finish_search().clear_hash();
+                self.board = Board::default();
+                self.history.clear();
+            }
+            "setoption" => self.set_option(args),
+            "position" => {
+                self.finish_search();
+                match parse_position(args, self.chess960) {
+                    Some((board, history)) => {
+                        self.board = board;
+                        self.history = history;
+                    }
+                    None => println!("info string invalid position: {}", args),
+                }
+            }
+            "go" => self.go(GoOptions::parse(args)),
+            "stop" => self.stop.store(true, Ordering::Relaxed),
+            "quit" => {
+                self.stop.store(true, Ordering::Relaxed);
+                return false
+            }
+            _ => {},
+        }
+        true
+    }
+
+    // setoption name <名字> value <值>
+    fn set_option(&mut self, args: &str) {
+        let Some((name, value)) = args.trim().strip_prefix("name ").and_then(|rest| rest.split_once(" value ")) else {
+            return
+        };
+        let value = value.trim();
+        match (name.trim(), value.parse::<usize>()) {
+            ("Hash", Ok(mb)) => self.finish_search().set_hash_size(mb.clamp(1, MAX_HASH_MB)),
+            ("MultiPV", Ok(multipv)) => self.multipv = multipv.clamp(1, MAX_MULTIPV),
+            ("UCI_Chess960", _) => self.chess960 = value == "true",
+            _ => {},
+        }
+    }
+
+    // 在另一个线程中搜索，每搜索完一层输出 info，最后输出 bestmove。go infinite 在收到 stop 之前不能给出 bestmove
+    fn go(&mut self, go: GoOptions) {
+        self.finish_search();
+        let mut engine = self.engine.take().unwrap();
+        let limits = go.limits(self.board.active_color);
+        let board = self.board.clone();
+        let history = self.history.clone();
+        let multipv = self.multipv;
+        self.infinite = go.infinite;
+        let stop = self.stop.clone();
+        stop.store(false, Ordering::Relaxed);
+        self.search = Some(thread::spawn(move || {
+            let start = Instant::now();
+            let lines = engine.analyze(&board, &history, &limits, multipv, |lines| {
+                for (i, line) in lines.iter().enumerate() {
+                    println!("{}", info_line(line, i + 1, start.elapsed()));
+                }
+            });
+            while go.infinite && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            match (lines[0].best, lines[0].pv.get(1)) {
+                (Some(best), Some(ponder)) => println!("bestmove {} ponder {}", best, ponder),
+                (Some(best), None) => println!("bestmove {}", best),
+                (None, _) => println!("bestmove 0000"),
+            }
+            engine
+        }));
+    }
+}
+
+fn main() {
+    let mut server = Server::new();
+    for line in io::stdin().lock().lines().map_while(Result::ok) {
+        if !server.handle(line.trim()) {
+            break
+        }
+    }
+    server.finish_search();
+}
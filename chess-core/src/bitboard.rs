@@ -0,0 +1,267 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+use std::sync::OnceLock;
+use crate::piece::*;
+use crate::square::BoardSize;
+
+// 位棋盘。第 x + BOARD_SIZE_I * y 位表示坐标 (x, y) 的格子，与 Board.pieces[x][y] 的坐标一致。
+// 按最大尺寸的棋盘编号，较小的棋盘只使用其中一部分，见 Bitboard::board
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Bitboard(pub u128);
+
+pub fn square(x: usize, y: usize) -> usize {
+    x + BOARD_SIZE_I * y
+}
+
+pub fn coords(sq: usize) -> (usize, usize) {
+    (sq % BOARD_SIZE_I, sq / BOARD_SIZE_I)
+}
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+    pub const ALL: Bitboard = Bitboard(!0);
+
+    // 给定尺寸的棋盘上的所有格子
+    pub fn board(size: BoardSize) -> Self {
+        let rank = (1u128 << size.files) - 1;
+        (0..size.ranks).fold(Bitboard::EMPTY, |bb, y| Bitboard(bb.0 | rank << (BOARD_SIZE_I * y)))
+    }
+
+    pub fn from_square(sq: usize) -> Self {
+        Bitboard(1 << sq)
+    }
+
+    pub fn contains(self, sq: usize) -> bool {
+        self.0 & (1 << sq) != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn any(self) -> bool {
+        self.0 != 0
+    }
+
+    pub fn more_than_one(self) -> bool {
+        self.0 & self.0.wrapping_sub(1) != 0
+    }
+
+    pub fn count(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    // 编号最小的格子
+    pub fn lsb(self) -> Option<usize> {
+        if self.0 == 0 { None } else { Some(self.0.trailing_zeros() as usize) }
+    }
+
+    // 编号最大的格子
+    pub fn msb(self) -> Option<usize> {
+        if self.0 == 0 { None } else { Some(127 - self.0.leading_zeros() as usize) }
+    }
+
+    pub fn set(&mut self, sq: usize) {
+        self.0 |= 1 << sq;
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Bitboard;
+    fn bitand(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Bitboard;
+    fn bitor(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 | rhs.0)
+    }
+}
+
+impl BitXor for Bitboard {
+    type Output = Bitboard;
+    fn bitxor(self, rhs: Bitboard) -> Bitboard {
+        Bitboard(self.0 ^ rhs.0)
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Bitboard) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Bitboard) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, rhs: Bitboard) {
+        self.0 ^= rhs.0;
+    }
+}
+
+// 依次取出位棋盘中的每个格子，从编号小到大
+pub struct Squares(u128);
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None
+        }
+        let sq = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(sq)
+    }
+}
+
+impl IntoIterator for Bitboard {
+    type Item = usize;
+    type IntoIter = Squares;
+
+    fn into_iter(self) -> Squares {
+        Squares(self.0)
+    }
+}
+
+// 八个方向，前四个方向格子编号递增，后四个递减
+const DIRECTIONS: [(isize, isize); 8] = [
+    (0, 1), (1, 0), (1, 1), (-1, 1),
+    (0, -1), (-1, 0), (-1, -1), (1, -1),
+];
+const ROOK_DIRECTIONS: [usize; 4] = [0, 1, 4, 5];
+const BISHOP_DIRECTIONS: [usize; 4] = [2, 3, 6, 7];
+
+const KNIGHT_OFFSETS: [(isize, isize); 8] = [
+    (1, 2), (2, 1), (2, -1), (1, -2),
+    (-1, -2), (-2, -1), (-2, 1), (-1, 2),
+];
+
+// 预先计算的攻击表，按最大尺寸的棋盘计算。较小的棋盘上，离开棋盘的射线不会再回到棋盘上，
+// 因此只需用 Bitboard::board 去掉棋盘外的目标格
+struct Tables {
+    knight: [Bitboard; SQUARE_COUNT],
+    king: [Bitboard; SQUARE_COUNT],
+    pawn: [[Bitboard; SQUARE_COUNT]; 2],
+    rays: [[Bitboard; SQUARE_COUNT]; 8],
+    between: Box<[[Bitboard; SQUARE_COUNT]; SQUARE_COUNT]>,
+}
+
+fn offset(sq: usize, (dx, dy): (isize, isize)) -> Option<usize> {
+    let (x, y) = coords(sq);
+    let (x, y) = (x as isize + dx, y as isize + dy);
+    if (0..BOARD_SIZE_I as isize).contains(&x) && (0..BOARD_SIZE_J as isize).contains(&y) {
+        Some(square(x as usize, y as usize))
+    } else {
+        None
+    }
+}
+
+fn jumps(sq: usize, offsets: &[(isize, isize)]) -> Bitboard {
+    let mut bb = Bitboard::EMPTY;
+    for &o in offsets {
+        if let Some(to) = offset(sq, o) {
+            bb.set(to);
+        }
+    }
+    bb
+}
+
+impl Tables {
+    fn new() -> Self {
+        let mut tables = Tables {
+            knight: [Bitboard::EMPTY; SQUARE_COUNT],
+            king: [Bitboard::EMPTY; SQUARE_COUNT],
+            pawn: [[Bitboard::EMPTY; SQUARE_COUNT]; 2],
+            rays: [[Bitboard::EMPTY; SQUARE_COUNT]; 8],
+            between: Box::new([[Bitboard::EMPTY; SQUARE_COUNT]; SQUARE_COUNT]),
+        };
+
+        for sq in 0..SQUARE_COUNT {
+            tables.knight[sq] = jumps(sq, &KNIGHT_OFFSETS);
+            tables.king[sq] = jumps(sq, &DIRECTIONS);
+            tables.pawn[PieceColor::White.index()][sq] = jumps(sq, &[(-1, 1), (1, 1)]);
+            tables.pawn[PieceColor::Black.index()][sq] = jumps(sq, &[(-1, -1), (1, -1)]);
+
+            for (d, &dir) in DIRECTIONS.iter().enumerate() {
+                let mut current = sq;
+                let mut passed = Bitboard::EMPTY;
+                while let Some(next) = offset(current, dir) {
+                    tables.rays[d][sq].set(next);
+                    tables.between[sq][next] = passed;
+                    passed.set(next);
+                    current = next;
+                }
+            }
+        }
+
+        tables
+    }
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(Tables::new)
+}
+
+pub fn knight_attacks(sq: usize) -> Bitboard {
+    tables().knight[sq]
+}
+
+pub fn king_attacks(sq: usize) -> Bitboard {
+    tables().king[sq]
+}
+
+// 某颜色的兵在 sq 上时攻击的格子
+pub fn pawn_attacks(color: PieceColor, sq: usize) -> Bitboard {
+    tables().pawn[color.index()][sq]
+}
+
+// 沿一个方向滑动，遇到第一个棋子（包括该棋子所在格）为止
+fn ray_attacks(sq: usize, d: usize, occupied: Bitboard) -> Bitboard {
+    let t = tables();
+    let ray = t.rays[d][sq];
+    let blockers = ray & occupied;
+    let first = if d < 4 { blockers.lsb() } else { blockers.msb() };
+    match first {
+        Some(b) => ray ^ t.rays[d][b],
+        None => ray,
+    }
+}
+
+pub fn rook_attacks(sq: usize, occupied: Bitboard) -> Bitboard {
+    ROOK_DIRECTIONS.iter().fold(Bitboard::EMPTY, |acc, &d| acc | ray_attacks(sq, d, occupied))
+}
+
+pub fn bishop_attacks(sq: usize, occupied: Bitboard) -> Bitboard {
+    BISHOP_DIRECTIONS.iter().fold(Bitboard::EMPTY, |acc, &d| acc | ray_attacks(sq, d, occupied))
+}
+
+pub fn queen_attacks(sq: usize, occupied: Bitboard) -> Bitboard {
+    rook_attacks(sq, occupied) | bishop_attacks(sq, occupied)
+}
+
+pub fn archbishop_attacks(sq: usize, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(sq, occupied) | knight_attacks(sq)
+}
+
+pub fn chancellor_attacks(sq: usize, occupied: Bitboard) -> Bitboard {
+    rook_attacks(sq, occupied) | knight_attacks(sq)
+}
+
+// 两格在同一直线或斜线上时，严格位于两格之间的格子；否则为空
+pub fn between(a: usize, b: usize) -> Bitboard {
+    tables().between[a][b]
+}
@@ -0,0 +1,1003 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use crate::{
+    bitboard::{between, king_attacks, Bitboard},
+    movegen::Position,
+    piece::*,
+    square::*,
+    fen::*,
+    variant::{VariantKind, CHECKS_TO_WIN},
+    zobrist,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DrawReason {
+    Stalemate,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+    InsufficientMaterial,
+    Agreement,
+    RaceTied,
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            DrawReason::Stalemate => "stalemate",
+            DrawReason::ThreefoldRepetition => "threefold repetition",
+            DrawReason::FivefoldRepetition => "fivefold repetition",
+            DrawReason::FiftyMoveRule => "fifty-move rule",
+            DrawReason::SeventyFiveMoveRule => "seventy-five-move rule",
+            DrawReason::InsufficientMaterial => "insufficient material",
+            DrawReason::Agreement => "agreement",
+            DrawReason::RaceTied => "both kings reaching the last rank",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WinReason {
+    Checkmate,
+    KingOfTheHill,
+    ThreeChecks,
+    RaceFinished,
+    KingExploded,
+    AllPiecesLost,
+    Stalemated,
+    KingCaptured,
+    TimeForfeit,
+}
+
+impl fmt::Display for WinReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            WinReason::Checkmate => "checkmate",
+            WinReason::KingOfTheHill => "king of the hill",
+            WinReason::ThreeChecks => "three checks",
+            WinReason::RaceFinished => "reaching the last rank",
+            WinReason::KingExploded => "exploding the king",
+            WinReason::AllPiecesLost => "losing all pieces",
+            WinReason::Stalemated => "being stalemated",
+            WinReason::KingCaptured => "capturing the king",
+            WinReason::TimeForfeit => "time forfeit",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BoardResult {
+    Winner(PieceColor, WinReason),
+    Draw(DrawReason),
+}
+
+#[derive(Clone)]
+pub struct Board {
+    pub pieces: Pieces,
+    pub active_color: PieceColor,
+    pub castling_availability: CastlingRights,
+    pub en_passant_target: Option<Square>,
+    pub halfmove: usize,
+    pub fullmove: usize,
+    pub size: BoardSize, // 棋盘的实际尺寸，由 FEN 中的行数和列数决定
+    pub chess960: bool, // 是否按 Chess960 的规则记录易位：王走到车的格子上，FEN 中的易位权可以写出车所在的列
+    pub variant: VariantKind,
+    pub checks: [u8; 2], // 双方已经将军的次数，按颜色的下标排列。只有三将军变体会统计
+    pub pockets: [Pocket; 2], // 双方手中的棋子，按颜色的下标排列。只有疯狂屋变体会使用
+    pub promoted: Bitboard, // 由兵升变而来的棋子所在的格子，疯狂屋中这些棋子被吃后变回兵
+    pub zobrist: u64, // 局面的 Zobrist 键，由 read_fen 和走子时维护。直接修改其他字段后需要用 zobrist::compute 重新计算
+}
+
+// 两个盘面相等当且仅当它们是同一个局面（见 same_position），不比较半回合计数和总回合数
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.zobrist == other.zobrist && same_position(self, other)
+    }
+}
+
+impl Eq for Board {}
+
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.zobrist.hash(state);
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        read_fen(INITIAL_FEN.to_string()).expect("initial FEN is valid")
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CastlingSide {
+    KingSide,
+    QueenSide,
+}
+
+impl CastlingSide {
+    pub const ALL: [CastlingSide; 2] = [CastlingSide::KingSide, CastlingSide::QueenSide];
+
+    pub fn index(&self) -> usize {
+        match *self {
+            CastlingSide::KingSide => 0,
+            CastlingSide::QueenSide => 1,
+        }
+    }
+
+    // 在有 files 列的棋盘上易位后王所在的列：王侧为倒数第二列，后侧为 c 列
+    pub fn king_file(&self, files: usize) -> usize {
+        match *self {
+            CastlingSide::KingSide => files - 2,
+            CastlingSide::QueenSide => 2,
+        }
+    }
+
+    // 在有 files 列的棋盘上易位后车所在的列，紧挨着王的内侧
+    pub fn rook_file(&self, files: usize) -> usize {
+        match *self {
+            CastlingSide::KingSide => files - 3,
+            CastlingSide::QueenSide => 3,
+        }
+    }
+}
+
+// 王车易位权。记录每一方王侧和后侧可以参与易位的车所在的列，没有易位权时为 None
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct CastlingRights([[Option<u8>; 2]; 2]);
+
+impl CastlingRights {
+    pub const NONE: CastlingRights = CastlingRights([[None; 2]; 2]);
+
+    // 标准国际象棋开局的易位权，车在有 files 列的棋盘的两个角上
+    pub fn standard(files: usize) -> Self {
+        let rights = [Some(files as u8 - 1), Some(0)];
+        CastlingRights([rights, rights])
+    }
+
+    pub fn rook_file(&self, color: PieceColor, side: CastlingSide) -> Option<usize> {
+        self.0[color.index()][side.index()].map(|f| f as usize)
+    }
+
+    pub fn has(&self, color: PieceColor, side: CastlingSide) -> bool {
+        self.0[color.index()][side.index()].is_some()
+    }
+
+    pub fn set(&mut self, color: PieceColor, side: CastlingSide, rook_file: Option<usize>) {
+        self.0[color.index()][side.index()] = rook_file.map(|f| f as u8);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == CastlingRights::NONE
+    }
+
+    // 王走动后失去双方向的易位权
+    pub fn clear_color(&mut self, color: PieceColor) {
+        self.0[color.index()] = [None; 2];
+    }
+
+    // 车离开或在原位被吃后失去对应的易位权
+    pub fn clear_square(&mut self, sq: Square, size: BoardSize) {
+        for color in PieceColor::ALL {
+            if sq.rank() != size.back_rank(color) {
+                continue;
+            }
+            for side in CastlingSide::ALL {
+                if self.rook_file(color, side) == Some(sq.file()) {
+                    self.set(color, side, None);
+                }
+            }
+        }
+    }
+
+    // 按 白王侧、白后侧、黑王侧、黑后侧 的顺序列出所有易位权
+    pub fn iter(&self) -> impl Iterator<Item = (PieceColor, CastlingSide, usize)> + '_ {
+        PieceColor::ALL.into_iter()
+            .flat_map(|color| CastlingSide::ALL.into_iter().map(move |side| (color, side)))
+            .filter_map(|(color, side)| self.rook_file(color, side).map(|file| (color, side, file)))
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct Step {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<PieceRole>, // 兵升变的目标棋子，非升变的步为 None
+    pub drop: Option<PieceRole>,      // 疯狂屋中从手中放下的棋子，此时 from 与 to 相同
+}
+
+impl Step {
+    pub fn new(from: Square, to: Square) -> Self {
+        Step { from, to, promotion: None, drop: None }
+    }
+
+    // 把手中的棋子放到 to 格
+    pub fn new_drop(role: PieceRole, to: Square) -> Self {
+        Step { from: to, to, promotion: None, drop: Some(role) }
+    }
+
+    pub fn with_promotion(self, role: PieceRole) -> Self {
+        Step { promotion: Some(role), ..self }
+    }
+}
+
+// UCI 记法，如 e2e4、e7e8q，放下棋子时如 N@f3
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(role) = self.drop {
+            return write!(f, "{}@{}", role_to_uci(role).to_ascii_uppercase(), self.to)
+        }
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(role) = self.promotion {
+            write!(f, "{}", role_to_uci(role))?;
+        }
+        Ok(())
+    }
+}
+
+fn role_to_uci(role: PieceRole) -> char {
+    match role {
+        PieceRole::Pawn => 'p',
+        PieceRole::Rook => 'r',
+        PieceRole::Knight => 'n',
+        PieceRole::Bishop => 'b',
+        PieceRole::Queen => 'q',
+        PieceRole::King => 'k',
+        PieceRole::Archbishop => 'a',
+        PieceRole::Chancellor => 'c',
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ParseStepError;
+
+impl fmt::Display for ParseStepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid UCI move")
+    }
+}
+
+impl std::error::Error for ParseStepError {}
+
+// 只检查格式，不检查在某个盘面上是否可行
+impl FromStr for Step {
+    type Err = ParseStepError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || !s.is_ascii() {
+            return Err(ParseStepError)
+        }
+        if let Some((role, square)) = s.split_once('@') {
+            let role = match role {
+                "P" => PieceRole::Pawn,
+                "N" => PieceRole::Knight,
+                "B" => PieceRole::Bishop,
+                "R" => PieceRole::Rook,
+                "Q" => PieceRole::Queen,
+                "A" => PieceRole::Archbishop,
+                "C" => PieceRole::Chancellor,
+                _ => return Err(ParseStepError),
+            };
+            let to = square.parse().map_err(|_| ParseStepError)?;
+            return Ok(Step::new_drop(role, to))
+        }
+        // 格子为一个字母加上一到两位数字，如 e2e4、a9a10
+        let square_end = |start: usize| {
+            s[start + 1..].find(|c: char| !c.is_ascii_digit()).map_or(s.len(), |i| start + 1 + i)
+        };
+        let from_end = square_end(0);
+        if from_end >= s.len() {
+            return Err(ParseStepError)
+        }
+        let to_end = square_end(from_end);
+        let from = s[..from_end].parse().map_err(|_| ParseStepError)?;
+        let to = s[from_end..to_end].parse().map_err(|_| ParseStepError)?;
+        let promotion = match &s[to_end..] {
+            "" => None,
+            "q" => Some(PieceRole::Queen),
+            "r" => Some(PieceRole::Rook),
+            "b" => Some(PieceRole::Bishop),
+            "n" => Some(PieceRole::Knight),
+            "k" => Some(PieceRole::King),
+            "a" => Some(PieceRole::Archbishop),
+            "c" => Some(PieceRole::Chancellor),
+            _ => return Err(ParseStepError),
+        };
+        Ok(Step { from, to, promotion, drop: None })
+    }
+}
+
+// 兵可以升变成的棋子
+pub const PROMOTION_ROLES: [PieceRole; 4] = [PieceRole::Queen, PieceRole::Rook, PieceRole::Bishop, PieceRole::Knight];
+
+// 一步棋的特殊性质
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct MoveFlags {
+    pub capture: bool,      // 包括吃过路兵
+    pub en_passant: bool,
+    pub castle: bool,
+    pub promotion: bool,
+    pub drop: bool,
+}
+
+// 一步棋的撤销记录：走动的棋子、被吃的棋子，以及走棋前的易位权、过路兵格、半回合计数和键值
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Move {
+    pub step: Step,
+    pub flags: MoveFlags,
+    pub piece: Piece,                   // 走动的棋子，升变时为原来的兵
+    pub captured: Option<Piece>,
+    pub captured_square: Square,        // 被吃棋子所在的格子，吃过路兵时与 step.to 不同
+    pub castling_availability: CastlingRights,
+    pub en_passant_target: Option<Square>,
+    pub halfmove: usize,
+    pub checks: [u8; 2],
+    pub pockets: [Pocket; 2],
+    pub promoted: Bitboard,
+    pub exploded: [Option<(Square, Piece)>; 8], // 原子象棋中吃子时被炸掉的周围棋子，不包括吃子和被吃的棋子
+    pub zobrist: u64,
+}
+
+// 与 Step 相同，使用 UCI 记法
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.step)
+    }
+}
+
+// 判断一步已知可行的棋的性质，不需要走棋
+pub fn move_flags(board: &Board, step: Step) -> MoveFlags {
+    if step.drop.is_some() {
+        return MoveFlags { drop: true, ..Default::default() }
+    }
+    let pawn = board.pieces[step.from].is_some_and(|p| p.piece_role == PieceRole::Pawn);
+    let en_passant = pawn && board.en_passant_target == Some(step.to) && step.from.file() != step.to.file();
+    // Chess960 中易位时王走到己方的车上，不算吃子
+    let castle = is_castle(board, step);
+    MoveFlags {
+        capture: en_passant || (!castle && board.pieces[step.to].is_some()),
+        en_passant,
+        castle,
+        promotion: is_promotion(board, step),
+        drop: false,
+    }
+}
+
+// 王车易位的方向。step 必须是易位的步，Chess960 中 step.to 为车所在的格子，否则为王的目标格
+pub fn castle_side(step: Step) -> CastlingSide {
+    if step.to.file() > step.from.file() { CastlingSide::KingSide } else { CastlingSide::QueenSide }
+}
+
+// 王车易位中王和车的终点，以及车的起点：(王终点, 车起点, 车终点)
+fn castle_squares(board: &Board, step: Step, color: PieceColor) -> (Square, Square, Square) {
+    let side = castle_side(step);
+    let rank = step.from.rank();
+    let rook_from = if board.chess960 {
+        step.to
+    } else {
+        let file = board.castling_availability.rook_file(color, side)
+            .unwrap_or(if side == CastlingSide::KingSide { board.size.files - 1 } else { 0 });
+        Square::new(file, rank)
+    };
+    let files = board.size.files;
+    (Square::new(side.king_file(files), rank), rook_from, Square::new(side.rook_file(files), rank))
+}
+
+impl Board {
+    // 变体计入键值，改变变体后重新计算
+    pub fn set_variant(&mut self, variant: VariantKind) {
+        self.variant = variant;
+        self.zobrist = zobrist::compute(self);
+    }
+
+    // 原地执行一步已知合法的棋，处理吃过路兵、升变、王车易位和易位权，返回撤销记录
+    pub fn make_move(&mut self, step: Step) -> Move {
+        let piece = match step.drop {
+            Some(role) => Piece { piece_role: role, piece_color: self.active_color },
+            None => self.pieces[step.from].expect("make_move: no piece on the from square"),
+        };
+        let pawn = piece.piece_role == PieceRole::Pawn;
+        let flags = move_flags(self, step);
+
+        let captured_square = if flags.en_passant {
+            step.to.offset(forward(piece.piece_color.flip())).unwrap()
+        } else {
+            step.to
+        };
+        let mut mv = Move {
+            step,
+            flags,
+            piece,
+            captured: if flags.capture { self.pieces[captured_square] } else { None },
+            captured_square,
+            castling_availability: self.castling_availability,
+            en_passant_target: self.en_passant_target,
+            halfmove: self.halfmove,
+            checks: self.checks,
+            pockets: self.pockets,
+            promoted: self.promoted,
+            exploded: [None; 8],
+            zobrist: self.zobrist,
+        };
+
+        // 旧的过路兵格要在棋子移动之前移出键值
+        self.zobrist ^= zobrist::en_passant_term(self);
+
+        // 移动棋子，处理吃子与升变。王车易位时先拿起王和车，再放到各自的终点，因为 Chess960 中终点可能是另一个棋子的起点
+        if flags.drop {
+            self.pieces[step.to] = Some(piece);
+            self.zobrist ^= zobrist::piece_key(piece, step.to);
+            self.change_pocket(piece.piece_color, piece.piece_role, Pocket::remove);
+        } else if flags.castle {
+            let (king_to, rook_from, rook_to) = castle_squares(self, step, piece.piece_color);
+            let rook = self.pieces[rook_from].take().expect("make_move: no rook to castle with");
+            self.pieces[step.from] = None;
+            self.pieces[king_to] = Some(piece);
+            self.pieces[rook_to] = Some(rook);
+            self.zobrist ^= zobrist::piece_key(piece, step.from) ^ zobrist::piece_key(piece, king_to);
+            self.zobrist ^= zobrist::piece_key(rook, rook_from) ^ zobrist::piece_key(rook, rook_to);
+        } else {
+            if let Some(captured) = mv.captured {
+                self.pieces[captured_square] = None;
+                self.zobrist ^= zobrist::piece_key(captured, captured_square);
+                // 疯狂屋中被吃的棋子进入吃子一方的手中，升变来的棋子变回兵
+                if self.variant.rules().has_pockets() {
+                    let role = if self.promoted.contains(captured_square.index()) { PieceRole::Pawn } else { captured.piece_role };
+                    self.change_pocket(piece.piece_color, role, Pocket::add);
+                }
+            }
+            let was_promoted = self.promoted.contains(step.from.index());
+            self.promoted &= !(Bitboard::from_square(step.from.index()) | Bitboard::from_square(captured_square.index()));
+            if was_promoted || (pawn && step.promotion.is_some()) {
+                self.promoted.set(step.to.index());
+            }
+            self.pieces[step.from] = None;
+            self.zobrist ^= zobrist::piece_key(piece, step.from);
+            if flags.capture && self.variant.rules().explodes() {
+                // 原子象棋中吃子的棋子不落下，和目标格周围的棋子一起被炸掉
+                self.promoted &= !Bitboard::from_square(step.to.index());
+                mv.exploded = self.explode(step.to);
+            } else {
+                let placed = match step.promotion {
+                    Some(role) if pawn => Piece { piece_role: role, ..piece },
+                    _ => piece,
+                };
+                self.pieces[step.to] = Some(placed);
+                self.zobrist ^= zobrist::piece_key(placed, step.to);
+            }
+        }
+
+        // 兵走两格时设置过路兵格
+        self.en_passant_target = if pawn && step.from.rank().abs_diff(step.to.rank()) == 2 {
+            step.from.offset(forward(piece.piece_color))
+        } else {
+            None
+        };
+
+        // 处理王车易位权：王走动后失去双方向的易位权，车离开或在原位被吃后失去对应的易位权
+        if piece.piece_role == PieceRole::King {
+            self.castling_availability.clear_color(piece.piece_color);
+        }
+        if piece.piece_role == PieceRole::Rook {
+            self.castling_availability.clear_square(step.from, self.size);
+        }
+        if mv.captured.is_some() {
+            self.castling_availability.clear_square(captured_square, self.size);
+        }
+        for &(sq, p) in mv.exploded.iter().flatten() {
+            if p.piece_role == PieceRole::King {
+                self.castling_availability.clear_color(p.piece_color);
+            }
+            self.castling_availability.clear_square(sq, self.size);
+        }
+        self.zobrist ^= zobrist::castling_keys(mv.castling_availability) ^ zobrist::castling_keys(self.castling_availability);
+
+        // 兵的移动和吃子重置半回合计数
+        if pawn || mv.captured.is_some() {
+            self.halfmove = 0;
+        } else {
+            self.halfmove += 1;
+        }
+        if piece.piece_color == PieceColor::Black {
+            self.fullmove += 1;
+        }
+        self.active_color = self.active_color.flip();
+        self.zobrist ^= zobrist::turn_key();
+
+        // 统计将军次数
+        let mover = piece.piece_color;
+        let count = self.checks[mover.index()];
+        if self.variant.rules().counts_checks() && count < CHECKS_TO_WIN && Position::from_board(self).checkers().any() {
+            self.zobrist ^= zobrist::checks_key(mover, count) ^ zobrist::checks_key(mover, count + 1);
+            self.checks[mover.index()] = count + 1;
+        }
+
+        // 新的过路兵格要在棋子都移动完之后才能判断能否被吃
+        self.zobrist ^= zobrist::en_passant_term(self);
+
+        mv
+    }
+
+    // 撤销 make_move 执行的一步棋，mv 必须是最近一次 make_move 的返回值
+    pub fn unmake_move(&mut self, mv: &Move) {
+        self.castling_availability = mv.castling_availability;
+        if mv.flags.drop {
+            self.pieces[mv.step.to] = None;
+        } else if mv.flags.castle {
+            let (king_to, rook_from, rook_to) = castle_squares(self, mv.step, mv.piece.piece_color);
+            let rook = self.pieces[rook_to].take();
+            self.pieces[king_to] = None;
+            self.pieces[rook_from] = rook;
+            self.pieces[mv.step.from] = Some(mv.piece);
+        } else {
+            self.pieces[mv.step.to] = None;
+            self.pieces[mv.step.from] = Some(mv.piece);
+            if let Some(captured) = mv.captured {
+                self.pieces[mv.captured_square] = Some(captured);
+            }
+            for &(sq, p) in mv.exploded.iter().flatten() {
+                self.pieces[sq] = Some(p);
+            }
+        }
+
+        if mv.piece.piece_color == PieceColor::Black {
+            self.fullmove -= 1;
+        }
+        self.active_color = mv.piece.piece_color;
+        self.en_passant_target = mv.en_passant_target;
+        self.halfmove = mv.halfmove;
+        self.checks = mv.checks;
+        self.pockets = mv.pockets;
+        self.promoted = mv.promoted;
+        self.zobrist = mv.zobrist;
+    }
+
+    // 炸掉 center 周围八格中除兵以外的棋子，返回被炸掉的棋子
+    fn explode(&mut self, center: Square) -> [Option<(Square, Piece)>; 8] {
+        let mut exploded = [None; 8];
+        for (i, sq) in king_attacks(center.index()).into_iter().map(Square::from_index).enumerate() {
+            if let Some(p) = self.pieces[sq]
+                && p.piece_role != PieceRole::Pawn
+            {
+                self.pieces[sq] = None;
+                self.zobrist ^= zobrist::piece_key(p, sq);
+                self.promoted &= !Bitboard::from_square(sq.index());
+                exploded[i] = Some((sq, p));
+            }
+        }
+        exploded
+    }
+
+    // 增加或减少手中某种棋子的数量，同时更新键值
+    pub fn change_pocket(&mut self, color: PieceColor, role: PieceRole, change: fn(&mut Pocket, PieceRole)) {
+        let pocket = &mut self.pockets[color.index()];
+        let before = pocket.count(role);
+        change(pocket, role);
+        self.zobrist ^= zobrist::pocket_key(color, role, before) ^ zobrist::pocket_key(color, role, pocket.count(role));
+    }
+}
+
+// 执行一步已知合法的棋，返回新的盘面
+fn act_move(board: &Board, step: Step) -> Board {
+    let mut b = board.clone();
+    b.make_move(step);
+    b
+}
+
+// 判断某个颜色的王是否安全。如果该颜色没有王则总是安全，如果该颜色有多个王则会判断其中某个王的安全性。不用在有多个王的时候调用。
+pub fn king_safe(board: &Board, c: PieceColor) -> bool {
+    let position = Position::from_board(board);
+    let Some(kp) = position.king_square(c) else {
+        return true
+    };
+    !position.is_attacked(kp, c.flip())
+}
+
+// 被牵制的棋子。ray 为王和牵制它的棋子之间的格子（包括被牵制的棋子所在的格子）以及牵制它的棋子所在的格子，被牵制的棋子只能在这条线上走
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Pin {
+    pub pinned: Square,
+    pub pinner: Square,
+    pub ray: Bitboard,
+}
+
+// 攻击关系的查询，用于分析、界面提示和引擎。结果都是位棋盘，下标为 Square::index
+impl Board {
+    // color 方攻击的所有格子，不考虑攻击的棋子是否被牵制
+    pub fn attacked_squares(&self, color: PieceColor) -> Bitboard {
+        Position::from_board(self).attacked_squares(color)
+    }
+
+    // color 方攻击 sq 格的所有棋子
+    pub fn attackers(&self, sq: Square, color: PieceColor) -> Bitboard {
+        let position = Position::from_board(self);
+        position.attackers(sq.index(), color, position.occupied())
+    }
+
+    // 正在将军行动方的棋子
+    pub fn checkers(&self) -> Bitboard {
+        Position::from_board(self).checkers()
+    }
+
+    // color 方被牵制在王上的棋子。没有王时返回空列表
+    pub fn pins(&self, color: PieceColor) -> Vec<Pin> {
+        let position = Position::from_board(self);
+        let Some(king) = position.king_square(color) else {
+            return Vec::new()
+        };
+        position.sliding_blockers(king, color.flip()).into_iter()
+            .filter(|&(pinned, _)| position.by_color[color.index()].contains(pinned))
+            .map(|(pinned, pinner)| Pin {
+                pinned: Square::from_index(pinned),
+                pinner: Square::from_index(pinner),
+                ray: between(king, pinner) | Bitboard::from_square(pinner),
+            })
+            .collect()
+    }
+
+    // color 方挡在己方远程棋子和对方王之间的棋子，走开（不沿着这条线）就形成闪击
+    pub fn discovered_check_candidates(&self, color: PieceColor) -> Bitboard {
+        let position = Position::from_board(self);
+        let Some(king) = position.king_square(color.flip()) else {
+            return Bitboard::EMPTY
+        };
+        let mut candidates = Bitboard::EMPTY;
+        for (blocker, _) in position.sliding_blockers(king, color) {
+            if position.by_color[color.index()].contains(blocker) {
+                candidates.set(blocker);
+            }
+        }
+        candidates
+    }
+}
+
+// 判断一步棋是否是王车易位。Chess960 中王走到己方的车上，否则王在底线横向走两格以上
+pub fn is_castle(board: &Board, step: Step) -> bool {
+    board.pieces[step.from].is_some_and(|p| {
+        let rank = board.size.back_rank(p.piece_color);
+        if p.piece_role != PieceRole::King || step.from.rank() != rank || step.to.rank() != rank {
+            return false
+        }
+        if board.chess960 {
+            board.pieces[step.to] == Some(Piece { piece_role: PieceRole::Rook, ..p })
+        } else {
+            step.from.file().abs_diff(step.to.file()) >= 2
+        }
+    })
+}
+
+// 把王车易位的另一种写法转换成盘面使用的写法：Chess960 盘面上王走到 g 列或 c 列，或标准盘面上王走到己方的车上。
+// 转换后的步不一定可行，不是易位的步原样返回
+pub fn normalize_step(board: &Board, step: Step) -> Step {
+    let Some(king) = board.pieces[step.from].filter(|p| p.piece_role == PieceRole::King) else {
+        return step
+    };
+    let rank = board.size.back_rank(king.piece_color);
+    if step.from.rank() != rank || step.to.rank() != rank || step.promotion.is_some() || is_castle(board, step) {
+        return step
+    }
+    let rights = board.castling_availability;
+    if board.chess960 {
+        // 王走到易位后的位置。如果这本身就是王的一步普通移动，则不当作易位
+        if king_attacks(step.from.index()).contains(step.to.index()) {
+            return step
+        }
+        for side in CastlingSide::ALL {
+            if let Some(file) = rights.rook_file(king.piece_color, side)
+                && side.king_file(board.size.files) == step.to.file()
+            {
+                return Step::new(step.from, Square::new(file, rank))
+            }
+        }
+    } else if board.pieces[step.to] == Some(Piece { piece_role: PieceRole::Rook, ..king }) {
+        for side in CastlingSide::ALL {
+            if rights.rook_file(king.piece_color, side) == Some(step.to.file()) {
+                return Step::new(step.from, Square::new(side.king_file(board.size.files), rank))
+            }
+        }
+    }
+    step
+}
+
+// 判断一步棋是否是兵走到底线，即需要指定升变棋子
+pub fn is_promotion(board: &Board, step: Step) -> bool {
+    board.pieces[step.from].is_some_and(|p| {
+        p.piece_role == PieceRole::Pawn && step.to.rank() == board.size.back_rank(p.piece_color.flip())
+    })
+}
+
+// 尝试移动。如果可行，返回成功后的盘面。兵走到底线时必须在 step.promotion 中指定升变的棋子，非升变的步不能指定。
+// 哪些步可行由变体决定，标准规则下如果移动没有应将或者送王，那么移动不可行。
+pub fn try_move(board: &Board, step: Step) -> Option<Board> {
+    let step = normalize_step(board, step);
+    let rules = board.variant.rules();
+    let legal = match step.drop {
+        Some(_) => rules.legal_drops(board),
+        None => rules.legal_moves_from(board, step.from),
+    };
+    if legal.contains(&step) {
+        Some(act_move(board, step))
+    } else {
+        None
+    }
+}
+
+// 查询从一个位置出发的所有可行的步。兵走到底线时，每种升变各算一步
+pub fn all_targets(board: &Board, from: Square) -> Vec<Step> {
+    board.variant.rules().legal_moves_from(board, from)
+}
+
+// 查询可能的所有移动
+pub fn all_move(board: &Board) -> Vec<Step> {
+    board.variant.rules().legal_moves(board)
+}
+
+// 判断双方是否都不可能将死对方：只剩王，或只剩一个轻子，或只剩同色格的象
+pub fn insufficient_material(board: &Board) -> bool {
+    let mut minors = 0;
+    let mut knights = 0;
+    let mut bishop_squares = [false, false];
+    for i in 0..BOARD_SIZE_I {
+        for j in 0..BOARD_SIZE_J {
+            let Some(piece) = board.pieces[i][j] else {
+                continue;
+            };
+            match piece.piece_role {
+                PieceRole::King => {},
+                PieceRole::Knight => { minors += 1; knights += 1; },
+                PieceRole::Bishop => { minors += 1; bishop_squares[(i + j) % 2] = true; },
+                _ => return false,
+            }
+        }
+    }
+    minors <= 1 || (knights == 0 && !(bishop_squares[0] && bishop_squares[1]))
+}
+
+// 当前行动方能否合法地吃过路兵。不能吃时过路兵格不影响局面是否重复
+pub fn en_passant_capturable(board: &Board) -> bool {
+    let Some(target) = board.en_passant_target else {
+        return false
+    };
+    let pawn = Some(Piece { piece_role: PieceRole::Pawn, piece_color: board.active_color });
+    let behind = forward(board.active_color.flip());
+    // 先找相邻的兵，大多数情况下不需要生成走法
+    let froms: Vec<Square> = [WEST, EAST].into_iter()
+        .filter_map(|side| target.offset(side)?.offset(behind))
+        .filter(|&from| board.pieces[from] == pawn)
+        .collect();
+    if froms.is_empty() {
+        return false
+    }
+    // 王不受保护的变体中吃过路兵总是可行的
+    if !board.variant.rules().royal_king() {
+        return true
+    }
+    let position = Position::from_board(board);
+    froms.into_iter()
+        .any(|from| position.legal_moves_from(from).contains(&Step::new(from, target)))
+}
+
+// 判断两个盘面是否是重复局面：变体、棋盘大小、棋子位置、行动方、易位权、可吃的过路兵、将军次数和手中的棋子均相同。
+// 疯狂屋中还要求升变来的棋子相同
+pub fn same_position(a: &Board, b: &Board) -> bool {
+    a.variant == b.variant
+        && a.size == b.size
+        && *a.pieces == *b.pieces
+        && a.checks == b.checks
+        && a.pockets == b.pockets
+        && (!a.variant.rules().has_pockets() || a.promoted == b.promoted)
+        && a.active_color == b.active_color
+        && a.castling_availability == b.castling_availability
+        && en_passant_capturable(a) == en_passant_capturable(b)
+        && (!en_passant_capturable(a) || a.en_passant_target == b.en_passant_target)
+}
+
+// 统计当前局面在棋局中出现的次数（包括当前局面）。history 为此前的所有盘面，按时间顺序排列
+pub fn repetition_count(board: &Board, history: &[Board]) -> usize {
+    // 吃子、兵的移动之后不可能出现重复局面，只需检查最近的 halfmove 个盘面
+    let start = history.len().saturating_sub(board.halfmove);
+    1 + history[start..].iter().filter(|b| *b == board).count()
+}
+
+// 判断当前局面是否是终局。如果是，返回棋局结果。不考虑重复局面，需要棋局历史时使用 end_game_with_history
+pub fn end_game(board: &Board) -> Option<BoardResult> {
+    end_game_with_history(board, &[])
+}
+
+// 结合棋局历史判断当前局面是否是终局。history 为此前的所有盘面，按时间顺序排列。
+// 变体特有的终局条件最先判断。三次重复和五十回合规则本应由棋手提出，这里同五次重复和七十五回合规则一样直接判和
+pub fn end_game_with_history(board: &Board, history: &[Board]) -> Option<BoardResult> {
+    let rules = board.variant.rules();
+    if let Some(result) = rules.variant_result(board) {
+        return Some(result)
+    }
+
+    if all_move(board).is_empty() {
+        return if rules.in_check(board, board.active_color) {
+            Some(BoardResult::Winner(board.active_color.flip(), WinReason::Checkmate))
+        } else {
+            Some(BoardResult::Draw(DrawReason::Stalemate))
+        }
+    }
+
+    let repetition = repetition_count(board, history);
+    let reason = if rules.insufficient_material(board) {
+        DrawReason::InsufficientMaterial
+    } else if repetition >= 5 {
+        DrawReason::FivefoldRepetition
+    } else if board.halfmove >= 150 {
+        DrawReason::SeventyFiveMoveRule
+    } else if repetition >= 3 {
+        DrawReason::ThreefoldRepetition
+    } else if board.halfmove >= 100 {
+        DrawReason::FiftyMoveRule
+    } else {
+        return None
+    };
+    Some(BoardResult::Draw(reason))
+}
+
+// 返回局面信息（终局或行动方）。result 为已经确定的棋局结果
+pub fn game_info(board: &Board, result: Option<BoardResult>) -> String {
+    if let Some(res) = result {
+        match res {
+            BoardResult::Winner(piece_color, reason) => {
+                match piece_color {
+                    PieceColor::White => format!("White win by {}", reason),
+                    PieceColor::Black => format!("Black win by {}", reason),
+                }
+            },
+            BoardResult::Draw(reason) => format!("Draw by {}", reason),
+        }
+    } else {
+        let info = match board.active_color {
+            PieceColor::White => "White play".to_string(),
+            PieceColor::Black => "Black play".to_string(),
+        };
+        if board.variant.rules().counts_checks() {
+            format!("{} (checks {}-{})", info, board.checks[0], board.checks[1])
+        } else {
+            info
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 走棋再撤销后盘面的每个字段都应复原
+    fn check_unmake(board: &mut Board, depth: usize) {
+        if depth == 0 {
+            return;
+        }
+        for step in all_move(board) {
+            let fields = |b: &Board| (b.pieces, b.active_color, b.castling_availability, b.en_passant_target, b.halfmove, b.fullmove, b.zobrist);
+            let before = fields(board);
+            let mv = board.make_move(step);
+            check_unmake(board, depth - 1);
+            board.unmake_move(&mv);
+            assert_eq!(fields(board), before);
+        }
+    }
+
+    #[test]
+    fn unmake_restores_board() {
+        for fen in [
+            INITIAL_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ] {
+            check_unmake(&mut read_fen(fen.to_string()).unwrap(), 3);
+        }
+        // 原子象棋中被炸掉的棋子也要复原
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut board = read_fen(fen.to_string()).unwrap();
+        board.set_variant(VariantKind::Atomic);
+        check_unmake(&mut board, 2);
+    }
+
+    #[test]
+    fn attack_queries() {
+        let squares = |bb: Bitboard| bb.into_iter().map(|sq| Square::from_index(sq).to_string()).collect::<Vec<_>>();
+        let board = Board::default();
+        assert_eq!(board.attacked_squares(PieceColor::White).count(), 22);
+        assert_eq!(squares(board.attackers("f3".parse().unwrap(), PieceColor::White)), ["g1", "e2", "g2"]);
+        assert!(board.attackers("f3".parse().unwrap(), PieceColor::Black).is_empty());
+        assert!(board.checkers().is_empty());
+
+        // 双方各有一个被牵制的兵
+        let board = read_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1".to_string()).unwrap();
+        assert_eq!(board.pins(PieceColor::White), vec![Pin {
+            pinned: "b5".parse().unwrap(),
+            pinner: "h5".parse().unwrap(),
+            ray: ["b5", "c5", "d5", "e5", "f5", "g5", "h5"].into_iter()
+                .fold(Bitboard::EMPTY, |bb, sq| bb | Bitboard::from_square(sq.parse::<Square>().unwrap().index())),
+        }]);
+        assert_eq!(board.pins(PieceColor::Black).iter().map(|pin| pin.pinned.to_string()).collect::<Vec<_>>(), ["f4"]);
+        assert!(board.discovered_check_candidates(PieceColor::White).is_empty());
+
+        // 马走开后车将军；马和车同时将军
+        let board = read_fen("4k3/8/8/8/4N3/8/8/4R1K1 w - - 0 1".to_string()).unwrap();
+        assert_eq!(squares(board.discovered_check_candidates(PieceColor::White)), ["e4"]);
+        assert!(board.pins(PieceColor::Black).is_empty());
+        let board = read_fen("4k3/8/3N4/8/8/8/8/4R1K1 b - - 0 1".to_string()).unwrap();
+        assert_eq!(squares(board.checkers()), ["e1", "d6"]);
+    }
+
+    #[test]
+    fn uci_round_trip() {
+        for s in ["e2e4", "e7e8q", "a2a1n", "e1g1", "a9a10c", "j2j1a", "A@e4"] {
+            assert_eq!(s.parse::<Step>().unwrap().to_string(), s);
+        }
+        for s in ["e2e", "e2e4p", "e7e8Q", "e2e11", "e2-e4", "", "K@e4"] {
+            assert_eq!(s.parse::<Step>(), Err(ParseStepError), "{}", s);
+        }
+    }
+
+    #[test]
+    fn move_record() {
+        let mut board = read_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3".to_string()).unwrap();
+        let mv = board.make_move("e5f6".parse().unwrap());
+        assert_eq!(mv.piece, Piece { piece_role: PieceRole::Pawn, piece_color: PieceColor::White });
+        assert_eq!(mv.captured, Some(Piece { piece_role: PieceRole::Pawn, piece_color: PieceColor::Black }));
+        assert_eq!(mv.captured_square, "f5".parse().unwrap());
+        assert_eq!(mv.en_passant_target, Some("f6".parse().unwrap()));
+        assert_eq!(mv.halfmove, 0);
+        assert_eq!(mv.flags, MoveFlags { capture: true, en_passant: true, castle: false, promotion: false, drop: false });
+        assert_eq!(mv.to_string(), "e5f6");
+    }
+
+    #[test]
+    fn repetition_draws() {
+        // 双方来回跳马，每四步回到同一个局面。第 8 步起每个局面都出现了三次，第 16 步时初始局面出现了五次
+        let mut board = Board::default();
+        let mut history = Vec::new();
+        for (i, uci) in ["g1f3", "g8f6", "f3g1", "f6g8"].iter().cycle().take(16).enumerate() {
+            let new_board = try_move(&board, uci.parse().unwrap()).unwrap();
+            history.push(std::mem::replace(&mut board, new_board));
+            let expected = match i + 1 {
+                16 => Some(BoardResult::Draw(DrawReason::FivefoldRepetition)),
+                8.. => Some(BoardResult::Draw(DrawReason::ThreefoldRepetition)),
+                _ => None,
+            };
+            assert!(end_game_with_history(&board, &history) == expected);
+        }
+        assert_eq!(repetition_count(&board, &history), 5);
+        // 只看当前盘面时不知道重复了几次
+        assert_eq!(repetition_count(&board, &[]), 1);
+        assert!(end_game(&board).is_none());
+    }
+
+    #[test]
+    fn move_rule_draws() {
+        let fen = |halfmove: usize| read_fen(format!("4k3/8/8/8/8/8/8/R3K3 w - - {} 80", halfmove)).unwrap();
+        assert!(end_game(&fen(99)).is_none());
+        assert!(end_game(&fen(100)) == Some(BoardResult::Draw(DrawReason::FiftyMoveRule)));
+        assert!(end_game(&fen(149)) == Some(BoardResult::Draw(DrawReason::FiftyMoveRule)));
+        assert!(end_game(&fen(150)) == Some(BoardResult::Draw(DrawReason::SeventyFiveMoveRule)));
+        // 将死优先于回合数
+        let mated = read_fen("R3k3/8/4K3/8/8/8/8/8 b - - 150 80".to_string()).unwrap();
+        assert!(end_game(&mated) == Some(BoardResult::Winner(PieceColor::White, WinReason::Checkmate)));
+    }
+
+    #[test]
+    fn insufficient_material_draws() {
+        let insufficient = |fen: &str| insufficient_material(&read_fen(fen.to_string()).unwrap());
+        assert!(insufficient("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(insufficient("4k3/8/8/8/8/8/8/4KN2 w - - 0 1"));
+        assert!(insufficient("2b1k3/8/8/8/8/8/8/4KB2 w - - 0 1"));
+        // 异色格的象、两个马、兵或车都可能将死
+        assert!(!insufficient("4kb2/8/8/8/8/8/8/4KB2 w - - 0 1"));
+        assert!(!insufficient("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1"));
+        assert!(!insufficient("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+        assert!(!insufficient("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"));
+        let board = read_fen("2b1k3/8/8/8/8/8/8/4KB2 w - - 0 1".to_string()).unwrap();
+        assert!(end_game(&board) == Some(BoardResult::Draw(DrawReason::InsufficientMaterial)));
+    }
+}
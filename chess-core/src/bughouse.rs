@@ -0,0 +1,211 @@
+use std::fmt;
+use regex::Regex;
+use crate::{
+    board::*, piece::*, step::{read_step, write_step}, variant::VariantKind,
+};
+
+// 双人四人象棋（bughouse）：两盘疯狂屋同时进行，一盘中吃掉的棋子交给另一盘中的队友，由队友放回棋盘。
+// A 盘的白方和 B 盘的黑方是队友。任意一盘分出胜负，整局结束
+
+// 两盘棋在 BPGN 中的名字，白方用大写字母，黑方用小写字母
+pub const BOARD_NAMES: [char; 2] = ['A', 'B'];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BughouseMove {
+    pub board: usize,       // 0 为 A 盘，1 为 B 盘
+    pub step: Step,
+    pub san: String,
+    pub color: PieceColor,
+    pub fullmove: usize,
+}
+
+// 读取 BPGN 时的错误
+#[derive(Clone, Debug, PartialEq)]
+pub enum BpgnError {
+    MissingMoveNumber(String),
+    IllegalMove { board: char, san: String },
+}
+
+impl fmt::Display for BpgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BpgnError::MissingMoveNumber(san) => write!(f, "move \"{}\" has no move number", san),
+            BpgnError::IllegalMove { board, san } => write!(f, "illegal move \"{}\" on board {}", san, board),
+        }
+    }
+}
+
+impl std::error::Error for BpgnError {}
+
+#[derive(Clone)]
+pub struct BughouseGame {
+    pub boards: [Board; 2],
+    pub moves: Vec<BughouseMove>,               // 两盘的着法，按走棋的时间顺序排列
+    pub result: Option<(usize, BoardResult)>,   // 先结束的一盘和它的结果
+}
+
+impl Default for BughouseGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BughouseGame {
+    pub fn new() -> Self {
+        let board = VariantKind::Bughouse.rules().start_position();
+        BughouseGame {
+            boards: [board.clone(), board],
+            moves: Vec::new(),
+            result: None,
+        }
+    }
+
+    // 在某盘棋中 color 方的队友：另一盘中颜色相反的一方
+    pub fn partner(board: usize, color: PieceColor) -> (usize, PieceColor) {
+        (1 - board, color.flip())
+    }
+
+    // 在第 index 盘走一步。吃到的棋子交给队友，棋局已经结束或着法不合法时返回 false
+    pub fn play(&mut self, index: usize, step: Step) -> bool {
+        if self.result.is_some() {
+            return false
+        }
+        let board = &self.boards[index];
+        let (color, fullmove) = (board.active_color, board.fullmove);
+        let pocket = board.pockets[color.index()];
+        let (Some(san), Some(mut new_board)) = (write_step(board, step), try_move(board, step)) else {
+            return false
+        };
+        let (partner, partner_color) = Self::partner(index, color);
+        for role in POCKET_ROLES {
+            if new_board.pockets[color.index()].count(role) > pocket.count(role) {
+                new_board.change_pocket(color, role, Pocket::remove);
+                self.boards[partner].change_pocket(partner_color, role, Pocket::add);
+            }
+        }
+        self.moves.push(BughouseMove {
+            board: index,
+            step,
+            san,
+            color,
+            fullmove,
+        });
+        self.result = end_game(&new_board).map(|result| (index, result));
+        self.boards[index] = new_board;
+        true
+    }
+
+    // 第 index 盘中 color 方超时，对方获胜
+    pub fn flag(&mut self, index: usize, color: PieceColor) {
+        if self.result.is_none() {
+            self.result = Some((index, BoardResult::Winner(color.flip(), WinReason::TimeForfeit)));
+        }
+    }
+
+    // BPGN 的 Result 标签，从 A 盘白方和 B 盘黑方一队的角度记录
+    pub fn result_tag(&self) -> &'static str {
+        match self.result {
+            None => "*",
+            Some((_, BoardResult::Draw(_))) => "1/2-1/2",
+            Some((index, BoardResult::Winner(color, _))) => {
+                if (index == 0) == (color == PieceColor::White) {
+                    "1-0"
+                } else {
+                    "0-1"
+                }
+            }
+        }
+    }
+
+    // 导出为 BPGN，着法写作 "1A. e4 1a. e5"，数字为该盘的回合数
+    pub fn to_bpgn(&self) -> String {
+        let movetext: Vec<String> = self.moves.iter()
+            .map(|mv| {
+                let name = match mv.color {
+                    PieceColor::White => BOARD_NAMES[mv.board],
+                    PieceColor::Black => BOARD_NAMES[mv.board].to_ascii_lowercase(),
+                };
+                format!("{}{}. {}", mv.fullmove, name, mv.san)
+            })
+            .collect();
+        format!("[Event \"Bughouse\"]\n[Result \"{}\"]\n\n{} {}", self.result_tag(), movetext.join(" "), self.result_tag())
+    }
+
+    // 读取 BPGN，忽略标签对和注释（如用时）。着法前的编号决定着法属于哪一盘
+    pub fn from_bpgn(bpgn: &str) -> Result<Self, BpgnError> {
+        let tags = Regex::new(r#"\[[^\]]*\]|\{[^}]*\}"#).unwrap();
+        let number = Regex::new(r"^\d+([AaBb])\.(.*)$").unwrap();
+        let movetext = tags.replace_all(bpgn, " ");
+
+        let mut game = Self::new();
+        let mut current = None;
+        for token in movetext.split_whitespace() {
+            if matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*") {
+                break
+            }
+            let san = match number.captures(token) {
+                Some(caps) => {
+                    let name = caps[1].chars().next().unwrap();
+                    current = Some(name);
+                    if caps[2].is_empty() {
+                        continue;
+                    }
+                    caps[2].to_string()
+                }
+                None => token.to_string(),
+            };
+            let Some(name) = current.take() else {
+                return Err(BpgnError::MissingMoveNumber(san))
+            };
+            let index = BOARD_NAMES.iter().position(|&c| c == name.to_ascii_uppercase()).unwrap();
+            let color = if name.is_ascii_uppercase() { PieceColor::White } else { PieceColor::Black };
+            let illegal = || BpgnError::IllegalMove { board: name, san: san.clone() };
+            let board = &game.boards[index];
+            if board.active_color != color {
+                return Err(illegal())
+            }
+            let Some(step) = read_step(board, san.clone()) else {
+                return Err(illegal())
+            };
+            if !game.play(index, step) {
+                return Err(illegal())
+            }
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::write_fen;
+
+    #[test]
+    fn captured_pieces_go_to_partner() {
+        let game = BughouseGame::from_bpgn("[WhiteA \"x\"]\n1A. e4 {59.1} 1a. d5 2A. exd5 1B. e4 1b. P@d4").unwrap();
+        assert_eq!(write_fen(game.boards[0].clone()), "rnbqkbnr/ppp1pppp/8/3P4/8/8/PPPP1PPP/RNBQKBNR[] b KQkq - 0 2");
+        assert_eq!(write_fen(game.boards[1].clone()), "rnbqkbnr/pppppppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR[] w KQkq - 0 2");
+        assert_eq!(game.to_bpgn(), "[Event \"Bughouse\"]\n[Result \"*\"]\n\n1A. e4 1a. d5 2A. exd5 1B. e4 1b. P@d4 *");
+        let again = BughouseGame::from_bpgn(&game.to_bpgn()).unwrap();
+        assert!(again.boards == game.boards);
+
+        assert_eq!(
+            BughouseGame::from_bpgn("1a. e5").err(),
+            Some(BpgnError::IllegalMove { board: 'a', san: "e5".to_string() }),
+        );
+        assert_eq!(BughouseGame::from_bpgn("1A. e4 e5").err(), Some(BpgnError::MissingMoveNumber("e5".to_string())));
+    }
+
+    #[test]
+    fn result_on_either_board() {
+        // B 盘黑方将死，A 盘白方一队获胜
+        let mut game = BughouseGame::from_bpgn("1B. f3 1b. e5 2B. g4 2b. Qh4#").unwrap();
+        assert!(game.result == Some((1, BoardResult::Winner(PieceColor::Black, WinReason::Checkmate))));
+        assert_eq!(game.result_tag(), "1-0");
+        assert!(!game.play(0, "e2e4".parse().unwrap()));
+
+        let mut game = BughouseGame::new();
+        game.flag(0, PieceColor::White);
+        assert_eq!(game.result_tag(), "0-1");
+    }
+}
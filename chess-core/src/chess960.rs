@@ -0,0 +1,153 @@
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use crate::{
+    bitboard::Bitboard, board::*, piece::*, square::{BoardSize, Square}, variant::VariantKind, zobrist,
+};
+
+// Chess960 的初始局面共 960 种，按 Scharnagl 的编号排列，518 号为标准国际象棋的初始局面
+pub const START_POSITION_COUNT: usize = 960;
+pub const STANDARD_START_POSITION: usize = 518;
+
+// 两个马在剩下的五个空格中的位置，按编号除以 96 的商查表
+const KNIGHT_TABLE: [(usize, usize); 10] = [
+    (0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4),
+];
+
+// 编号为 sp 的初始局面中底线上的棋子，从 a 列到 h 列
+pub fn back_rank_roles(sp: usize) -> [PieceRole; 8] {
+    assert!(sp < START_POSITION_COUNT, "start position {} is out of range", sp);
+    let mut rank: [Option<PieceRole>; 8] = [None; 8];
+    let mut n = sp;
+
+    // 白格象在 b、d、f、h 列，黑格象在 a、c、e、g 列
+    rank[2 * (n % 4) + 1] = Some(PieceRole::Bishop);
+    n /= 4;
+    rank[2 * (n % 4)] = Some(PieceRole::Bishop);
+    n /= 4;
+
+    // 后放在第 n % 6 个空格上
+    let empty = |rank: &[Option<PieceRole>; 8]| -> Vec<usize> {
+        (0..8).filter(|&i| rank[i].is_none()).collect()
+    };
+    rank[empty(&rank)[n % 6]] = Some(PieceRole::Queen);
+    n /= 6;
+
+    let (a, b) = KNIGHT_TABLE[n];
+    let squares = empty(&rank);
+    rank[squares[a]] = Some(PieceRole::Knight);
+    rank[squares[b]] = Some(PieceRole::Knight);
+
+    // 剩下的三个空格依次放车、王、车，这样王总在两个车之间
+    let squares = empty(&rank);
+    for (i, role) in squares.into_iter().zip([PieceRole::Rook, PieceRole::King, PieceRole::Rook]) {
+        rank[i] = Some(role);
+    }
+    rank.map(Option::unwrap)
+}
+
+// 编号为 sp 的初始局面，双方都有两个方向的易位权
+pub fn start_position(sp: usize) -> Board {
+    let roles = back_rank_roles(sp);
+    let mut pieces = Pieces::new();
+    let mut castling_availability = CastlingRights::NONE;
+    for color in PieceColor::ALL {
+        let (back, pawns) = match color {
+            PieceColor::White => (0, 1),
+            PieceColor::Black => (BoardSize::STANDARD.ranks - 1, BoardSize::STANDARD.ranks - 2),
+        };
+        for (x, role) in roles.into_iter().enumerate() {
+            pieces[Square::new(x, back)] = Some(Piece { piece_role: role, piece_color: color });
+            pieces[Square::new(x, pawns)] = Some(Piece { piece_role: PieceRole::Pawn, piece_color: color });
+        }
+        let king = roles.iter().position(|&r| r == PieceRole::King).unwrap();
+        let rooks: Vec<usize> = (0..8).filter(|&x| roles[x] == PieceRole::Rook).collect();
+        castling_availability.set(color, CastlingSide::QueenSide, rooks.iter().copied().find(|&x| x < king));
+        castling_availability.set(color, CastlingSide::KingSide, rooks.iter().copied().find(|&x| x > king));
+    }
+    let mut board = Board {
+        pieces,
+        active_color: PieceColor::White,
+        castling_availability,
+        en_passant_target: None,
+        halfmove: 0,
+        fullmove: 1,
+        size: BoardSize::STANDARD,
+        chess960: true,
+        variant: VariantKind::Standard,
+        checks: [0; 2],
+        pockets: [Pocket::default(); 2],
+        promoted: Bitboard::EMPTY,
+        zobrist: 0,
+    };
+    board.zobrist = zobrist::compute(&board);
+    board
+}
+
+// 随机选取一个初始局面的编号。随机数取自标准库中每次随机生成密钥的哈希器
+pub fn random_start_position() -> usize {
+    (RandomState::new().hash_one(0u8) % START_POSITION_COUNT as u64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen::*, perft::perft};
+
+    #[test]
+    fn numbered_start_positions() {
+        assert_eq!(write_fen(start_position(STANDARD_START_POSITION)), INITIAL_FEN);
+        assert_eq!(write_fen(start_position(0)), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+        assert_eq!(write_shredder_fen(start_position(0)), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1");
+        assert_eq!(write_fen(start_position(959)), "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1");
+        // 所有编号对应不同的局面
+        let all: std::collections::HashSet<[PieceRole; 8]> = (0..START_POSITION_COUNT).map(back_rank_roles).collect();
+        assert_eq!(all.len(), START_POSITION_COUNT);
+        assert!(random_start_position() < START_POSITION_COUNT);
+    }
+
+    #[test]
+    fn castling_fen_round_trip() {
+        for fen in [
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1",
+            // 同一侧有两个车时，里面的车用列字母表示
+            "rk2r3/8/8/8/8/8/8/1R2KR1R w FQk - 0 1",
+        ] {
+            let board = read_fen(fen.to_string()).unwrap();
+            assert!(board.chess960);
+            assert_eq!(write_fen(board), fen);
+        }
+        let shredder = read_fen("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1".to_string()).unwrap();
+        assert!(shredder == start_position(0));
+        assert!(!read_fen(INITIAL_FEN.to_string()).unwrap().chess960);
+    }
+
+    #[test]
+    fn chess960_perft() {
+        // 数据来自 Chess960 的标准 perft 测试集
+        for (fen, counts) in [
+            ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", &[21, 528, 12189][..]),
+            ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", &[21, 807, 18002]),
+            ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", &[20, 479, 10471]),
+        ] {
+            let board = read_fen(fen.to_string()).unwrap();
+            for (depth, &count) in counts.iter().enumerate() {
+                assert_eq!(perft(&board, depth + 1), count, "{} depth {}", fen, depth + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn castling_moves() {
+        let board = read_fen("4k3/8/8/8/8/8/8/5RKR w HF - 0 1".to_string()).unwrap();
+        // 王走到车上表示易位，f1 的车挡住了王侧易位
+        let castle = Step::new("g1".parse().unwrap(), "f1".parse().unwrap());
+        assert!(!all_move(&board).contains(&Step::new("g1".parse().unwrap(), "h1".parse().unwrap())));
+        assert_eq!(crate::step::write_step(&board, castle).unwrap(), "O-O-O");
+        assert_eq!(crate::step::read_step(&board, "O-O-O".to_string()), Some(castle));
+        assert_eq!(crate::step::read_step(&board, "O-O".to_string()), None);
+        let after = try_move(&board, castle).unwrap();
+        assert_eq!(write_fen(after.clone()), "4k3/8/8/8/8/8/8/2KR3R b - - 1 1");
+        // 王直接走到易位后的位置也按易位处理
+        assert!(try_move(&board, Step::new("g1".parse().unwrap(), "c1".parse().unwrap())) == Some(after));
+    }
+}
@@ -0,0 +1,656 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+use std::time::{Duration, Instant};
+use crate::{
+    board::*, piece::*, see::{piece_value, see}, square::{BoardSize, Square},
+};
+
+// 搜索的最大层数，包括静态搜索
+pub const MAX_PLY: usize = 64;
+// 将死的分数减去到将死为止的层数，这样越快的将死分数越高
+const MATE: i32 = 30000;
+const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+const INFINITY: i32 = MATE + 1;
+// 置换表默认的大小（MB）
+pub const DEFAULT_HASH_MB: usize = 16;
+// 按时间限制搜索时，每搜索这么多个节点检查一次时间
+const TIME_CHECK_NODES: u64 = 1024;
+
+// 搜索的限制：迭代加深到指定深度，或搜索的节点数、时间用完时停止
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SearchLimit {
+    Depth(usize),
+    Nodes(u64),
+    Time(Duration),
+}
+
+// 电脑的棋力：限制搜索的深度和节点数，并在最好的几条变例中随机选一条，但比最佳变例差的分数有上限
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Strength {
+    pub depth: usize,
+    pub nodes: u64,
+    pub candidates: usize,  // 参与随机选择的变例数
+    pub max_loss: i32,      // 选中的变例最多比最佳变例差这么多分
+}
+
+impl Strength {
+    // 从弱到强的等级
+    pub const LEVELS: [Strength; 6] = [
+        Strength { depth: 1, nodes: 1_000, candidates: 4, max_loss: 200 },
+        Strength { depth: 2, nodes: 5_000, candidates: 3, max_loss: 120 },
+        Strength { depth: 3, nodes: 20_000, candidates: 3, max_loss: 60 },
+        Strength { depth: 4, nodes: 100_000, candidates: 2, max_loss: 25 },
+        Strength { depth: 6, nodes: 500_000, candidates: 1, max_loss: 0 },
+        Strength { depth: MAX_PLY, nodes: u64::MAX, candidates: 1, max_loss: 0 },
+    ];
+}
+
+// 局面的分数，从行动方的角度。Cp 以兵为 100，Mate(n) 表示 n 步之内将死对方，负数表示 n 步之内被将死
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Score {
+    Cp(i32),
+    Mate(i32),
+}
+
+impl Score {
+    fn from_value(value: i32) -> Self {
+        if value.abs() < MATE_BOUND {
+            return Score::Cp(value)
+        }
+        let moves = (MATE - value.abs() + 1) / 2;
+        Score::Mate(if value > 0 { moves } else { -moves })
+    }
+
+    // from_value 的逆运算，用于比较分数
+    fn value(self) -> i32 {
+        match self {
+            Score::Cp(cp) => cp,
+            Score::Mate(n) if n > 0 => MATE - 2 * n + 1,
+            Score::Mate(n) => -MATE - 2 * n,
+        }
+    }
+}
+
+// 如 +0.35、#3、#-2
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Score::Cp(cp) => write!(f, "{:+.2}", *cp as f32 / 100.0),
+            Score::Mate(n) => write!(f, "#{}", n),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    pub best: Option<Step>,     // 没有合法着法（棋局已经结束）时为 None
+    pub pv: Vec<Step>,          // 主要变例，从 best 开始
+    pub score: Score,
+    pub depth: usize,           // 完成的迭代深度
+    pub nodes: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct TtEntry {
+    key: u64,
+    depth: usize,
+    value: i32,
+    bound: Bound,
+    best: Option<Step>,
+}
+
+// 迭代加深的 negamax 搜索，使用 alpha-beta 剪枝、静态搜索和置换表。
+// 着法顺序：置换表中的着法、吃子（MVV-LVA）、杀手着法，其余按历史分数排列。
+// 置换表在多次搜索之间保留，其余的表每次搜索时清空。
+// 搜索可以在另一个线程中通过 stop_flag 停止，停止标志不会自动清除，需要由调用方在开始搜索前清除
+pub struct Engine {
+    tt: Vec<Option<TtEntry>>,
+    killers: [[Option<Step>; 2]; MAX_PLY],
+    history: Vec<i32>,      // 按 [颜色][起点][终点] 排列，产生剪枝的不吃子着法的得分
+    pv: Vec<Vec<Step>>,     // pv[ply] 为第 ply 层的主要变例
+    path: Vec<u64>,         // 棋局历史和搜索路径上的局面键值，用于判断重复局面
+    nodes: u64,
+    limits: Vec<SearchLimit>,
+    start: Instant,
+    stopped: bool,
+    stop: Arc<AtomicBool>,
+    excluded: Vec<Step>,    // 多条变例的搜索中根节点不考虑的着法，即已经找到的变例的第一步
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        let mut engine = Engine {
+            tt: Vec::new(),
+            killers: [[None; 2]; MAX_PLY],
+            history: vec![0; 2 * SQUARE_COUNT * SQUARE_COUNT],
+            pv: vec![Vec::new(); MAX_PLY + 1],
+            path: Vec::new(),
+            nodes: 0,
+            limits: Vec::new(),
+            start: Instant::now(),
+            stopped: false,
+            stop: Arc::new(AtomicBool::new(false)),
+            excluded: Vec::new(),
+        };
+        engine.set_hash_size(DEFAULT_HASH_MB);
+        engine
+    }
+
+    // 按 MB 设置置换表的大小，同时清空置换表
+    pub fn set_hash_size(&mut self, mb: usize) {
+        let entries = (mb.max(1) << 20) / std::mem::size_of::<Option<TtEntry>>();
+        self.tt = vec![None; entries];
+    }
+
+    pub fn clear_hash(&mut self) {
+        self.tt.fill(None);
+    }
+
+    // 设置后正在进行的搜索会尽快停止
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    // 按 strength 的棋力选一步棋，最多思考 time。没有合法着法时返回 None
+    pub fn play(&mut self, board: &Board, history: &[Board], strength: Strength, time: Duration) -> Option<Step> {
+        let limits = [SearchLimit::Depth(strength.depth), SearchLimit::Nodes(strength.nodes), SearchLimit::Time(time)];
+        let lines = self.analyze(board, history, &limits, strength.candidates.max(1), |_| {});
+        let best = lines[0].score.value();
+        let candidates: Vec<Step> = lines.iter()
+            .filter(|line| best - line.score.value() <= strength.max_loss)
+            .filter_map(|line| line.best)
+            .collect();
+        if candidates.is_empty() {
+            return None
+        }
+        let seed = RandomState::new().hash_one(board.zobrist);
+        Some(candidates[seed as usize % candidates.len()])
+    }
+
+    // 搜索当前局面的最佳着法，达到任何一个限制时停止。history 为此前的所有盘面，按时间顺序排列，用于判断重复局面
+    pub fn search(&mut self, board: &Board, history: &[Board], limits: &[SearchLimit]) -> SearchResult {
+        self.analyze(board, history, limits, 1, |_| {}).swap_remove(0)
+    }
+
+    // 搜索前 multipv 条最佳变例：第 k 条变例是根节点排除前 k - 1 条变例的第一步之后的最佳变例。
+    // 每完成一层迭代用这一层的所有变例调用一次 report。返回最后完成的一层的所有变例，至少有一条，
+    // 棋局已经结束时只有一条没有着法的变例
+    pub fn analyze(
+        &mut self,
+        board: &Board,
+        history: &[Board],
+        limits: &[SearchLimit],
+        multipv: usize,
+        mut report: impl FnMut(&[SearchResult]),
+    ) -> Vec<SearchResult> {
+        self.killers = [[None; 2]; MAX_PLY];
+        self.history.fill(0);
+        self.path = history.iter().map(|b| b.zobrist).collect();
+        self.nodes = 0;
+        self.limits = limits.to_vec();
+        self.start = Instant::now();
+        self.stopped = false;
+
+        let mut board = board.clone();
+        let moves = all_move(&board);
+        if let Some(value) = terminal_value(&board, &moves, 0) {
+            return vec![SearchResult {
+                best: None,
+                pv: Vec::new(),
+                score: Score::from_value(value),
+                depth: 0,
+                nodes: 0,
+            }]
+        }
+        let multipv = multipv.clamp(1, moves.len());
+        let max_depth = limits.iter()
+            .filter_map(|&limit| match limit {
+                SearchLimit::Depth(depth) => Some(depth),
+                _ => None,
+            })
+            .min()
+            .unwrap_or(MAX_PLY)
+            .clamp(1, MAX_PLY - 1);
+
+        let mut results = Vec::new();
+        for depth in 1..=max_depth {
+            let mut lines = Vec::new();
+            self.excluded.clear();
+            while lines.len() < multipv {
+                let value = self.negamax(&mut board, depth, 0, -INFINITY, INFINITY);
+                let Some(&best) = self.pv[0].first().filter(|_| !self.stopped) else {
+                    break
+                };
+                lines.push(SearchResult {
+                    best: Some(best),
+                    pv: self.pv[0].clone(),
+                    score: Score::from_value(value),
+                    depth,
+                    nodes: self.nodes,
+                });
+                self.excluded.push(best);
+            }
+            // 没有完成的迭代不使用
+            if self.stopped {
+                break
+            }
+            report(&lines);
+            results = lines;
+            // 已经分出胜负时更深的搜索不会改变结果
+            if multipv == 1 && matches!(results[0].score, Score::Mate(_)) {
+                break
+            }
+        }
+        self.excluded.clear();
+
+        // 第一层迭代就被打断时，至少给出一步合法的着法
+        if results.is_empty() {
+            results.push(SearchResult {
+                best: Some(moves[0]),
+                pv: vec![moves[0]],
+                score: Score::Cp(0),
+                depth: 0,
+                nodes: self.nodes,
+            });
+        }
+        results
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped {
+            self.stopped = self.limits.iter().any(|&limit| match limit {
+                SearchLimit::Depth(_) => false,
+                SearchLimit::Nodes(nodes) => self.nodes >= nodes,
+                SearchLimit::Time(time) => self.nodes.is_multiple_of(TIME_CHECK_NODES) && self.start.elapsed() >= time,
+            }) || self.stop.load(Ordering::Relaxed);
+        }
+        self.stopped
+    }
+
+    // 与棋局历史或搜索路径上的局面重复时按和棋处理。吃子、兵的移动之后不可能重复，只需检查最近的 halfmove 个局面
+    fn is_repetition(&self, board: &Board) -> bool {
+        self.path.iter().rev().take(board.halfmove).any(|&key| key == board.zobrist)
+    }
+
+    fn negamax(&mut self, board: &mut Board, depth: usize, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv[ply].clear();
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0
+        }
+        if ply > 0 && self.is_repetition(board) {
+            return 0
+        }
+        let moves = all_move(board);
+        if let Some(value) = terminal_value(board, &moves, ply) {
+            return value
+        }
+        if depth == 0 || ply >= MAX_PLY - 1 {
+            return self.quiesce(board, ply, alpha, beta)
+        }
+
+        let key = board.zobrist;
+        let index = key as usize % self.tt.len();
+        let entry = self.tt[index].filter(|e| e.key == key);
+        if ply > 0 && let Some(e) = entry && e.depth >= depth {
+            let value = value_from_tt(e.value, ply);
+            match e.bound {
+                Bound::Exact => return value,
+                Bound::Lower if value >= beta => return value,
+                Bound::Upper if value <= alpha => return value,
+                _ => {},
+            }
+        }
+
+        let mut moves = moves;
+        if ply == 0 {
+            moves.retain(|step| !self.excluded.contains(step));
+        }
+        self.order_moves(board, &mut moves, ply, entry.and_then(|e| e.best));
+        let alpha_orig = alpha;
+        let mut best_value = -INFINITY;
+        let mut best_move = None;
+        for step in moves {
+            let quiet = !move_flags(board, step).capture && step.promotion.is_none();
+            let mv = board.make_move(step);
+            self.path.push(key);
+            let value = -self.negamax(board, depth - 1, ply + 1, -beta, -alpha);
+            self.path.pop();
+            board.unmake_move(&mv);
+            if self.stopped {
+                return 0
+            }
+            if value > best_value {
+                best_value = value;
+                best_move = Some(step);
+            }
+            if value > alpha {
+                alpha = value;
+                let (head, tail) = self.pv.split_at_mut(ply + 1);
+                head[ply].clear();
+                head[ply].push(step);
+                head[ply].extend_from_slice(&tail[0]);
+            }
+            if alpha >= beta {
+                if quiet {
+                    let killers = &mut self.killers[ply];
+                    if killers[0] != Some(step) {
+                        killers[1] = killers[0];
+                        killers[0] = Some(step);
+                    }
+                    let index = history_index(board.active_color, step);
+                    self.history[index] = (self.history[index] + (depth * depth) as i32).min(KILLER_SCORE - 1);
+                }
+                break
+            }
+        }
+
+        let bound = if best_value <= alpha_orig {
+            Bound::Upper
+        } else if best_value >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        // 排除了部分着法的根节点的结果不保存
+        if ply > 0 || self.excluded.is_empty() {
+            self.tt[index] = Some(TtEntry {
+                key,
+                depth,
+                value: value_to_tt(best_value, ply),
+                bound,
+                best: best_move,
+            });
+        }
+        best_value
+    }
+
+    // 静态搜索：只考虑吃子和升变，直到局面平静。行动方可以选择不吃子，此时按局面评估的分数计算
+    fn quiesce(&mut self, board: &mut Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv[ply].clear();
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0
+        }
+        let moves = all_move(board);
+        if let Some(value) = terminal_value(board, &moves, ply) {
+            return value
+        }
+        // 被将军时不能不走棋，要搜索所有应将的着法
+        let in_check = board.variant.rules().in_check(board, board.active_color);
+        let stand_pat = evaluate(board);
+        if ply >= MAX_PLY - 1 || (!in_check && stand_pat >= beta) {
+            return stand_pat
+        }
+        if !in_check {
+            alpha = alpha.max(stand_pat);
+        }
+
+        // 交换后吃亏的吃子不考虑
+        let mut captures: Vec<Step> = if in_check {
+            moves
+        } else {
+            moves.into_iter()
+                .filter(|&step| {
+                    let flags = move_flags(board, step);
+                    (flags.capture || flags.promotion) && see(board, step) >= 0
+                })
+                .collect()
+        };
+        self.order_moves(board, &mut captures, ply, None);
+        for step in captures {
+            let mv = board.make_move(step);
+            let value = -self.quiesce(board, ply + 1, -beta, -alpha);
+            board.unmake_move(&mv);
+            if self.stopped {
+                return 0
+            }
+            if value >= beta {
+                return value
+            }
+            alpha = alpha.max(value);
+        }
+        alpha
+    }
+
+    fn order_moves(&self, board: &Board, moves: &mut [Step], ply: usize, tt_move: Option<Step>) {
+        moves.sort_by_cached_key(|&step| -self.move_score(board, step, ply, tt_move));
+    }
+
+    fn move_score(&self, board: &Board, step: Step, ply: usize, tt_move: Option<Step>) -> i32 {
+        if tt_move == Some(step) {
+            return TT_MOVE_SCORE
+        }
+        let flags = move_flags(board, step);
+        if flags.capture || flags.promotion {
+            // 先吃价值高的棋子，同样的棋子用价值低的棋子吃
+            let victim = if flags.en_passant {
+                piece_value(PieceRole::Pawn)
+            } else {
+                board.pieces[step.to].map_or(0, |p| piece_value(p.piece_role))
+            };
+            let promotion = step.promotion.map_or(0, piece_value);
+            let attacker = board.pieces[step.from].map_or(0, |p| piece_value(p.piece_role));
+            return CAPTURE_SCORE + (victim + promotion) * 10 - attacker / 100
+        }
+        if self.killers[ply].contains(&Some(step)) {
+            return KILLER_SCORE
+        }
+        self.history[history_index(board.active_color, step)]
+    }
+}
+
+const TT_MOVE_SCORE: i32 = 3_000_000;
+const CAPTURE_SCORE: i32 = 2_000_000;
+const KILLER_SCORE: i32 = 1_000_000;
+
+fn history_index(color: PieceColor, step: Step) -> usize {
+    (color.index() * SQUARE_COUNT + step.from.index()) * SQUARE_COUNT + step.to.index()
+}
+
+// 置换表中的将死分数按到当前节点的距离保存，读取时再换算成到根节点的距离
+fn value_to_tt(value: i32, ply: usize) -> i32 {
+    if value >= MATE_BOUND {
+        value + ply as i32
+    } else if value <= -MATE_BOUND {
+        value - ply as i32
+    } else {
+        value
+    }
+}
+
+fn value_from_tt(value: i32, ply: usize) -> i32 {
+    if value >= MATE_BOUND {
+        value - ply as i32
+    } else if value <= -MATE_BOUND {
+        value + ply as i32
+    } else {
+        value
+    }
+}
+
+// 终局的分数，从行动方的角度。与 end_game 的判断顺序相同，但不重复生成着法，也不判断重复局面
+fn terminal_value(board: &Board, moves: &[Step], ply: usize) -> Option<i32> {
+    let rules = board.variant.rules();
+    let mate = |winner: PieceColor| if winner == board.active_color { MATE - ply as i32 } else { ply as i32 - MATE };
+    if let Some(result) = rules.variant_result(board) {
+        return Some(match result {
+            BoardResult::Winner(color, _) => mate(color),
+            BoardResult::Draw(_) => 0,
+        })
+    }
+    if moves.is_empty() {
+        return Some(if rules.in_check(board, board.active_color) { mate(board.active_color.flip()) } else { 0 })
+    }
+    (rules.insufficient_material(board) || board.halfmove >= 100).then_some(0)
+}
+
+// 局面评估，从行动方的角度：子力（包括手中的棋子）加上位置分，再加上变体的获胜目标分。
+// 子力按标准国际象棋计算，送吃变体中反过来计算
+pub fn evaluate(board: &Board) -> i32 {
+    let rules = board.variant.rules();
+    let sign = |color: PieceColor| if color == board.active_color { 1 } else { -1 };
+    let mut material = 0;
+    for sq in board.size.squares() {
+        if let Some(piece) = board.pieces[sq] {
+            material += sign(piece.piece_color) * (piece_value(piece.piece_role) + position_bonus(piece, sq, board.size));
+        }
+    }
+    for color in PieceColor::ALL {
+        for role in POCKET_ROLES {
+            material += sign(color) * board.pockets[color.index()].count(role) as i32 * piece_value(role);
+        }
+    }
+    let goal: i32 = PieceColor::ALL.into_iter().map(|color| sign(color) * rules.goal_bonus(board, color)).sum();
+    rules.material_sign() * material + goal
+}
+
+// 兵越往前走越有价值，王以外的其他棋子越靠近中心越灵活
+fn position_bonus(piece: Piece, sq: Square, size: BoardSize) -> i32 {
+    let (files, ranks) = (size.files as i32, size.ranks as i32);
+    let (file, rank) = (sq.file() as i32, sq.rank() as i32);
+    match piece.piece_role {
+        PieceRole::Pawn => (rank - size.pawn_rank(piece.piece_color) as i32).abs() * 5,
+        PieceRole::King => 0,
+        _ => {
+            let distance = (2 * file - (files - 1)).abs() + (2 * rank - (ranks - 1)).abs();
+            files + ranks - 2 - distance
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen::read_fen, variant::VariantKind};
+
+    fn search(fen: &str, limit: SearchLimit) -> SearchResult {
+        let board = read_fen(fen.to_string()).unwrap();
+        let result = Engine::new().search(&board, &[], &[limit]);
+        // 主要变例中的每一步都是合法的
+        let mut b = board.clone();
+        for &step in result.pv.iter() {
+            b = try_move(&b, step).unwrap();
+        }
+        result
+    }
+
+    #[test]
+    fn finds_mates_and_material() {
+        let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", SearchLimit::Depth(3));
+        assert_eq!(result.best, Some("a1a8".parse().unwrap()));
+        assert_eq!(result.score, Score::Mate(1));
+        assert_eq!(result.score.to_string(), "#1");
+
+        // 两步杀：王先走到 b6，再用车将死
+        let result = search("k7/8/2K5/8/8/8/8/7R w - - 0 1", SearchLimit::Depth(4));
+        assert_eq!(result.score, Score::Mate(2));
+        assert_eq!(result.pv.len(), 3);
+
+        // 吃掉没有保护的后，而不是被兵保护的车。吃后之前白方少了七个兵的子力
+        let result = search("5k2/8/p7/1r6/3q4/8/4B3/3RK3 w - - 0 1", SearchLimit::Depth(3));
+        assert_eq!(result.best, Some("d1d4".parse().unwrap()));
+        assert!(matches!(result.score, Score::Cp(cp) if cp > 100));
+    }
+
+    #[test]
+    fn finished_games_and_limits() {
+        // 已经被将死和逼和时没有着法
+        let result = search("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", SearchLimit::Depth(2));
+        assert_eq!((result.best, result.score), (None, Score::Mate(0)));
+        let result = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", SearchLimit::Depth(2));
+        assert_eq!((result.best, result.score), (None, Score::Cp(0)));
+
+        let result = search(crate::fen::INITIAL_FEN, SearchLimit::Nodes(500));
+        assert!(result.best.is_some());
+        assert!(result.nodes <= 500);
+        let result = search(crate::fen::INITIAL_FEN, SearchLimit::Time(Duration::from_millis(50)));
+        assert!(result.best.is_some() && result.depth >= 1);
+    }
+
+    #[test]
+    fn strength_levels() {
+        let board = read_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string()).unwrap();
+        let mut engine = Engine::new();
+        let strongest = *Strength::LEVELS.last().unwrap();
+        assert_eq!(engine.play(&board, &[], strongest, Duration::from_secs(5)), Some("a1a8".parse().unwrap()));
+        // 最弱的等级也不会错过一步杀，或者放弃白吃的后
+        let free_queen = read_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1".to_string()).unwrap();
+        for _ in 0..10 {
+            assert_eq!(engine.play(&board, &[], Strength::LEVELS[0], Duration::from_secs(5)), Some("a1a8".parse().unwrap()));
+            assert_eq!(engine.play(&free_queen, &[], Strength::LEVELS[0], Duration::from_secs(5)), Some("d2d5".parse().unwrap()));
+        }
+        let mated = read_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1".to_string()).unwrap();
+        assert_eq!(engine.play(&mated, &[], strongest, Duration::from_secs(5)), None);
+    }
+
+    #[test]
+    fn variant_evaluation() {
+        let eval = |fen: &str, variant: VariantKind| {
+            let mut board = read_fen(fen.to_string()).unwrap();
+            board.set_variant(variant);
+            evaluate(&board)
+        };
+        // 送吃变体中少一个后反而更好
+        let fen = "4k3/8/8/8/8/8/8/3QK3 b - - 0 1";
+        assert!(eval(fen, VariantKind::Standard) < 0);
+        assert!(eval(fen, VariantKind::Antichess) > 0);
+        // 王离中心越近越好
+        let near = eval("4k3/8/8/8/8/3K4/8/8 w - - 0 1", VariantKind::KingOfTheHill);
+        let far = eval("4k3/8/8/8/8/8/8/K7 w - - 0 1", VariantKind::KingOfTheHill);
+        assert!(near > far + 100);
+        // 王的赛跑中王越靠前越好
+        let ahead = eval("8/8/8/8/8/1K6/8/6k1 w - - 0 1", VariantKind::RacingKings);
+        assert!(ahead > 0);
+        // 三将军中已经将军两次的一方占优
+        let board = read_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 1+3 0 1".to_string()).unwrap();
+        assert!(evaluate(&board) > 300);
+    }
+
+    #[test]
+    fn check_evasions_in_quiescence() {
+        // 黑方被将军，静态搜索不能按子力多一个后直接返回
+        let mut board = read_fen("k7/2K5/8/8/8/8/7q/R7 b - - 0 1".to_string()).unwrap();
+        let mut engine = Engine::new();
+        let value = engine.quiesce(&mut board, 0, -MATE, MATE);
+        assert!(value < evaluate(&board));
+    }
+
+    #[test]
+    fn multipv_and_stop() {
+        let board = read_fen("5k2/8/p7/1r6/3q4/8/4B3/3RK3 w - - 0 1".to_string()).unwrap();
+        let mut engine = Engine::new();
+        let mut reported = Vec::new();
+        let lines = engine.analyze(&board, &[], &[SearchLimit::Depth(3)], 3, |lines| reported.push(lines.len()));
+        assert_eq!(reported, vec![3, 3, 3]);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].best, Some("d1d4".parse().unwrap()));
+        // 第一步互不相同，分数从高到低
+        assert!(lines[1].best != lines[0].best && lines[2].best != lines[0].best && lines[2].best != lines[1].best);
+        assert!(lines.windows(2).all(|w| match (w[0].score, w[1].score) {
+            (Score::Cp(a), Score::Cp(b)) => a >= b,
+            _ => true,
+        }));
+
+        // 设置停止标志后立即停止，仍然给出合法的着法
+        engine.stop_flag().store(true, Ordering::Relaxed);
+        let result = engine.search(&board, &[], &[]);
+        assert_eq!(result.depth, 0);
+        assert!(all_move(&board).contains(&result.best.unwrap()));
+    }
+}
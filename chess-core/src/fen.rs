@@ -0,0 +1,630 @@
+use crate::bitboard::{square, Bitboard};
+use crate::piece::*;
+use crate::board::*;
+use crate::square::{BoardSize, Square};
+use crate::variant::{VariantKind, CHECKS_TO_WIN};
+use crate::zobrist;
+use regex::Regex;
+use std::fmt;
+
+pub const INITIAL_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+fn char_to_role(c: char) -> Option<PieceRole> {
+    match c.to_ascii_lowercase() {
+        'k' => Some(PieceRole::King),
+        'q' => Some(PieceRole::Queen),
+        'r' => Some(PieceRole::Rook),
+        'b' => Some(PieceRole::Bishop),
+        'n' => Some(PieceRole::Knight),
+        'p' => Some(PieceRole::Pawn),
+        'a' => Some(PieceRole::Archbishop),
+        'c' => Some(PieceRole::Chancellor),
+        _ => None,
+    }
+}
+
+fn piece_to_char(piece: Option<Piece>) -> char {
+    if let Some(p) = piece {
+        let c = match p.piece_role {
+            PieceRole::Pawn => 'p',
+            PieceRole::Rook => 'r',
+            PieceRole::Knight => 'n',
+            PieceRole::Bishop => 'b',
+            PieceRole::Queen => 'q',
+            PieceRole::King => 'k',
+            PieceRole::Archbishop => 'a',
+            PieceRole::Chancellor => 'c',
+        };
+        match p.piece_color {
+            PieceColor::White => c.to_ascii_uppercase(),
+            PieceColor::Black => c,
+        }
+    } else {
+        '1'
+    }
+}
+
+// FEN 的六个字段
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FenField {
+    PiecePlacement,
+    ActiveColor,
+    Castling,
+    EnPassant,
+    Checks,
+    Halfmove,
+    Fullmove,
+}
+
+impl fmt::Display for FenField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            FenField::PiecePlacement => "piece placement",
+            FenField::ActiveColor => "active color",
+            FenField::Castling => "castling availability",
+            FenField::EnPassant => "en passant target",
+            FenField::Checks => "remaining checks",
+            FenField::Halfmove => "halfmove clock",
+            FenField::Fullmove => "fullmove number",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FenErrorKind {
+    Empty,
+    UnexpectedChar(char),
+    DuplicateChar(char),
+    RankTooLong,
+    RankTooShort,
+    TooManyRanks,
+    TooFewRanks,
+    InvalidSquare,
+    InvalidNumber,
+    TooManyFields,
+    UnclosedPocket,
+}
+
+impl fmt::Display for FenErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenErrorKind::Empty => write!(f, "empty FEN"),
+            FenErrorKind::UnexpectedChar(c) => write!(f, "unexpected character '{}'", c),
+            FenErrorKind::DuplicateChar(c) => write!(f, "duplicate character '{}'", c),
+            FenErrorKind::RankTooLong => write!(f, "rank has too many squares"),
+            FenErrorKind::RankTooShort => write!(f, "rank has too few squares"),
+            FenErrorKind::TooManyRanks => write!(f, "too many ranks"),
+            FenErrorKind::TooFewRanks => write!(f, "too few ranks"),
+            FenErrorKind::InvalidSquare => write!(f, "invalid square"),
+            FenErrorKind::InvalidNumber => write!(f, "invalid number"),
+            FenErrorKind::TooManyFields => write!(f, "too many fields"),
+            FenErrorKind::UnclosedPocket => write!(f, "missing ']' after pocket"),
+        }
+    }
+}
+
+// FEN 解析错误。position 是出错字符在整个 FEN 字符串中的下标（从 0 开始，按字符计）
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FenError {
+    pub field: FenField,
+    pub position: usize,
+    pub kind: FenErrorKind,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in {} at column {}", self.kind, self.field, self.position + 1)
+    }
+}
+
+impl std::error::Error for FenError {}
+
+// 按空白切分字段，同时记录每个字段起始字符的下标
+fn split_fields(fen: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut start: Option<(usize, usize)> = None;
+    for (pos, (byte, c)) in fen.char_indices().enumerate() {
+        if c.is_whitespace() {
+            if let Some((p, b)) = start.take() {
+                fields.push((p, &fen[b..byte]));
+            }
+        } else if start.is_none() {
+            start = Some((pos, byte));
+        }
+    }
+    if let Some((p, b)) = start {
+        fields.push((p, &fen[b..]));
+    }
+    fields
+}
+
+// 棋子位置字段的内容。疯狂屋中棋子后面的 ~ 表示升变来的棋子，末尾的 [...] 为双方手中的棋子
+struct Placement {
+    size: BoardSize,
+    pieces: Pieces,
+    promoted: Bitboard,
+    pockets: Option<[Pocket; 2]>,
+}
+
+fn parse_pocket(start: usize, s: &str) -> Result<[Pocket; 2], FenError> {
+    let mut pockets = [Pocket::default(); 2];
+    for (offset, c) in s.chars().enumerate() {
+        match char_to_role(c) {
+            Some(role) if role != PieceRole::King => {
+                let color = if c.is_ascii_lowercase() { PieceColor::Black } else { PieceColor::White };
+                pockets[color.index()].add(role);
+            },
+            _ => return Err(FenError { field: FenField::PiecePlacement, position: start + offset, kind: FenErrorKind::UnexpectedChar(c) }),
+        }
+    }
+    Ok(pockets)
+}
+
+fn parse_piece_placement(start: usize, placement: &str) -> Result<Placement, FenError> {
+    let error = |position, kind| FenError { field: FenField::PiecePlacement, position, kind };
+
+    // 先分出手中的棋子
+    let (placement, pockets) = match placement.find('[') {
+        Some(bracket) => {
+            let pocket_start = start + placement[..bracket].chars().count() + 1;
+            let rest = &placement[bracket + 1..];
+            let Some(close) = rest.find(']') else {
+                return Err(error(start + placement.chars().count(), FenErrorKind::UnclosedPocket));
+            };
+            if let Some(c) = rest[close + 1..].chars().next() {
+                let position = pocket_start + rest[..close].chars().count() + 1;
+                return Err(error(position, FenErrorKind::UnexpectedChar(c)));
+            }
+            (&placement[..bracket], Some(parse_pocket(pocket_start, &rest[..close])?))
+        },
+        None => (placement, None),
+    };
+
+    // 棋盘尺寸由行数和第一行的宽度决定，不是支持的尺寸时按 8×8 报告错误
+    let ranks = placement.split('/').count();
+    let files = rank_width(placement.split('/').next().unwrap_or(""));
+    let size = BoardSize::SUPPORTED.into_iter()
+        .find(|size| size.files == files && size.ranks == ranks)
+        .unwrap_or(BoardSize::STANDARD);
+
+    let mut pieces = Pieces::new();
+    let mut promoted = Bitboard::EMPTY;
+    let mut row = size.ranks - 1;
+    let mut col = 0;
+    let mut number: Option<usize> = None; // 正在读取的空格数，可能有多位
+    let mut last_piece: Option<Square> = None;
+
+    for (offset, c) in placement.chars().enumerate() {
+        let position = start + offset;
+        if !c.is_ascii_digit() {
+            number = None;
+        }
+        match c {
+            '/' => {
+                if col < size.files {
+                    return Err(error(position, FenErrorKind::RankTooShort));
+                }
+                if row == 0 {
+                    return Err(error(position, FenErrorKind::TooManyRanks));
+                }
+                row -= 1;
+                col = 0;
+                last_piece = None;
+            }
+            '0'..='9' => {
+                let digit = c.to_digit(10).unwrap() as usize;
+                if number.is_none() && digit == 0 {
+                    return Err(error(position, FenErrorKind::UnexpectedChar(c)));
+                }
+                let previous = number.unwrap_or(0);
+                let n = previous * 10 + digit;
+                col = col - previous + n;
+                number = Some(n);
+                if col > size.files {
+                    return Err(error(position, FenErrorKind::RankTooLong));
+                }
+                last_piece = None;
+            }
+            '~' => {
+                let Some(sq) = last_piece.take() else {
+                    return Err(error(position, FenErrorKind::UnexpectedChar(c)));
+                };
+                promoted.set(sq.index());
+            }
+            c => {
+                let Some(role) = char_to_role(c) else {
+                    return Err(error(position, FenErrorKind::UnexpectedChar(c)));
+                };
+                if col >= size.files {
+                    return Err(error(position, FenErrorKind::RankTooLong));
+                }
+                pieces[col][row] = Some(Piece {
+                    piece_role: role,
+                    piece_color: if c.is_ascii_lowercase() { PieceColor::Black } else { PieceColor::White },
+                });
+                last_piece = Some(Square::new(col, row));
+                col += 1;
+            }
+        }
+    }
+
+    // 字段结束时最后一行必须填满，并且行数与棋盘尺寸相同
+    let end = start + placement.chars().count();
+    if col < size.files {
+        return Err(error(end, FenErrorKind::RankTooShort));
+    }
+    if row > 0 {
+        return Err(error(end, FenErrorKind::TooFewRanks));
+    }
+    Ok(Placement { size, pieces, promoted, pockets })
+}
+
+// 一行的宽度，连续的数字作为一个数
+fn rank_width(rank: &str) -> usize {
+    let mut width = 0;
+    let mut number = 0;
+    for c in rank.chars() {
+        match c.to_digit(10) {
+            Some(d) => number = number * 10 + d as usize,
+            None => {
+                width += number + usize::from(c != '~');
+                number = 0;
+            },
+        }
+    }
+    width + number
+}
+
+fn parse_active_color(start: usize, s: &str) -> Result<PieceColor, FenError> {
+    let error = |offset, c| FenError { field: FenField::ActiveColor, position: start + offset, kind: FenErrorKind::UnexpectedChar(c) };
+    let mut chars = s.chars();
+    let color = match chars.next() {
+        Some('w') => PieceColor::White,
+        Some('b') => PieceColor::Black,
+        Some(c) => return Err(error(0, c)),
+        None => unreachable!(),
+    };
+    if let Some(c) = chars.next() {
+        return Err(error(1, c));
+    }
+    Ok(color)
+}
+
+// 底线上王所在的列
+fn king_file(pieces: &Pieces, size: BoardSize, color: PieceColor) -> Option<usize> {
+    let y = size.back_rank(color);
+    (0..size.files).find(|&x| pieces[x][y] == Some(Piece { piece_role: PieceRole::King, piece_color: color }))
+}
+
+// 底线上王某一侧最外面的车所在的列，即 X-FEN 中 KQkq 所指的车
+fn outermost_rook(pieces: &Pieces, size: BoardSize, color: PieceColor, side: CastlingSide) -> Option<usize> {
+    let y = size.back_rank(color);
+    let king = king_file(pieces, size, color)?;
+    let is_rook = |x: usize| pieces[x][y] == Some(Piece { piece_role: PieceRole::Rook, piece_color: color });
+    match side {
+        CastlingSide::KingSide => (king + 1..size.files).rev().find(|&x| is_rook(x)),
+        CastlingSide::QueenSide => (0..king).find(|&x| is_rook(x)),
+    }
+}
+
+// 同时支持 KQkq、X-FEN 和 Shredder-FEN。KQkq 指王那一侧最外面的车，找不到时取棋盘两个角上的列；
+// A-H、a-h（10 列的棋盘上到 J、j）直接给出车所在的列，方向由车在王的哪一侧决定。返回的第二项表示是否出现了列字母
+fn parse_castling(start: usize, s: &str, pieces: &Pieces, size: BoardSize) -> Result<(CastlingRights, bool), FenError> {
+    let mut rights = CastlingRights::NONE;
+    if s == "-" {
+        return Ok((rights, false));
+    }
+    let mut file_letters = false;
+    for (offset, c) in s.chars().enumerate() {
+        let color = if c.is_ascii_uppercase() { PieceColor::White } else { PieceColor::Black };
+        let (side, rook_file) = match c.to_ascii_lowercase() {
+            'k' => (CastlingSide::KingSide, outermost_rook(pieces, size, color, CastlingSide::KingSide).unwrap_or(size.files - 1)),
+            'q' => (CastlingSide::QueenSide, outermost_rook(pieces, size, color, CastlingSide::QueenSide).unwrap_or(0)),
+            f if f.is_ascii_lowercase() && (f as usize - 'a' as usize) < size.files => {
+                file_letters = true;
+                let file = f as usize - 'a' as usize;
+                let king = king_file(pieces, size, color).unwrap_or(size.files / 2);
+                let side = if file > king { CastlingSide::KingSide } else { CastlingSide::QueenSide };
+                (side, file)
+            },
+            _ => return Err(FenError { field: FenField::Castling, position: start + offset, kind: FenErrorKind::UnexpectedChar(c) }),
+        };
+        if rights.has(color, side) {
+            return Err(FenError { field: FenField::Castling, position: start + offset, kind: FenErrorKind::DuplicateChar(c) });
+        }
+        rights.set(color, side, Some(rook_file));
+    }
+    Ok((rights, file_letters))
+}
+
+// 易位权是否只能按 Chess960 的规则理解：王不在中间（8 列的棋盘上为 e 列）或车不在角上
+fn is_chess960_castling(pieces: &Pieces, size: BoardSize, rights: CastlingRights) -> bool {
+    rights.iter().any(|(color, side, file)| {
+        let corner = match side {
+            CastlingSide::KingSide => size.files - 1,
+            CastlingSide::QueenSide => 0,
+        };
+        file != corner || king_file(pieces, size, color) != Some(size.files / 2)
+    })
+}
+
+fn parse_en_passant(start: usize, s: &str, size: BoardSize) -> Result<Option<Square>, FenError> {
+    if s == "-" {
+        return Ok(None);
+    }
+    let error = |offset, kind| FenError { field: FenField::EnPassant, position: start + offset, kind };
+    let chars: Vec<char> = s.chars().collect();
+    let file = match chars[0] {
+        c if c.is_ascii_lowercase() && (c as usize - 'a' as usize) < size.files => c as usize - 'a' as usize,
+        _ => return Err(error(0, FenErrorKind::InvalidSquare)),
+    };
+    // 行号可能有两位
+    let digits = chars[1..].iter().take_while(|c| c.is_ascii_digit()).count().min(2);
+    let rank = match chars[1..1 + digits].iter().collect::<String>().parse::<usize>() {
+        Ok(n) if (1..=size.ranks).contains(&n) && chars[1] != '0' => n - 1,
+        _ => return Err(error(1, FenErrorKind::InvalidSquare)),
+    };
+    if let Some(&c) = chars.get(1 + digits) {
+        return Err(error(1 + digits, FenErrorKind::UnexpectedChar(c)));
+    }
+    Ok(Some(Square::new(file, rank)))
+}
+
+// 三将军变体中双方剩余的将军次数，如 3+3，返回双方已经将军的次数
+fn parse_checks(start: usize, s: &str) -> Result<[u8; 2], FenError> {
+    let error = |offset| FenError { field: FenField::Checks, position: start + offset, kind: FenErrorKind::InvalidNumber };
+    let chars: Vec<char> = s.chars().collect();
+    if chars.len() != 3 || chars[1] != '+' {
+        return Err(error(0));
+    }
+    let mut checks = [0; 2];
+    for (color, offset) in [(0, 0), (1, 2)] {
+        match chars[offset].to_digit(10) {
+            Some(d) if d <= CHECKS_TO_WIN as u32 => checks[color] = CHECKS_TO_WIN - d as u8,
+            _ => return Err(error(offset)),
+        }
+    }
+    Ok(checks)
+}
+
+fn parse_number(field: FenField, start: usize, s: &str) -> Result<usize, FenError> {
+    s.parse().map_err(|_| {
+        // 指向第一个不是数字的字符；全是数字时说明数值溢出，指向字段开头
+        let offset = s.chars().position(|c| !c.is_ascii_digit()).unwrap_or(0);
+        FenError { field, position: start + offset, kind: FenErrorKind::InvalidNumber }
+    })
+}
+
+// 解析 FEN。只有棋子位置是必需的，缺少的后续字段取默认值 "w KQkq - 0 1"。
+// 吃过路兵格之后可以有三将军变体的剩余将军次数，此时盘面的变体为三将军
+pub fn read_fen(fen: String) -> Result<Board, FenError> {
+    let mut fields = split_fields(&fen);
+    let checks = match fields.get(4) {
+        Some(&(start, s)) if s.contains('+') => {
+            fields.remove(4);
+            Some(parse_checks(start, s)?)
+        },
+        _ => None,
+    };
+
+    if let Some(&(position, _)) = fields.get(6) {
+        return Err(FenError { field: FenField::Fullmove, position, kind: FenErrorKind::TooManyFields });
+    }
+
+    // 1. 解析棋子位置
+    let Some(&(start, piece_placement)) = fields.first() else {
+        return Err(FenError { field: FenField::PiecePlacement, position: 0, kind: FenErrorKind::Empty });
+    };
+    let Placement { size, pieces, promoted, pockets } = parse_piece_placement(start, piece_placement)?;
+
+    // 2. 解析当前行棋方
+    let active_color = match fields.get(1) {
+        Some(&(start, s)) => parse_active_color(start, s)?,
+        None => PieceColor::White,
+    };
+
+    // 3. 解析易位权限。使用了列字母或者王和车不在标准位置时按 Chess960 处理
+    let (castling_availability, file_letters) = match fields.get(2) {
+        Some(&(start, s)) => parse_castling(start, s, &pieces, size)?,
+        None => (CastlingRights::standard(size.files), false),
+    };
+    let chess960 = file_letters || is_chess960_castling(&pieces, size, castling_availability);
+
+    // 4. 解析吃过路兵格
+    let en_passant_target = match fields.get(3) {
+        Some(&(start, s)) => parse_en_passant(start, s, size)?,
+        None => None,
+    };
+
+    // 5. 解析半回合计数和总回合数
+    let halfmove = match fields.get(4) {
+        Some(&(start, s)) => parse_number(FenField::Halfmove, start, s)?,
+        None => 0,
+    };
+    let fullmove = match fields.get(5) {
+        Some(&(start, s)) => parse_number(FenField::Fullmove, start, s)?,
+        None => 1,
+    };
+
+    let mut board = Board {
+        pieces,
+        active_color,
+        castling_availability,
+        en_passant_target,
+        halfmove,
+        fullmove,
+        size,
+        chess960,
+        variant: if pockets.is_some() {
+            VariantKind::Crazyhouse
+        } else if checks.is_some() {
+            VariantKind::ThreeCheck
+        } else {
+            VariantKind::Standard
+        },
+        checks: checks.unwrap_or_default(),
+        pockets: pockets.unwrap_or_default(),
+        promoted,
+        zobrist: 0,
+    };
+    board.zobrist = zobrist::compute(&board);
+    Ok(board)
+}
+
+// 生成 X-FEN：易位权指向王那一侧最外面的车时写作 KQkq，否则写出车所在的列。标准局面与普通的 FEN 相同
+pub fn write_fen(board: Board) -> String {
+    write_fen_with(board, false)
+}
+
+// 生成 Shredder-FEN：易位权总是写出车所在的列，如 HAha
+pub fn write_shredder_fen(board: Board) -> String {
+    write_fen_with(board, true)
+}
+
+fn write_fen_with(board: Board, shredder: bool) -> String {
+    // 1. 生成棋子位置部分，疯狂屋中标出升变来的棋子
+    let pockets = board.variant.rules().has_pockets();
+    let piece_placement = (0..board.size.ranks).rev().map(|j| {
+        (0..board.size.files).map(|i| {
+            let promoted = pockets && board.pieces[i][j].is_some() && board.promoted.contains(square(i, j));
+            let c = piece_to_char(board.pieces[i][j]);
+            if promoted { format!("{}~", c) } else { c.to_string() }
+        })
+        .collect::<String>()
+    })
+    .collect::<Vec<_>>()
+    .join("/");
+
+    // 压缩连续空格（如 "1111" -> "4"）
+    let re = Regex::new("1{2,}").unwrap();
+    let mut piece_placement = re.replace_all(&piece_placement, |caps: &regex::Captures| {
+        caps[0].len().to_string()
+    }).to_string();
+
+    // 疯狂屋中双方手中的棋子，白方在前
+    if pockets {
+        piece_placement.push('[');
+        for color in PieceColor::ALL {
+            for role in POCKET_ROLES {
+                let c = piece_to_char(Some(Piece { piece_role: role, piece_color: color }));
+                for _ in 0..board.pockets[color.index()].count(role) {
+                    piece_placement.push(c);
+                }
+            }
+        }
+        piece_placement.push(']');
+    }
+
+    // 2. 当前行棋方
+    let active_color = match board.active_color {
+        PieceColor::White => "w",
+        PieceColor::Black => "b",
+    };
+
+    // 3. 易位权限
+    let castling = board.castling_availability.iter().map(|(color, side, file)| {
+        let (c, corner) = match side {
+            CastlingSide::KingSide => ('k', board.size.files - 1),
+            CastlingSide::QueenSide => ('q', 0),
+        };
+        let c = if !shredder && outermost_rook(&board.pieces, board.size, color, side).unwrap_or(corner) == file {
+            c
+        } else {
+            (b'a' + file as u8) as char
+        };
+        match color {
+            PieceColor::White => c.to_ascii_uppercase(),
+            PieceColor::Black => c,
+        }
+    }).collect::<String>();
+    let castling = if castling.is_empty() { "-".to_string() } else { castling };
+
+    // 4. 吃过路兵格
+    let en_passant = match board.en_passant_target {
+        Some(sq) => sq.to_string(),
+        None => "-".to_string(),
+    };
+
+    // 三将军变体中双方剩余的将军次数
+    let checks = if board.variant.rules().counts_checks() {
+        format!(" {}+{}", CHECKS_TO_WIN - board.checks[0], CHECKS_TO_WIN - board.checks[1])
+    } else {
+        String::new()
+    };
+
+    // 5. 半回合计数和总回合数
+    let halfmove = board.halfmove.to_string();
+    let fullmove = board.fullmove.to_string();
+
+    // 组合所有部分
+    format!(
+        "{} {} {} {}{} {} {}",
+        piece_placement, active_color, castling, en_passant, checks, halfmove, fullmove
+    )
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(fen: &str) -> FenError {
+        match read_fen(fen.to_string()) {
+            Ok(_) => panic!("{} should be rejected", fen),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for fen in [
+            INITIAL_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40",
+            "rnabqkbcnr/pppppppppp/10/10/4P5/10/PPPP1PPPPP/RNABQKBCNR b KQkq e3 0 1",
+            "r8r/1nbqkcabn1/ppppp1pppp/10/5p4/10/10/PPPPPPPPPP/1NBQKCABN1/R8R w - f7 0 2",
+        ] {
+            assert_eq!(write_fen(read_fen(fen.to_string()).unwrap()), fen);
+        }
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let board = read_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR".to_string()).unwrap();
+        assert_eq!(write_fen(board), INITIAL_FEN);
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_character() {
+        let cases = [
+            ("", FenField::PiecePlacement, 0, FenErrorKind::Empty),
+            ("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenField::PiecePlacement, 13, FenErrorKind::UnexpectedChar('x')),
+            ("rnbqkbnrr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenField::PiecePlacement, 8, FenErrorKind::RankTooLong),
+            ("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenField::PiecePlacement, 18, FenErrorKind::RankTooLong),
+            ("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenField::PiecePlacement, 16, FenErrorKind::RankTooShort),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8 w KQkq - 0 1", FenField::PiecePlacement, 43, FenErrorKind::TooManyRanks),
+            ("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenField::PiecePlacement, 41, FenErrorKind::TooFewRanks),
+            ("8/8/8/8/8/8/8/8 x - - 0 1", FenField::ActiveColor, 16, FenErrorKind::UnexpectedChar('x')),
+            ("8/8/8/8/8/8/8/8 wb - - 0 1", FenField::ActiveColor, 17, FenErrorKind::UnexpectedChar('b')),
+            ("8/8/8/8/8/8/8/8 w KQxq - 0 1", FenField::Castling, 20, FenErrorKind::UnexpectedChar('x')),
+            ("8/8/8/8/8/8/8/8 w KK - 0 1", FenField::Castling, 19, FenErrorKind::DuplicateChar('K')),
+            ("8/8/8/8/8/8/8/8 w - i3 0 1", FenField::EnPassant, 20, FenErrorKind::InvalidSquare),
+            ("8/8/8/8/8/8/8/8 w - e9 0 1", FenField::EnPassant, 21, FenErrorKind::InvalidSquare),
+            ("8/8/8/8/8/8/8/8 w - e 0 1", FenField::EnPassant, 21, FenErrorKind::InvalidSquare),
+            ("8/8/8/8/8/8/8/8 w - e3x 0 1", FenField::EnPassant, 22, FenErrorKind::UnexpectedChar('x')),
+            ("8/8/8/8/8/8/8/8 w - - 1a 1", FenField::Halfmove, 23, FenErrorKind::InvalidNumber),
+            ("8/8/8/8/8/8/8/8 w - - 0 -1", FenField::Fullmove, 24, FenErrorKind::InvalidNumber),
+            ("8/8/8/8/8/8/8/8 w - - 0 1 x", FenField::Fullmove, 26, FenErrorKind::TooManyFields),
+            ("8/8/8/8/8/8/8/8 w - - 4+3 0 1", FenField::Checks, 22, FenErrorKind::InvalidNumber),
+            ("8/8/8/8/8/8/8/~7 w - - 0 1", FenField::PiecePlacement, 14, FenErrorKind::UnexpectedChar('~')),
+            ("8/8/8/8/8/8/8/8[Qx] w - - 0 1", FenField::PiecePlacement, 17, FenErrorKind::UnexpectedChar('x')),
+            ("8/8/8/8/8/8/8/8[Q w - - 0 1", FenField::PiecePlacement, 17, FenErrorKind::UnclosedPocket),
+            ("8/8/8/8/8/8/8/8[Q]x w - - 0 1", FenField::PiecePlacement, 18, FenErrorKind::UnexpectedChar('x')),
+            ("10/10/10/10/10/10/10/10 w - j9 0 1", FenField::EnPassant, 29, FenErrorKind::InvalidSquare),
+            ("8/8/8/8/8/8/8/08 w - - 0 1", FenField::PiecePlacement, 14, FenErrorKind::UnexpectedChar('0')),
+        ];
+        for (fen, field, position, kind) in cases {
+            assert_eq!(error(fen), FenError { field, position, kind }, "{}", fen);
+        }
+    }
+}
@@ -0,0 +1,587 @@
+use std::fmt;
+use crate::{
+    board::*, fen::{read_fen, write_fen, FenError}, pgn::{parse_pgn, parse_pgn_tags}, piece::PieceColor, step::{read_step, write_step},
+    validate::{check_position, describe_issues, PositionIssue, ValidationMode}, variant::VariantKind,
+};
+
+#[derive(Clone)]
+pub struct MoveData {
+    pub ply: usize,     // 步数编号(1,2,3...)
+    pub san: String,    // 标准代数记法
+    pub color: PieceColor,  // 行动方的颜色
+}
+
+#[derive(Clone)]
+struct GameTreeNode {
+    board: Board,
+    sons: Vec<(Step, usize, MoveData)>, // 默认第一个是主分支
+    parent: Option<usize>,
+    result: Option<BoardResult>,        // 协议和棋等在这个局面上给出的、盘面之外的结果
+}
+
+impl GameTreeNode {
+    fn new(board: Board) -> Self {
+        GameTreeNode {
+            board,
+            sons: Vec::new(),
+            parent: None,
+            result: None,
+        }
+    }
+}
+
+// 读取棋局树文本时的错误，行号从 1 开始
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameTreeError {
+    MissingHeader,
+    InvalidVariant(String),
+    InvalidFen(FenError),
+    InvalidPosition(Vec<PositionIssue>),
+    InvalidNodeCount(String),
+    MalformedLine(usize),
+    InvalidNode { line: usize, node: String },
+    IllegalMove { line: usize, san: String },
+}
+
+impl fmt::Display for GameTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameTreeError::MissingHeader => write!(f, "missing \"[chess game tree]\" header"),
+            GameTreeError::InvalidVariant(s) => write!(f, "unknown variant \"{}\"", s),
+            GameTreeError::InvalidFen(e) => write!(f, "invalid initial FEN: {}", e),
+            GameTreeError::InvalidPosition(issues) => write!(f, "illegal initial position: {}", describe_issues(issues)),
+            GameTreeError::InvalidNodeCount(s) => write!(f, "invalid node count \"{}\"", s),
+            GameTreeError::MalformedLine(line) => write!(f, "malformed move list on line {}", line),
+            GameTreeError::InvalidNode { line, node } => write!(f, "invalid node \"{}\" on line {}", node, line),
+            GameTreeError::IllegalMove { line, san } => write!(f, "illegal move \"{}\" on line {}", san, line),
+        }
+    }
+}
+
+impl std::error::Error for GameTreeError {}
+
+// PGN 中 Chess960 棋局的 Variant 标签。Chess960 不是单独的变体，只是 Board::chess960 为 true 的标准国际象棋
+const CHESS960_TAG: &str = "Chess960";
+
+#[derive(Default)]
+pub struct GameTree {
+    nodes: Vec<GameTreeNode>,
+    root: usize,
+    focus: usize,
+}
+
+impl GameTree {
+    pub fn new(board: Board) -> Self {
+        GameTree {
+            nodes: vec![GameTreeNode::new(board)],
+            root: 0,
+            focus: 0,
+        }
+    }
+
+    // 读取 PGN 的着法。有 Variant 标签时从该变体的初始局面开始，无法识别的变体按标准国际象棋处理，
+    // Variant 为 Chess960 时按 Chess960 的规则处理易位。
+    // 有 FEN 标签时从 FEN 给出的局面开始（如让子棋），和 from_string 一样按 mode 检查这个局面
+    pub fn from_pgn(pgn: String, mode: ValidationMode) -> Result<Self, GameTreeError> {
+        let tags = parse_pgn_tags(&pgn);
+        let tag = |tag: &str| tags.iter().find(|(name, _)| name == tag).map(|(_, value)| value.clone());
+        let chess960 = tag("Variant").as_deref() == Some(CHESS960_TAG);
+        let variant: VariantKind = tag("Variant")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default();
+        let mut board = match tag("FEN") {
+            Some(fen) => {
+                let mut board = read_fen(fen).map_err(GameTreeError::InvalidFen)?;
+                if variant != VariantKind::Standard {
+                    board.set_variant(variant);
+                }
+                board.chess960 |= chess960;
+                check_position(board, mode).map_err(GameTreeError::InvalidPosition)?
+            }
+            None => {
+                let mut board = variant.rules().start_position();
+                board.chess960 |= chess960;
+                board
+            }
+        };
+        let mut tree = Self::new(board.clone());
+
+        let steps = parse_pgn(&pgn);
+
+        for step in steps.iter() {
+            if let Some(s) = read_step(&board, step.clone()) && tree.try_move(s) {
+                board = tree.board();
+            }
+        }
+
+        Ok(tree)
+    }
+
+    pub fn pgn(&self, mut current: usize) -> String {
+        let mut path: Vec<usize> = Vec::new();
+        let mut sans: Vec<String> = Vec::new();
+
+        while current != self.root {
+            if let Some(parent) = self.nodes[current].parent {
+                path.push(current);
+                current = parent;
+            } else {
+                unreachable!()
+            }
+        }
+        path.reverse();
+
+        current = self.root;
+        for node in path {
+            for (_step, son, move_data) in self.nodes[current].sons.iter() {
+                if node == *son {
+                    match move_data.color {
+                        PieceColor::White => sans.push(format!("{}.{}", move_data.ply, move_data.san)),
+                        // 从黑方开始的棋局，第一步写作 "1...e5"
+                        PieceColor::Black if sans.is_empty() => sans.push(format!("{}...{}", move_data.ply, move_data.san)),
+                        PieceColor::Black => sans.push(move_data.san.clone()),
+                    }
+                    current = *son;
+                    break
+                }
+            }
+        }
+        let movetext = sans.join(" ");
+        // 非标准变体和 Chess960 记录 Variant 标签，不从变体的初始局面开始时（如让子棋、Chess960）记录 SetUp 和 FEN 标签
+        let mut tags = Vec::new();
+        let variant = self.variant();
+        let root = &self.nodes[self.root].board;
+        if variant != VariantKind::Standard {
+            tags.push(format!("[Variant \"{}\"]", variant));
+        } else if root.chess960 {
+            tags.push(format!("[Variant \"{}\"]", CHESS960_TAG));
+        }
+        if *root != variant.rules().start_position() || root.chess960 {
+            tags.push("[SetUp \"1\"]".to_string());
+            tags.push(format!("[FEN \"{}\"]", write_fen(root.clone())));
+        }
+        if tags.is_empty() {
+            movetext
+        } else {
+            format!("{}\n\n{}", tags.join("\n"), movetext)
+        }
+    }
+
+    // 棋局使用的变体，即初始局面的变体
+    pub fn variant(&self) -> VariantKind {
+        self.nodes[self.root].board.variant
+    }
+
+    pub fn from_string(s: String, mode: ValidationMode) -> Result<Self, GameTreeError> {
+        let mut lines: Vec<&str> = s.trim().lines().collect();
+    
+        if lines.len() < 3 || lines[0].trim() != "[chess game tree]" {
+            return Err(GameTreeError::MissingHeader)
+        }
+
+        // 标题行之后可以有一行 [Variant "..."]，没有时为标准国际象棋。removed_lines 用于计算错误信息中的行号
+        let (variant, removed_lines) = match parse_pgn_tags(lines[1]).first() {
+            Some((name, value)) if name == "Variant" => {
+                let variant = value.parse().map_err(|_| GameTreeError::InvalidVariant(value.clone()))?;
+                lines.remove(1);
+                (variant, 1)
+            },
+            _ => (VariantKind::Standard, 0),
+        };
+        if lines.len() < 3 {
+            return Err(GameTreeError::MissingHeader)
+        }
+
+        let mut initial = read_fen(lines[1].to_string()).map_err(GameTreeError::InvalidFen)?;
+        if variant != VariantKind::Standard {
+            initial.set_variant(variant);
+        }
+        let initial = check_position(initial, mode).map_err(GameTreeError::InvalidPosition)?;
+        let nodes_count: usize = match lines[2].trim().parse() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(GameTreeError::InvalidNodeCount(lines[2].to_string())),
+        };
+        let info_lines = &lines[3..];
+
+        let mut tree = GameTree {
+            nodes: vec![GameTreeNode::new(initial); nodes_count],
+            root: 0,
+            focus: 0,
+        };
+
+        for (node_id, line) in info_lines.iter().enumerate() {
+            if node_id >= nodes_count {
+                break 
+            }
+            // 行号从 1 开始，前三行是标题、FEN 和节点数
+            let line_number = node_id + 4 + removed_lines;
+
+            for move_info in line.split('|').filter(|m| !m.trim().is_empty()) {
+                // 解析 (son_id, san)
+                let parts: Vec<&str> = move_info.trim()
+                    .strip_prefix('(')
+                    .and_then(|s| s.strip_suffix(')'))
+                    .map(|inner| inner.splitn(2, ", ").collect())
+                    .unwrap_or_default();
+                if parts.len() != 2 {
+                    return Err(GameTreeError::MalformedLine(line_number));
+                }
+                // 子节点的编号总是大于父节点，且每个节点只能有一个父节点，这样就不会出现环
+                let son_id = match parts[0].parse::<usize>() {
+                    Ok(id) if id > node_id && id < nodes_count && tree.nodes[id].parent.is_none() => id,
+                    _ => return Err(GameTreeError::InvalidNode { line: line_number, node: parts[0].to_string() }),
+                };
+                let san = parts[1].to_string();
+                let illegal = || GameTreeError::IllegalMove { line: line_number, san: san.clone() };
+                let step = read_step(&tree.nodes[node_id].board, san.clone()).ok_or_else(illegal)?;
+                let board = try_move(&tree.nodes[node_id].board, step).ok_or_else(illegal)?;
+                tree.nodes[son_id].board = board;
+                tree.nodes[son_id].parent = Some(node_id);
+                let move_data = MoveData {
+                    ply: tree.nodes[node_id].board.fullmove,
+                    san: write_step(&tree.nodes[node_id].board, step).unwrap(),
+                    color: tree.nodes[node_id].board.active_color,
+                };
+                tree.nodes[node_id].sons.push((step, son_id, move_data));
+            }
+        }
+
+        Ok(tree) 
+    }
+
+    pub fn board(&self) -> Board {
+        self.nodes[self.focus].board.clone()
+    }
+
+    // 从根到当前焦点之前的所有盘面，按时间顺序排列，用于判断重复局面
+    pub fn history(&self) -> Vec<Board> {
+        let mut boards = Vec::new();
+        let mut current = self.focus;
+        while let Some(parent) = self.nodes[current].parent {
+            boards.push(self.nodes[parent].board.clone());
+            current = parent;
+        }
+        boards.reverse();
+        boards
+    }
+
+    // 从根到当前焦点的所有着法，按时间顺序排列
+    pub fn steps(&self) -> Vec<Step> {
+        let mut steps = Vec::new();
+        let mut current = self.focus;
+        while let Some(parent) = self.nodes[current].parent {
+            let (step, _, _) = self.nodes[parent].sons.iter().find(|(_, son, _)| *son == current).unwrap();
+            steps.push(*step);
+            current = parent;
+        }
+        steps.reverse();
+        steps
+    }
+
+    pub fn root_board(&self) -> Board {
+        self.nodes[self.root].board.clone()
+    }
+
+    // 当前焦点盘面的棋局结果，考虑重复局面
+    // 当前局面的结果，盘面之外的结果优先
+    pub fn result(&self) -> Option<BoardResult> {
+        self.nodes[self.focus].result.or_else(|| end_game_with_history(&self.nodes[self.focus].board, &self.history()))
+    }
+
+    // 在当前局面上结束棋局（如协议和棋）。之前的局面不受影响，这个局面之后不能再走棋
+    pub fn set_result(&mut self, result: BoardResult) {
+        self.nodes[self.focus].result = Some(result);
+    }
+
+    pub fn root(&self) -> usize {
+        self.root
+    }
+
+    pub fn focus(&self) -> usize {
+        self.focus
+    }
+
+    pub fn parent(&self, idx: usize) -> Option<usize> {
+        self.nodes[idx].parent
+    }
+
+    // 某个节点的所有后续步，第一个是主分支
+    pub fn sons(&self, idx: usize) -> &[(Step, usize, MoveData)] {
+        &self.nodes[idx].sons
+    }
+
+    pub fn is_first_board(&self) -> bool {
+        self.focus == self.root
+    }
+
+    pub fn is_last_board(&self) -> bool {
+        self.nodes[self.focus].sons.is_empty()
+    }
+
+    pub fn move_to_start(&mut self) {
+        while let Some(parent) = self.nodes[self.focus].parent {
+            self.focus = parent;
+        }
+    }
+
+    pub fn move_backward(&mut self) {
+        if let Some(parent) = self.nodes[self.focus].parent {
+            self.focus = parent;
+        }
+    }
+
+    pub fn move_forward(&mut self) {
+        if !self.nodes[self.focus].sons.is_empty() {
+            self.focus = self.nodes[self.focus].sons[0].1;
+        }
+    }
+
+    pub fn move_to_end(&mut self) {
+        while !self.nodes[self.focus].sons.is_empty() {
+            self.focus = self.nodes[self.focus].sons[0].1;
+        }
+    }
+
+    pub fn move_to_node(&mut self, idx: usize) {
+        self.focus = idx;
+    }
+
+    // 由于rust的禁止双重借用的规则被迫用了比较奇怪的写法，实际上函数式会好一些
+    pub fn try_move(&mut self, step: Step) -> bool {
+        if self.nodes[self.focus].result.is_some() {
+            return false;
+        }
+        let step = normalize_step(&self.nodes[self.focus].board, step);
+        {
+            let node = &self.nodes[self.focus];
+            for (s, son, _) in &node.sons {
+                if step == *s {
+                    self.focus = *son;
+                    return true;
+                }
+            }
+        }
+        if let Some(board) = try_move(&self.nodes[self.focus].board, step) {
+            let new_index = self.nodes.len();
+            self.nodes.push(GameTreeNode::new(board));
+            self.nodes[new_index].parent = Some(self.focus);
+            let move_data = MoveData {
+                ply: self.nodes[self.focus].board.fullmove,
+                san: write_step(&self.nodes[self.focus].board, step).unwrap(),
+                color: self.nodes[self.focus].board.active_color,
+            };
+            self.nodes[self.focus].sons.push((step, new_index, move_data));
+            self.focus = new_index;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn collect_remaining_nodes(
+        &mut self, 
+        current: usize, 
+        node_to_delete: usize, 
+        remaining_nodes: &mut Vec<usize>,
+        node_mapping: &mut Vec<Option<usize>>,
+    ) {
+        if current == node_to_delete {
+            return;
+        }
+
+        let new_index = remaining_nodes.len();
+        remaining_nodes.push(current);
+        node_mapping[current] = Some(new_index);
+
+        for (_, son, _) in self.nodes[current].sons.clone() {
+            self.collect_remaining_nodes(son, node_to_delete, remaining_nodes, node_mapping);
+        }
+    }
+
+    // 将某个节点在父节点的后续步中提到第一位
+    pub fn promote_variation(&mut self, current: usize) {
+        if let Some(parent) = self.nodes[current].parent {
+            let mut pos = 0;
+            for (idx, (_, son_id, _)) in self.nodes[parent].sons.iter().enumerate() {
+                if *son_id == current {
+                    pos = idx;
+                }
+            }
+            if pos != 0 {
+                self.nodes[parent].sons.swap(0, pos);
+            }
+        }
+    }
+
+    // 将从根到某个节点的路径设为主线
+    pub fn set_as_mainline(&mut self, current: usize) {
+        let mut cur = current;
+        while let Some(parent) = self.nodes[cur].parent {
+            self.promote_variation(cur);
+            cur = parent;
+        }
+    }
+
+    // 删除某个节点及其所有后续节点。根节点不能删除。如果焦点被删除，焦点移到根并返回 true
+    pub fn delete_variation(&mut self, current: usize) -> bool {
+        if current == self.root {
+            return false;
+        }
+
+        let mut remaining_nodes = Vec::new();
+        let mut node_mapping = vec![None; self.nodes.len()];
+
+        self.collect_remaining_nodes(self.root, current, &mut remaining_nodes, &mut node_mapping);
+
+        // 创建新的节点向量并更新索引
+        let mut new_nodes = Vec::with_capacity(remaining_nodes.len());
+        
+        // 首先创建所有节点（但sons和parent还未更新）
+        for &old_index in &remaining_nodes {
+            let mut node = self.nodes[old_index].clone();
+            node.sons.clear(); // 清空子节点，稍后重新建立
+            node.parent = node.parent.and_then(|p| node_mapping[p]); // 更新父节点索引
+            new_nodes.push(node);
+        }
+        
+        // 然后重新建立子节点关系
+        for &old_index in &remaining_nodes {
+            let new_index = node_mapping[old_index].unwrap();
+            for (step, son_old_index, move_data) in &self.nodes[old_index].sons {
+                if let Some(son_new_index) = node_mapping[*son_old_index] {
+                    new_nodes[new_index].sons.push((*step, son_new_index, move_data.clone()));
+                }
+            }
+        }
+
+        self.nodes = new_nodes;
+        self.root = 0;
+        // 更新焦点。如果原来的焦点被删除，将焦点移到根并更新棋盘。
+        match node_mapping[self.focus] {
+            Some(focus) => {
+                self.focus = focus;
+                false
+            },
+            None => {
+                self.focus = 0;
+                true
+            },
+        }
+    }
+}
+
+// 棋局树的文本格式：标题行、初始局面的 FEN、节点数，之后每行是一个节点的所有 (子节点, 步)
+impl fmt::Display for GameTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let title = match self.variant() {
+            VariantKind::Standard => "[chess game tree]".to_string(),
+            variant => format!("[chess game tree]\n[Variant \"{}\"]", variant),
+        };
+        let initial = write_fen(self.nodes[self.root].board.clone());
+        let nodes = self.nodes.len();
+
+        let info = self.nodes.iter().map(|node| {
+            node.sons.iter().map(|(_step, son, move_data)| {
+                format!("({}, {})", son, move_data.san)
+            })
+            .collect::<Vec<String>>()
+            .join("|")
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+        write!(f, "{}\n{}\n{}\n{}", title, initial, nodes, info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agreed_draw_stays_on_its_node() {
+        let mut tree = GameTree::new(Board::default());
+        for san in ["e4", "e5"] {
+            assert!(tree.try_move(read_step(&tree.board(), san.to_string()).unwrap()));
+        }
+        let draw = BoardResult::Draw(DrawReason::Agreement);
+        tree.set_result(draw);
+        assert!(tree.result() == Some(draw));
+        // 和棋之后不能再走棋，之前的局面没有结果
+        assert!(!tree.try_move(read_step(&tree.board(), "Nf3".to_string()).unwrap()));
+        tree.move_backward();
+        assert!(tree.result().is_none());
+        assert!(tree.try_move(read_step(&tree.board(), "c5".to_string()).unwrap()));
+        tree.move_to_start();
+        tree.move_to_end();
+        assert!(tree.result() == Some(draw));
+    }
+
+    #[test]
+    fn variant_is_recorded() {
+        let mut tree = GameTree::new(VariantKind::ThreeCheck.rules().start_position());
+        for san in ["e4", "e5", "Bc4", "Nc6", "Bxf7+"] {
+            let step = read_step(&tree.board(), san.to_string()).unwrap();
+            assert!(tree.try_move(step));
+        }
+
+        let text = tree.to_string();
+        assert!(text.starts_with("[chess game tree]\n[Variant \"Three-check\"]\n"));
+        let loaded = GameTree::from_string(text, ValidationMode::Strict).unwrap();
+        assert_eq!(loaded.variant(), VariantKind::ThreeCheck);
+        assert_eq!(loaded.to_string(), tree.to_string());
+
+        let pgn = tree.pgn(tree.focus());
+        assert_eq!(pgn, "[Variant \"Three-check\"]\n\n1.e4 e5 2.Bc4 Nc6 3.Bxf7+");
+        let loaded = GameTree::from_pgn(pgn, ValidationMode::Strict).unwrap();
+        assert_eq!(loaded.board().checks, [1, 0]);
+
+        let bad = "[chess game tree]\n[Variant \"Losers\"]\n8/8/8/8/8/8/8/8\n1\n".to_string();
+        assert_eq!(GameTree::from_string(bad, ValidationMode::Lenient).err(), Some(GameTreeError::InvalidVariant("Losers".to_string())));
+    }
+
+    #[test]
+    fn setup_position_is_recorded() {
+        let start = read_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPP1PP/RNBQKBNR b KQkq - 0 1".to_string()).unwrap();
+        let mut tree = GameTree::new(start.clone());
+        for san in ["e5", "e4", "Qh4+"] {
+            let step = read_step(&tree.board(), san.to_string()).unwrap();
+            assert!(tree.try_move(step));
+        }
+        let pgn = tree.pgn(tree.focus());
+        assert_eq!(pgn, "[SetUp \"1\"]\n[FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPP1PP/RNBQKBNR b KQkq - 0 1\"]\n\n1...e5 2.e4 Qh4+");
+        let loaded = GameTree::from_pgn(pgn, ValidationMode::Strict).unwrap();
+        assert!(loaded.root_board() == start);
+        assert_eq!(loaded.steps(), tree.steps());
+        assert_eq!(loaded.steps().len(), 3);
+        assert!(loaded.board() == tree.board());
+
+        // 无法读取或不合法的 FEN 标签是错误，不会退回初始局面
+        let bad = "[SetUp \"1\"]\n[FEN \"8/8 w - - 0 1\"]\n\n1.e4".to_string();
+        assert!(matches!(GameTree::from_pgn(bad, ValidationMode::Lenient), Err(GameTreeError::InvalidFen(_))));
+        let two_kings = "[SetUp \"1\"]\n[FEN \"kk6/8/8/8/8/8/8/K7 w - - 0 1\"]\n\n1.Kb1".to_string();
+        assert!(matches!(GameTree::from_pgn(two_kings, ValidationMode::Strict), Err(GameTreeError::InvalidPosition(_))));
+
+        // 从初始局面开始的棋局不需要 FEN 标签
+        assert_eq!(GameTree::new(Board::default()).pgn(0), "");
+    }
+
+    #[test]
+    fn chess960_is_recorded() {
+        use crate::chess960::{start_position, STANDARD_START_POSITION};
+
+        // 和标准初始局面相同的 Chess960 棋局，易位写作王走到车上
+        let mut tree = GameTree::new(start_position(STANDARD_START_POSITION));
+        for san in ["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "O-O"] {
+            let step = read_step(&tree.board(), san.to_string()).unwrap();
+            assert!(tree.try_move(step));
+        }
+        let pgn = tree.pgn(tree.focus());
+        assert!(pgn.starts_with("[Variant \"Chess960\"]\n[SetUp \"1\"]\n[FEN "));
+        let loaded = GameTree::from_pgn(pgn, ValidationMode::Strict).unwrap();
+        assert!(loaded.root_board().chess960);
+        assert_eq!(loaded.variant(), VariantKind::Standard);
+        assert_eq!(loaded.steps(), tree.steps());
+        assert_eq!(loaded.steps().last(), Some(&"e1h1".parse().unwrap()));
+    }
+}
@@ -0,0 +1,21 @@
+// 国际象棋规则、记谱和棋局树，不依赖 Bevy 和 egui
+
+pub mod piece;
+pub mod square;
+pub mod bitboard;
+pub mod movegen;
+pub mod board;
+pub mod zobrist;
+pub mod fen;
+pub mod step;
+pub mod pgn;
+pub mod perft;
+pub mod see;
+pub mod engine;
+pub mod uci;
+pub mod validate;
+pub mod game_tree;
+pub mod chess960;
+pub mod odds;
+pub mod variant;
+pub mod bughouse;
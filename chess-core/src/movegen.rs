@@ -0,0 +1,350 @@
+use crate::{
+    bitboard::*,
+    board::*,
+    piece::*,
+    square::{forward, BoardSize, Square},
+};
+
+// 用位棋盘表示的局面，用于快速生成着法。由 Board 转换而来
+#[derive(Clone)]
+pub struct Position {
+    pub by_color: [Bitboard; 2],
+    pub by_role: [Bitboard; 8],
+    pub active_color: PieceColor,
+    pub castling_availability: CastlingRights,
+    pub en_passant_target: Option<usize>,
+    pub chess960: bool,
+    pub size: BoardSize,
+    pub board_mask: Bitboard, // 棋盘上的格子，棋子不能走到棋盘外
+    pub promotion_roles: &'static [PieceRole],
+}
+
+impl Position {
+    pub fn from_board(board: &Board) -> Self {
+        let mut position = Position {
+            by_color: [Bitboard::EMPTY; 2],
+            by_role: [Bitboard::EMPTY; 8],
+            active_color: board.active_color,
+            castling_availability: board.castling_availability,
+            en_passant_target: board.en_passant_target.map(Square::index),
+            chess960: board.chess960,
+            size: board.size,
+            board_mask: Bitboard::board(board.size),
+            promotion_roles: board.variant.rules().promotion_roles(),
+        };
+        for sq in Square::all() {
+            if let Some(piece) = board.pieces[sq] {
+                position.by_color[piece.piece_color.index()].set(sq.index());
+                position.by_role[piece.piece_role.index()].set(sq.index());
+            }
+        }
+        position
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.by_color[0] | self.by_color[1]
+    }
+
+    pub fn pieces(&self, color: PieceColor, role: PieceRole) -> Bitboard {
+        self.by_color[color.index()] & self.by_role[role.index()]
+    }
+
+    pub fn piece_at(&self, sq: usize) -> Option<Piece> {
+        let piece_color = PieceColor::ALL.into_iter().find(|c| self.by_color[c.index()].contains(sq))?;
+        let piece_role = PieceRole::ALL.into_iter().find(|r| self.by_role[r.index()].contains(sq))?;
+        Some(Piece { piece_role, piece_color })
+    }
+
+    // 某颜色的王所在的格子。如果有多个，返回编号最小的一个
+    pub fn king_square(&self, color: PieceColor) -> Option<usize> {
+        self.pieces(color, PieceRole::King).lsb()
+    }
+
+    // 在给定的占用情况下，by 方攻击 sq 格的所有棋子
+    pub fn attackers(&self, sq: usize, by: PieceColor, occupied: Bitboard) -> Bitboard {
+        let them = self.by_color[by.index()];
+        let role = |role: PieceRole| self.by_role[role.index()];
+        let rooks = role(PieceRole::Rook) | role(PieceRole::Queen) | role(PieceRole::Chancellor);
+        let bishops = role(PieceRole::Bishop) | role(PieceRole::Queen) | role(PieceRole::Archbishop);
+        let knights = role(PieceRole::Knight) | role(PieceRole::Archbishop) | role(PieceRole::Chancellor);
+        them & (
+            (knight_attacks(sq) & knights)
+            | (king_attacks(sq) & self.by_role[PieceRole::King.index()])
+            | (pawn_attacks(by.flip(), sq) & self.by_role[PieceRole::Pawn.index()])
+            | (rook_attacks(sq, occupied) & rooks)
+            | (bishop_attacks(sq, occupied) & bishops)
+        )
+    }
+
+    pub fn is_attacked(&self, sq: usize, by: PieceColor) -> bool {
+        self.attackers(sq, by, self.occupied()).any()
+    }
+
+    // 正在将军行动方的棋子
+    pub fn checkers(&self) -> Bitboard {
+        match self.king_square(self.active_color) {
+            Some(king) => self.attackers(king, self.active_color.flip(), self.occupied()),
+            None => Bitboard::EMPTY,
+        }
+    }
+
+    // color 方攻击的所有格子
+    pub fn attacked_squares(&self, color: PieceColor) -> Bitboard {
+        let mut attacked = Bitboard::EMPTY;
+        for from in self.by_color[color.index()] {
+            attacked |= self.piece_attacks(self.piece_at(from).unwrap().piece_role, color, from);
+        }
+        attacked & self.board_mask
+    }
+
+    // slider_color 方的远程棋子与 king 格之间只隔着一个棋子时，返回 (中间的棋子, 远程棋子)。
+    // 中间的棋子与 king 格同色时被牵制，与远程棋子同色时走开就形成闪击
+    pub fn sliding_blockers(&self, king: usize, slider_color: PieceColor) -> Vec<(usize, usize)> {
+        let rooks = self.pieces(slider_color, PieceRole::Rook) | self.pieces(slider_color, PieceRole::Queen) | self.pieces(slider_color, PieceRole::Chancellor);
+        let bishops = self.pieces(slider_color, PieceRole::Bishop) | self.pieces(slider_color, PieceRole::Queen) | self.pieces(slider_color, PieceRole::Archbishop);
+        let snipers = (rook_attacks(king, Bitboard::EMPTY) & rooks)
+            | (bishop_attacks(king, Bitboard::EMPTY) & bishops);
+        snipers.into_iter()
+            .filter_map(|sniper| {
+                let blockers = between(king, sniper) & self.occupied();
+                (blockers.count() == 1).then(|| (blockers.lsb().unwrap(), sniper))
+            })
+            .collect()
+    }
+
+    // 计算行动方被牵制的棋子。pin_rays[sq] 为 sq 上的棋子能走的范围，未被牵制的棋子为全盘
+    fn pin_rays(&self, king: usize) -> [Bitboard; SQUARE_COUNT] {
+        let mut rays = [Bitboard::ALL; SQUARE_COUNT];
+        let us = self.by_color[self.active_color.index()];
+        for (pinned, sniper) in self.sliding_blockers(king, self.active_color.flip()) {
+            if us.contains(pinned) {
+                rays[pinned] = between(king, sniper) | Bitboard::from_square(sniper);
+            }
+        }
+        rays
+    }
+
+    // 生成所有合法着法
+    pub fn legal_moves(&self) -> Vec<Step> {
+        let mut moves = Vec::with_capacity(64);
+        self.generate(Bitboard::ALL, &mut moves);
+        moves
+    }
+
+    // 生成从某格出发的所有合法着法
+    pub fn legal_moves_from(&self, from: Square) -> Vec<Step> {
+        let mut moves = Vec::new();
+        self.generate(Bitboard::from_square(from.index()), &mut moves);
+        moves
+    }
+
+    // 生成所有伪合法着法，不考虑走完后己方的王是否被攻击，没有王时其它棋子也可以走。
+    // 王被攻击时不能易位。原子象棋和自杀象棋在此基础上按各自的规则筛选
+    pub fn pseudo_legal_moves(&self) -> Vec<Step> {
+        let color = self.active_color;
+        let us_bb = self.by_color[color.index()];
+        let mut moves = Vec::with_capacity(64);
+        for from in us_bb {
+            let role = self.piece_at(from).unwrap().piece_role;
+            if role == PieceRole::Pawn {
+                self.generate_pawn(from, None, Bitboard::ALL, &mut moves);
+                continue;
+            }
+            for to in self.piece_attacks(role, color, from) & !us_bb & self.board_mask {
+                push_step(&mut moves, from, to, None);
+            }
+        }
+        if let Some(king) = self.king_square(color)
+            && !self.is_attacked(king, color.flip())
+        {
+            self.generate_castling(king, &mut moves);
+        }
+        moves
+    }
+
+    // color 方能看到的格子：己方棋子所在的格子，以及己方棋子能走到或攻击的格子。用于暗棋
+    pub fn visible_squares(&self, color: PieceColor) -> Bitboard {
+        let mut position = self.clone();
+        if color != self.active_color {
+            position.active_color = color;
+            position.en_passant_target = None;
+        }
+        let mut visible = self.by_color[color.index()] | self.attacked_squares(color);
+        for step in position.pseudo_legal_moves() {
+            visible.set(step.to.index());
+        }
+        visible & self.board_mask
+    }
+
+    // color 方的棋子在 from 格时攻击的格子，包括棋盘外的格子
+    fn piece_attacks(&self, role: PieceRole, color: PieceColor, from: usize) -> Bitboard {
+        let occupied = self.occupied();
+        match role {
+            PieceRole::Pawn => pawn_attacks(color, from),
+            PieceRole::Knight => knight_attacks(from),
+            PieceRole::Bishop => bishop_attacks(from, occupied),
+            PieceRole::Rook => rook_attacks(from, occupied),
+            PieceRole::Queen => queen_attacks(from, occupied),
+            PieceRole::King => king_attacks(from),
+            PieceRole::Archbishop => archbishop_attacks(from, occupied),
+            PieceRole::Chancellor => chancellor_attacks(from, occupied),
+        }
+    }
+
+    // 可以放下手中棋子的格子：空格，被将军时只能放在王和将军的棋子之间。放下棋子不会让己方的王被将军
+    pub fn drop_targets(&self) -> Bitboard {
+        let empty = !self.occupied() & self.board_mask;
+        let Some(king) = self.king_square(self.active_color) else {
+            return empty
+        };
+        let checkers = self.checkers();
+        match checkers.lsb() {
+            None => empty,
+            Some(_) if checkers.more_than_one() => Bitboard::EMPTY,
+            Some(checker) => between(king, checker) & empty,
+        }
+    }
+
+    fn generate(&self, from_mask: Bitboard, moves: &mut Vec<Step>) {
+        let color = self.active_color;
+        let them = color.flip();
+        // 行动方没有王时不能走棋，与 Board 原有的规则一致
+        let Some(king) = self.king_square(color) else {
+            return
+        };
+        let us_bb = self.by_color[color.index()];
+        let occupied = self.occupied();
+
+        // 王的移动：目标格在王离开后不能被攻击
+        if from_mask.contains(king) {
+            let without_king = occupied ^ Bitboard::from_square(king);
+            for to in king_attacks(king) & !us_bb & self.board_mask {
+                if self.attackers(to, them, without_king).is_empty() {
+                    push_step(moves, king, to, None);
+                }
+            }
+        }
+
+        let checkers = self.attackers(king, them, occupied);
+        if checkers.more_than_one() {
+            return
+        }
+        // 被将军时其它棋子只能吃掉将军的棋子或挡在中间
+        let check_mask = match checkers.lsb() {
+            Some(checker) => between(king, checker) | checkers,
+            None => Bitboard::ALL,
+        };
+        let pin_rays = self.pin_rays(king);
+
+        for from in us_bb & from_mask & !Bitboard::from_square(king) {
+            let role = self.piece_at(from).unwrap().piece_role;
+            let mask = check_mask & pin_rays[from];
+            if role == PieceRole::Pawn {
+                self.generate_pawn(from, Some(king), mask, moves);
+                continue;
+            }
+            for to in self.piece_attacks(role, color, from) & !us_bb & mask & self.board_mask {
+                push_step(moves, from, to, None);
+            }
+        }
+
+        if checkers.is_empty() && from_mask.contains(king) {
+            self.generate_castling(king, moves);
+        }
+    }
+
+    // king 为 None 时生成伪合法着法，吃过路兵不检查王的安全
+    fn generate_pawn(&self, from: usize, king: Option<usize>, mask: Bitboard, moves: &mut Vec<Step>) {
+        let color = self.active_color;
+        let them = color.flip();
+        let occupied = self.occupied();
+        let forward = forward(color);
+        let start_rank = self.size.pawn_rank(color);
+        let last_rank = self.size.back_rank(them);
+        let step = |sq: usize| Square::from_index(sq).offset(forward)
+            .map(Square::index)
+            .filter(|&to| self.board_mask.contains(to));
+
+        let mut targets = pawn_attacks(color, from) & self.by_color[them.index()];
+        if let Some(one) = step(from) && !occupied.contains(one) {
+            targets.set(one);
+            if let Some(two) = step(one)
+                && coords(from).1 == start_rank && !occupied.contains(two) {
+                targets.set(two);
+            }
+        }
+
+        for to in targets & mask {
+            if coords(to).1 == last_rank {
+                for &role in self.promotion_roles {
+                    push_step(moves, from, to, Some(role));
+                }
+            } else {
+                push_step(moves, from, to, None);
+            }
+        }
+
+        // 吃过路兵：直接检查吃完之后王是否安全，可以处理横向的牵制
+        if let Some(ep) = self.en_passant_target
+            && pawn_attacks(color, from).contains(ep) && !occupied.contains(ep) {
+            let captured = Square::from_index(ep).offset((-forward.0, -forward.1)).unwrap().index();
+            let after = occupied
+                ^ Bitboard::from_square(from)
+                ^ Bitboard::from_square(ep)
+                ^ Bitboard::from_square(captured);
+            let safe = king.is_none_or(|king| {
+                (self.attackers(king, them, after) & !Bitboard::from_square(captured)).is_empty()
+            });
+            if safe {
+                push_step(moves, from, ep, None);
+            }
+        }
+    }
+
+    // 王车易位。王和车的终点在标准规则和 Chess960 中相同，王和车之间、两者到终点之间除了这两个棋子外都必须为空，
+    // 王经过的格子（包括起点和终点）都不能被攻击。Chess960 中用王走到车上表示易位
+    fn generate_castling(&self, king: usize, moves: &mut Vec<Step>) {
+        let color = self.active_color;
+        let them = color.flip();
+        let rank = self.size.back_rank(color);
+        if coords(king).1 != rank {
+            return
+        }
+        let rooks = self.pieces(color, PieceRole::Rook);
+        let occupied = self.occupied();
+
+        for side in CastlingSide::ALL {
+            let Some(rook_x) = self.castling_availability.rook_file(color, side) else {
+                continue;
+            };
+            let rook = square(rook_x, rank);
+            let king_to = square(side.king_file(self.size.files), rank);
+            let rook_to = square(side.rook_file(self.size.files), rank);
+            if !rooks.contains(rook) {
+                continue;
+            }
+            let without = occupied ^ Bitboard::from_square(king) ^ Bitboard::from_square(rook);
+            let must_be_empty = between(king, rook)
+                | between(king, king_to) | Bitboard::from_square(king_to)
+                | between(rook, rook_to) | Bitboard::from_square(rook_to);
+            if (must_be_empty & without).any() {
+                continue;
+            }
+            let path = between(king, king_to) | Bitboard::from_square(king_to);
+            if path.into_iter().all(|sq| self.attackers(sq, them, without).is_empty()) {
+                let to = if self.chess960 { rook } else { king_to };
+                push_step(moves, king, to, None);
+            }
+        }
+    }
+}
+
+fn push_step(moves: &mut Vec<Step>, from: usize, to: usize, promotion: Option<PieceRole>) {
+    moves.push(Step {
+        from: Square::from_index(from),
+        to: Square::from_index(to),
+        promotion,
+        drop: None,
+    });
+}
+
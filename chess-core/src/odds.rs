@@ -0,0 +1,132 @@
+use std::fmt;
+use crate::{
+    board::*, piece::*, square::{BoardSize, Square}, zobrist,
+};
+
+// 让子棋的预设。让子的一方（giver）从标准初始局面中拿掉棋子，或让对方先走、连走几步
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Odds {
+    #[default]
+    Knight,         // 让后翼的马
+    Rook,           // 让后翼的车，同时失去后翼易位权
+    Queen,          // 让后
+    PawnAndMove,    // 让 f 兵，并且由对方先走
+    ExtraMoves(u8), // 对方先走，并且开局连走这么多步
+}
+
+impl Odds {
+    pub const ALL: [Odds; 6] = [
+        Odds::Knight,
+        Odds::Rook,
+        Odds::Queen,
+        Odds::PawnAndMove,
+        Odds::ExtraMoves(2),
+        Odds::ExtraMoves(3),
+    ];
+
+    pub fn name(self) -> String {
+        match self {
+            Odds::Knight => "Knight odds".to_string(),
+            Odds::Rook => "Rook odds".to_string(),
+            Odds::Queen => "Queen odds".to_string(),
+            Odds::PawnAndMove => "Pawn and move".to_string(),
+            Odds::ExtraMoves(n) => format!("{} moves", n),
+        }
+    }
+
+    // 让子方拿掉的棋子所在的列和它是否为兵
+    fn removed_file(self) -> Option<(usize, bool)> {
+        match self {
+            Odds::Knight => Some((1, false)),
+            Odds::Rook => Some((0, false)),
+            Odds::Queen => Some((3, false)),
+            Odds::PawnAndMove => Some((5, true)),
+            Odds::ExtraMoves(_) => None,
+        }
+    }
+
+    // 被让方开局连走时，让子方不应着的次数。被让方走完这么多步之后才轮到让子方
+    pub fn extra_moves(self) -> usize {
+        match self {
+            Odds::ExtraMoves(n) => (n as usize).saturating_sub(1),
+            _ => 0,
+        }
+    }
+
+    // 由 giver 一方让子的初始局面
+    pub fn start_position(self, giver: PieceColor) -> Board {
+        let size = BoardSize::STANDARD;
+        let mut board = Board::default();
+        if let Some((file, pawn)) = self.removed_file() {
+            let rank = if pawn { size.pawn_rank(giver) } else { size.back_rank(giver) };
+            let sq = Square::new(file, rank);
+            board.pieces[sq] = None;
+            board.castling_availability.clear_square(sq, size);
+        }
+        if matches!(self, Odds::PawnAndMove | Odds::ExtraMoves(_)) {
+            board.active_color = giver.flip();
+        }
+        board.zobrist = zobrist::compute(&board);
+        board
+    }
+}
+
+impl fmt::Display for Odds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// 被让方连走的一步：走完后仍由被让方走棋，没有吃过路兵的机会。不能将军，否则下一步就可以吃王
+pub fn play_extra_move(board: &Board, step: Step) -> Option<Board> {
+    let mut new_board = try_move(board, step)?;
+    if !king_safe(&new_board, board.active_color.flip()) {
+        return None
+    }
+    new_board.active_color = board.active_color;
+    new_board.en_passant_target = None;
+    new_board.fullmove = board.fullmove;
+    new_board.zobrist = zobrist::compute(&new_board);
+    Some(new_board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen::{read_fen, write_fen}, step::read_step};
+
+    #[test]
+    fn odds_start_positions() {
+        for (odds, giver, fen) in [
+            (Odds::Knight, PieceColor::White, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1"),
+            (Odds::Rook, PieceColor::White, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w Kkq - 0 1"),
+            (Odds::Rook, PieceColor::Black, "1nbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQk - 0 1"),
+            (Odds::Queen, PieceColor::White, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1"),
+            (Odds::PawnAndMove, PieceColor::Black, "rnbqkbnr/ppppp1pp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            (Odds::PawnAndMove, PieceColor::White, "rnbqkbnr/pppppppp/8/8/8/8/PPPPP1PP/RNBQKBNR b KQkq - 0 1"),
+            (Odds::ExtraMoves(2), PieceColor::White, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1"),
+        ] {
+            let board = odds.start_position(giver);
+            assert_eq!(write_fen(board.clone()), fen, "{} {:?}", odds, giver);
+            assert_eq!(board.zobrist, zobrist::compute(&board));
+        }
+    }
+
+    #[test]
+    fn extra_moves() {
+        let odds = Odds::ExtraMoves(3);
+        assert_eq!(odds.extra_moves(), 2);
+        let mut board = odds.start_position(PieceColor::Black);
+        for san in ["e4", "d4"] {
+            let step = read_step(&board, san.to_string()).unwrap();
+            board = play_extra_move(&board, step).unwrap();
+        }
+        assert_eq!(write_fen(board.clone()), "rnbqkbnr/pppppppp/8/8/3PP3/8/PPP2PPP/RNBQKBNR w KQkq - 0 1");
+        // 连走的着法不能将军
+        let mut board = read_fen("rnbqkbnr/ppppp1pp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1".to_string()).unwrap();
+        let check = read_step(&board, "Qh5+".to_string()).unwrap();
+        assert!(play_extra_move(&board, check).is_none());
+        board = play_extra_move(&board, read_step(&board, "Nf3".to_string()).unwrap()).unwrap();
+        assert_eq!(board.active_color, PieceColor::White);
+    }
+}
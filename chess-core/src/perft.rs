@@ -0,0 +1,113 @@
+use crate::board::*;
+
+// 统计从当前局面出发走 depth 步的所有着法序列数，用于检验着法生成是否正确
+pub fn perft(board: &Board, depth: usize) -> u64 {
+    perft_in_place(&mut board.clone(), depth)
+}
+
+// 在同一个盘面上走棋和撤销，不为每一步复制盘面
+fn perft_in_place(board: &mut Board, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1
+    }
+    let moves = all_move(board);
+    if depth == 1 {
+        return moves.len() as u64
+    }
+    let mut nodes = 0;
+    for step in moves {
+        let mv = board.make_move(step);
+        nodes += perft_in_place(board, depth - 1);
+        board.unmake_move(&mv);
+    }
+    nodes
+}
+
+// 分别统计每一步之后的 perft 结果，便于和其它程序逐步对比
+pub fn divide(board: &Board, depth: usize) -> Vec<(Step, u64)> {
+    if depth == 0 {
+        return Vec::new()
+    }
+    let mut board = board.clone();
+    all_move(&board).into_iter()
+        .map(|step| {
+            let mv = board.make_move(step);
+            let nodes = perft_in_place(&mut board, depth - 1);
+            board.unmake_move(&mv);
+            (step, nodes)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::*;
+
+    fn perft_fen(fen: &str, depth: usize) -> u64 {
+        perft(&read_fen(fen.to_string()).unwrap(), depth)
+    }
+
+    // expected[i] 为深度 i + 1 的结果
+    fn check(fen: &str, expected: &[u64]) {
+        let board = read_fen(fen.to_string()).unwrap();
+        for (depth, &nodes) in expected.iter().enumerate() {
+            assert_eq!(perft(&board, depth + 1), nodes, "perft({}) of {}", depth + 1, fen);
+        }
+    }
+
+    #[test]
+    fn initial_position() {
+        check(INITIAL_FEN, &[20, 400, 8902, 197281]);
+    }
+
+    #[test]
+    fn kiwipete() {
+        check("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]);
+    }
+
+    #[test]
+    fn rook_endgame() {
+        check("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238, 674624]);
+    }
+
+    #[test]
+    fn promotions_and_castling() {
+        check("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467]);
+        check("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1", &[6, 264, 9467]);
+        check("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]);
+        check("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89890]);
+    }
+
+    #[test]
+    fn en_passant_edge_cases() {
+        // 吃过路兵后露出横向或斜向的将军
+        assert_eq!(perft_fen("8/5bk1/8/2Pp4/8/1K6/8/8 w - d6 0 1", 6), 824064);
+        assert_eq!(perft_fen("8/8/1k6/8/2pP4/8/5BK1/8 b - d3 0 1", 6), 824064);
+        assert_eq!(perft_fen("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 6), 1440467);
+        assert_eq!(perft_fen("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", 6), 1134888);
+    }
+
+    #[test]
+    fn castling_edge_cases() {
+        assert_eq!(perft_fen("5k2/8/8/8/8/8/8/4K2R w K - 0 1", 6), 661072);
+        assert_eq!(perft_fen("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", 6), 803711);
+        assert_eq!(perft_fen("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", 4), 1274206);
+        assert_eq!(perft_fen("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", 4), 1720476);
+    }
+
+    #[test]
+    fn promotion_edge_cases() {
+        assert_eq!(perft_fen("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 6), 3821001);
+        assert_eq!(perft_fen("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", 6), 217342);
+        assert_eq!(perft_fen("8/P1k5/K7/8/8/8/8/8 w - - 0 1", 6), 92683);
+    }
+
+    #[test]
+    fn checks_and_stalemates() {
+        assert_eq!(perft_fen("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", 5), 1004658);
+        assert_eq!(perft_fen("K1k5/8/P7/8/8/8/8/8 w - - 0 1", 6), 2217);
+        assert_eq!(perft_fen("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", 7), 567584);
+        assert_eq!(perft_fen("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", 4), 23527);
+    }
+}
@@ -0,0 +1,48 @@
+use regex::Regex;
+
+// PGN 的标签对，如 [Variant "King of the Hill"]，按出现的顺序排列
+pub fn parse_pgn_tags(pgn: &str) -> Vec<(String, String)> {
+    let re = Regex::new(r#"\[\s*(\w+)\s+"([^"]*)"\s*\]"#).unwrap();
+    re.captures_iter(pgn)
+        .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+        .collect()
+}
+
+// 取出 PGN 中的所有步，忽略标签对
+pub fn parse_pgn(pgn: &str) -> Vec<String> {
+    let re = Regex::new(r#"\[[^\]]*\]"#).unwrap();
+    let movetext = re.replace_all(pgn, " ");
+    movetext.split_whitespace()
+        .filter_map(|token| {
+            // 检查是否是 "{ply}.{step}" 格式
+            if let Some(dot_pos) = token.find('.') {
+                // 确保点号不在开头或结尾
+                if dot_pos > 0 && dot_pos < token.len() - 1 {
+                    // 返回点号后面的部分，黑方的着法前可以有多个点号，如 "1...e5"
+                    let step = token[dot_pos + 1..].trim_start_matches('.');
+                    return (!step.is_empty()).then(|| step.to_string());
+                }
+            } else {
+                return Some(token.to_string());
+            }
+            
+            None
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_and_moves() {
+        let pgn = "[Event \"Casual\"]\n[Variant \"King of the Hill\"]\n\n1.e4 e5 2.Ke2";
+        assert_eq!(parse_pgn_tags(pgn), vec![
+            ("Event".to_string(), "Casual".to_string()),
+            ("Variant".to_string(), "King of the Hill".to_string()),
+        ]);
+        assert_eq!(parse_pgn(pgn), vec!["e4", "e5", "Ke2"]);
+        assert_eq!(parse_pgn("1...e5 2.d4 2... exd4"), vec!["e5", "d4", "exd4"]);
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut, Index, IndexMut};
+use crate::square::Square;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PieceRole {
+    Pawn,
+    Rook,
+    Knight,
+    Bishop,
+    Queen,
+    King,
+    Archbishop, // 象和马的组合
+    Chancellor, // 车和马的组合
+}
+
+impl PieceRole {
+    pub const ALL: [PieceRole; 8] = [
+        PieceRole::Pawn,
+        PieceRole::Rook,
+        PieceRole::Knight,
+        PieceRole::Bishop,
+        PieceRole::Queen,
+        PieceRole::King,
+        PieceRole::Archbishop,
+        PieceRole::Chancellor,
+    ];
+
+    // 在位棋盘等数组中的下标
+    pub fn index(&self) -> usize {
+        match *self {
+            PieceRole::Pawn => 0,
+            PieceRole::Rook => 1,
+            PieceRole::Knight => 2,
+            PieceRole::Bishop => 3,
+            PieceRole::Queen => 4,
+            PieceRole::King => 5,
+            PieceRole::Archbishop => 6,
+            PieceRole::Chancellor => 7,
+        }
+    }
+}
+
+impl fmt::Display for PieceRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let role = match self {
+            PieceRole::Pawn => "Pawn",
+            PieceRole::Rook => "Rook",
+            PieceRole::Knight => "Knight",
+            PieceRole::Bishop => "Bishop",
+            PieceRole::Queen => "Queen",
+            PieceRole::King => "King",
+            PieceRole::Archbishop => "Archbishop",
+            PieceRole::Chancellor => "Chancellor",
+        };
+        write!(f, "{}", role)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum PieceColor {
+    #[default]
+    White,
+    Black,
+}
+
+impl PieceColor {
+    pub const ALL: [PieceColor; 2] = [PieceColor::White, PieceColor::Black];
+
+    // 在位棋盘等数组中的下标
+    pub fn index(&self) -> usize {
+        match *self {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        }
+    }
+
+    pub fn flip(&self) -> PieceColor {
+        match *self {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Piece {
+    pub piece_role: PieceRole,
+    pub piece_color: PieceColor,
+}
+
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let color = match self.piece_color {
+            PieceColor::White => "W",
+            PieceColor::Black => "B",
+        };
+        write!(f, "{}{}", color, self.piece_role)
+    }
+}
+
+// 手中的棋子，按 PieceRole 的下标记录每种棋子的数量。只有疯狂屋变体使用
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Pocket([u8; 8]);
+
+// 可以放在手中的棋子，按 FEN 中的书写顺序排列
+pub const POCKET_ROLES: [PieceRole; 5] = [PieceRole::Queen, PieceRole::Rook, PieceRole::Bishop, PieceRole::Knight, PieceRole::Pawn];
+
+impl Pocket {
+    pub fn count(&self, role: PieceRole) -> u8 {
+        self.0[role.index()]
+    }
+
+    pub fn add(&mut self, role: PieceRole) {
+        self.0[role.index()] += 1;
+    }
+
+    pub fn remove(&mut self, role: PieceRole) {
+        self.0[role.index()] -= 1;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&n| n == 0)
+    }
+
+    // 手中有的棋子种类
+    pub fn roles(&self) -> impl Iterator<Item = PieceRole> + '_ {
+        POCKET_ROLES.into_iter().filter(|&role| self.count(role) > 0)
+    }
+}
+
+// 支持的最大棋盘尺寸。实际的列数和行数记录在 Board.size 中，超出实际尺寸的格子总是空的
+pub const BOARD_SIZE_I: usize = 10;
+pub const BOARD_SIZE_J: usize = 10;
+pub const SQUARE_COUNT: usize = BOARD_SIZE_I * BOARD_SIZE_J;
+
+// 棋盘上的棋子，pieces[x][y] 为第 x 列第 y 行的棋子。按最大尺寸分配的定长数组，复制盘面时不需要分配内存
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Pieces([[Option<Piece>; BOARD_SIZE_J]; BOARD_SIZE_I]);
+
+impl Deref for Pieces {
+    type Target = [[Option<Piece>; BOARD_SIZE_J]; BOARD_SIZE_I];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Pieces {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+// 既可以用 pieces[x][y]，也可以用 pieces[square] 访问
+impl Index<usize> for Pieces {
+    type Output = [Option<Piece>; BOARD_SIZE_J];
+
+    fn index(&self, file: usize) -> &Self::Output {
+        &self.0[file]
+    }
+}
+
+impl IndexMut<usize> for Pieces {
+    fn index_mut(&mut self, file: usize) -> &mut Self::Output {
+        &mut self.0[file]
+    }
+}
+
+impl Index<Square> for Pieces {
+    type Output = Option<Piece>;
+
+    fn index(&self, sq: Square) -> &Self::Output {
+        &self.0[sq.file()][sq.rank()]
+    }
+}
+
+impl IndexMut<Square> for Pieces {
+    fn index_mut(&mut self, sq: Square) -> &mut Self::Output {
+        &mut self.0[sq.file()][sq.rank()]
+    }
+}
+
+impl Pieces {
+    pub fn new() -> Self {
+        Pieces([[None; BOARD_SIZE_J]; BOARD_SIZE_I])
+    }
+}
+
+impl Default for Pieces {
+    fn default() -> Self {
+        Pieces::new()
+    }
+}
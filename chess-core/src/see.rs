@@ -0,0 +1,116 @@
+use crate::{
+    bitboard::Bitboard, board::*, movegen::Position, piece::*, square::forward,
+};
+
+// 棋子的价值，以兵为 100。王的价值足够大，保证吃王总是最有利的
+pub fn piece_value(role: PieceRole) -> i32 {
+    match role {
+        PieceRole::Pawn => 100,
+        PieceRole::Knight => 300,
+        PieceRole::Bishop => 300,
+        PieceRole::Rook => 500,
+        PieceRole::Archbishop => 700,
+        PieceRole::Chancellor => 800,
+        PieceRole::Queen => 900,
+        PieceRole::King => 20000,
+    }
+}
+
+// 吃子时按价值从小到大选择棋子
+const SEE_ORDER: [PieceRole; 8] = [
+    PieceRole::Pawn, PieceRole::Knight, PieceRole::Bishop, PieceRole::Rook,
+    PieceRole::Archbishop, PieceRole::Chancellor, PieceRole::Queen, PieceRole::King,
+];
+
+// 静态交换评估：走完 step 之后，双方轮流用价值最小的棋子在目标格上互相吃子，任何一方都可以在对自己不利时停止，
+// 返回走棋一方最终得到的子力。远程棋子后面的同线棋子在前面的棋子吃完后加入（X 光攻击）。
+// 不考虑牵制和变体规则，step 应为合法的着法
+pub fn see(board: &Board, step: Step) -> i32 {
+    let position = Position::from_board(board);
+    let to = step.to.index();
+    let mover = match step.drop {
+        Some(role) => Piece { piece_role: role, piece_color: board.active_color },
+        None => board.pieces[step.from].expect("no piece on the from square"),
+    };
+
+    let mut occupied = position.occupied();
+    let mut captured = board.pieces[step.to].map_or(0, |p| piece_value(p.piece_role));
+    if step.drop.is_none() {
+        occupied ^= Bitboard::from_square(step.from.index());
+        // 吃过路兵时被吃的兵不在目标格上
+        if mover.piece_role == PieceRole::Pawn && board.en_passant_target == Some(step.to) {
+            let (df, dr) = forward(mover.piece_color);
+            let pawn = step.to.offset((-df, -dr)).unwrap();
+            occupied ^= Bitboard::from_square(pawn.index());
+            captured = piece_value(PieceRole::Pawn);
+        }
+    }
+    let mut on_square = mover.piece_role;
+    if let Some(role) = step.promotion {
+        captured += piece_value(role) - piece_value(PieceRole::Pawn);
+        on_square = role;
+    }
+
+    // gain[d] 为第 d 次吃子后吃子一方的得失，不考虑之后的吃子
+    let mut gain = vec![captured];
+    let mut color = mover.piece_color.flip();
+    loop {
+        let attackers = position.attackers(to, color, occupied) & occupied;
+        let Some((role, from)) = SEE_ORDER.into_iter().find_map(|role| {
+            (attackers & position.by_role[role.index()]).lsb().map(|sq| (role, sq))
+        }) else {
+            break
+        };
+        // 对方还能吃回来时王不能吃子
+        let without = occupied ^ Bitboard::from_square(from);
+        if role == PieceRole::King && (position.attackers(to, color.flip(), without) & without).any() {
+            break
+        }
+        gain.push(piece_value(on_square) - gain.last().unwrap());
+        on_square = role;
+        occupied = without;
+        color = color.flip();
+    }
+
+    // 从最后一次吃子往回推，每一方都可以选择不吃
+    while gain.len() > 1 {
+        let last = gain.pop().unwrap();
+        let prev = gain.last_mut().unwrap();
+        *prev = -(-*prev).max(last);
+    }
+    gain[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::read_fen;
+
+    #[test]
+    fn see_table() {
+        for (fen, uci, value) in [
+            // 吃掉没有保护的兵
+            ("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5", 100),
+            // 双方都有 X 光攻击：马吃兵之后依次换掉马、车、象和后
+            ("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5", -200),
+            ("4k3/8/1n6/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5", 0),
+            ("4k3/8/1n6/3p4/8/8/8/3QK3 w - - 0 1", "d1d5", -800),
+            // 两个车叠在一起，后面的车通过 X 光攻击参与交换
+            ("4k3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5", 100),
+            ("3rk3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5", -400),
+            // 对方还能吃回来时王不能吃子
+            ("3rk3/8/8/8/1b6/8/3p4/3RK3 w - - 0 1", "d1d2", -400),
+            ("3rk3/8/8/8/8/8/3p4/3RK3 w - - 0 1", "d1d2", 100),
+            // 吃过路兵和升变
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 100),
+            ("r6k/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7a8q", 1300),
+            ("1r5k/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q", -100),
+            // 不吃子的着法：走到被兵攻击的格子
+            ("4k3/8/8/3p4/8/8/8/2B1K3 w - - 0 1", "c1e3", 0),
+            ("4k3/8/8/3p4/8/8/8/1B2K3 w - - 0 1", "b1e4", -300),
+        ] {
+            let board = read_fen(fen.to_string()).unwrap();
+            assert_eq!(see(&board, uci.parse().unwrap()), value, "{} {}", fen, uci);
+        }
+    }
+}
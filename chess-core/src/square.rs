@@ -0,0 +1,195 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::piece::*;
+
+// 棋盘上的一格。file 为列（a 列为 0），rank 为行（第 1 行为 0）
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Square {
+    file: u8,
+    rank: u8,
+}
+
+// 方向偏移，(列的变化, 行的变化)，行增加的方向为白方的前方
+pub const NORTH: (isize, isize) = (0, 1);
+pub const SOUTH: (isize, isize) = (0, -1);
+pub const EAST: (isize, isize) = (1, 0);
+pub const WEST: (isize, isize) = (-1, 0);
+pub const NORTH_EAST: (isize, isize) = (1, 1);
+pub const NORTH_WEST: (isize, isize) = (-1, 1);
+pub const SOUTH_EAST: (isize, isize) = (1, -1);
+pub const SOUTH_WEST: (isize, isize) = (-1, -1);
+
+pub const ROOK_DIRECTIONS: [(isize, isize); 4] = [NORTH, SOUTH, EAST, WEST];
+pub const BISHOP_DIRECTIONS: [(isize, isize); 4] = [NORTH_EAST, NORTH_WEST, SOUTH_EAST, SOUTH_WEST];
+pub const KING_DIRECTIONS: [(isize, isize); 8] = [NORTH, SOUTH, EAST, WEST, NORTH_EAST, NORTH_WEST, SOUTH_EAST, SOUTH_WEST];
+pub const KNIGHT_OFFSETS: [(isize, isize); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+
+// 某一方的兵前进的方向
+pub fn forward(color: PieceColor) -> (isize, isize) {
+    match color {
+        PieceColor::White => NORTH,
+        PieceColor::Black => SOUTH,
+    }
+}
+
+// 棋盘的实际尺寸，不超过 BOARD_SIZE_I × BOARD_SIZE_J
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BoardSize {
+    pub files: usize,
+    pub ranks: usize,
+}
+
+impl BoardSize {
+    pub const STANDARD: BoardSize = BoardSize { files: 8, ranks: 8 };
+    pub const CAPABLANCA: BoardSize = BoardSize { files: 10, ranks: 8 };
+    pub const GRAND: BoardSize = BoardSize { files: 10, ranks: 10 };
+
+    // FEN 中可以使用的尺寸
+    pub const SUPPORTED: [BoardSize; 3] = [BoardSize::STANDARD, BoardSize::CAPABLANCA, BoardSize::GRAND];
+
+    pub fn contains(self, sq: Square) -> bool {
+        sq.file() < self.files && sq.rank() < self.ranks
+    }
+
+    // 某一方的底线
+    pub fn back_rank(self, color: PieceColor) -> usize {
+        match color {
+            PieceColor::White => 0,
+            PieceColor::Black => self.ranks - 1,
+        }
+    }
+
+    // 某一方的兵开局时所在的行，兵只能从这一行走两格。10 行的棋盘（大象棋）上兵从第 3 行出发
+    pub fn pawn_rank(self, color: PieceColor) -> usize {
+        let offset = if self.ranks >= 10 { 2 } else { 1 };
+        match color {
+            PieceColor::White => offset,
+            PieceColor::Black => self.ranks - 1 - offset,
+        }
+    }
+
+    // 棋盘上的所有格子，按编号排列
+    pub fn squares(self) -> impl Iterator<Item = Square> {
+        Square::all().filter(move |&sq| self.contains(sq))
+    }
+}
+
+impl Default for BoardSize {
+    fn default() -> Self {
+        BoardSize::STANDARD
+    }
+}
+
+impl Square {
+    pub fn new(file: usize, rank: usize) -> Self {
+        assert!(file < BOARD_SIZE_I && rank < BOARD_SIZE_J, "square ({}, {}) is off the board", file, rank);
+        Square { file: file as u8, rank: rank as u8 }
+    }
+
+    // 超出棋盘时返回 None
+    pub fn try_new(file: usize, rank: usize) -> Option<Self> {
+        (file < BOARD_SIZE_I && rank < BOARD_SIZE_J).then(|| Square::new(file, rank))
+    }
+
+    pub fn file(self) -> usize {
+        self.file as usize
+    }
+
+    pub fn rank(self) -> usize {
+        self.rank as usize
+    }
+
+    // 在位棋盘中的编号
+    pub fn index(self) -> usize {
+        self.file() + BOARD_SIZE_I * self.rank()
+    }
+
+    pub fn from_index(index: usize) -> Self {
+        Square::new(index % BOARD_SIZE_I, index / BOARD_SIZE_I)
+    }
+
+    // 沿偏移 (df, dr) 移动后的格子，超出棋盘时返回 None
+    pub fn offset(self, (df, dr): (isize, isize)) -> Option<Self> {
+        let file = self.file().checked_add_signed(df)?;
+        let rank = self.rank().checked_add_signed(dr)?;
+        Square::try_new(file, rank)
+    }
+
+    pub fn file_char(self) -> char {
+        (b'a' + self.file) as char
+    }
+
+    // 记谱中的行号，从 1 开始，可能有两位
+    pub fn rank_number(self) -> usize {
+        self.rank() + 1
+    }
+
+    // 最大尺寸的棋盘上的所有格子，按编号排列
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..BOARD_SIZE_I * BOARD_SIZE_J).map(Square::from_index)
+    }
+}
+
+// 代数记法，如 e4
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file_char(), self.rank_number())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ParseSquareError;
+
+impl fmt::Display for ParseSquareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid square")
+    }
+}
+
+impl std::error::Error for ParseSquareError {}
+
+impl FromStr for Square {
+    type Err = ParseSquareError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // 列为一个小写字母，行为不以 0 开头的数字
+        let mut chars = s.chars();
+        let Some(file) = chars.next().filter(char::is_ascii_lowercase) else {
+            return Err(ParseSquareError)
+        };
+        let rank = chars.as_str();
+        if rank.is_empty() || rank.len() > 2 || rank.starts_with('0') || !rank.chars().all(|c| c.is_ascii_digit()) {
+            return Err(ParseSquareError)
+        }
+        let file = file as usize - 'a' as usize;
+        let rank = rank.parse::<usize>().unwrap() - 1;
+        Square::try_new(file, rank).ok_or(ParseSquareError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn algebraic_round_trip() {
+        for sq in Square::all() {
+            assert_eq!(sq.to_string().parse::<Square>(), Ok(sq));
+        }
+        assert_eq!("e4".parse::<Square>(), Ok(Square::new(4, 3)));
+        assert_eq!("j10".parse::<Square>(), Ok(Square::new(9, 9)));
+        assert_eq!(Square::new(9, 9).to_string(), "j10");
+        for s in ["", "e", "e0", "e11", "k1", "E4", "e44", "e01"] {
+            assert_eq!(s.parse::<Square>(), Err(ParseSquareError), "{}", s);
+        }
+    }
+
+    #[test]
+    fn offsets() {
+        let e4 = Square::new(4, 3);
+        assert_eq!(e4.offset(NORTH_EAST), Some(Square::new(5, 4)));
+        assert_eq!(Square::new(0, 0).offset(WEST), None);
+        assert_eq!(Square::new(9, 9).offset(NORTH), None);
+        assert_eq!(KNIGHT_OFFSETS.iter().filter_map(|&d| e4.offset(d)).count(), 8);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{
+    piece::*,
+    board::*,
+    square::Square,
+};
+use regex::Regex;
+
+fn piece_name(piecerole: PieceRole) -> String {
+    match piecerole {
+        PieceRole::Pawn => "".to_string(),
+        PieceRole::Rook => "R".to_string(),
+        PieceRole::Knight => "N".to_string(),
+        PieceRole::Bishop => "B".to_string(),
+        PieceRole::Queen => "Q".to_string(),
+        PieceRole::King => "K".to_string(),
+        PieceRole::Archbishop => "A".to_string(),
+        PieceRole::Chancellor => "C".to_string(),
+    }
+}
+
+fn name_to_role(name: String) -> PieceRole {
+    match name.as_str() {
+        "" => PieceRole::Pawn,
+        "K" => PieceRole::King,
+        "Q" => PieceRole::Queen,
+        "R" => PieceRole::Rook,
+        "B" => PieceRole::Bishop,
+        "N" => PieceRole::Knight,
+        "A" => PieceRole::Archbishop,
+        "C" => PieceRole::Chancellor,
+        _ => {
+            unreachable!()
+        },
+    }
+}
+
+// 将一个表示步的字符串转换为Step. 
+pub fn read_step(board: &Board, s: String) -> Option<Step> {
+    let color = board.active_color;
+    if s.contains('@') {
+        // 放下手中的棋子，如 N@f3，兵可以写作 P@e4 或 @e4
+        let drop_re = Regex::new(r"^([QRBNACP]?)@([a-j](?:10|[1-9]))[+#]?$").unwrap();
+        let caps = drop_re.captures(&s)?;
+        let role = match &caps[1] {
+            "" | "P" => PieceRole::Pawn,
+            name => name_to_role(name.to_string()),
+        };
+        let step = Step::new_drop(role, caps[2].parse().ok()?);
+        let s = if s.starts_with('@') { format!("P{}", s) } else { s };
+        write_step(board, step).is_some_and(|res_s| s == res_s).then_some(step)
+    } else if s.starts_with("O-O") {
+        // 易位的起点和终点在 Chess960 中不固定，从所有着法中找写法相同的易位
+        all_move(board).into_iter()
+            .filter(|&step| is_castle(board, step))
+            .find(|&step| write_step(board, step).is_some_and(|res_s| s == res_s))
+    } else {
+        let chess_move_re = Regex::new(
+            r"^([KQRBNAC]?)([a-j]?(?:10|[1-9])?)(x?)([a-j](?:10|[1-9]))(?:=([QRBNKAC]))?([+#]?)$"
+        ).unwrap();
+        
+        let (role, target, promotion) = chess_move_re.captures(&s).map(|caps| (
+            caps.get(1).map_or("", |m| m.as_str()),  // role
+            caps.get(4).map_or("", |m| m.as_str()),  // target
+            caps.get(5).map(|m| m.as_str()),         // promotion
+        ))?;
+
+        let (role, target, promotion) = (
+            name_to_role(role.to_string()),
+            target.parse::<Square>().ok()?,
+            promotion.map(|p| name_to_role(p.to_string())),
+        );
+
+        
+        for from in Square::all() {
+            let Some(p) = board.pieces[from] else {
+                continue;
+            };
+            if p.piece_color != color || p.piece_role != role {
+                continue;
+            }
+            let step = Step {
+                from,
+                to: target,
+                promotion,
+                drop: None,
+            };
+            if write_step(board, step).is_some_and(|res_s| s == res_s) {
+                return Some(step)
+            }
+        }
+
+        None
+    }
+}
+
+// 走完一步后的将军或将杀标记
+fn check_suffix(new_board: &Board) -> &'static str {
+    let check = new_board.variant.rules().in_check(new_board, new_board.active_color);
+    let checkmate = match end_game(new_board) {
+        Some(res) => match res {
+            BoardResult::Winner(_, reason) => reason == WinReason::Checkmate,
+            BoardResult::Draw(_) => false,
+        },
+        None => false,
+    };
+    if checkmate { 
+        "#" 
+    } else if check {
+        "+"
+    } else {
+        ""
+    }
+}
+
+pub fn write_step(board: &Board, step: Step) -> Option<String> {
+    let step = normalize_step(board, step);
+    let new_board = try_move(board, step)?;
+
+    if let Some(role) = step.drop {
+        let name = match role {
+            PieceRole::Pawn => "P".to_string(),
+            role => piece_name(role),
+        };
+        return Some(format!("{}@{}{}", name, step.to, check_suffix(&new_board)))
+    }
+
+    let Some(piece) = &board.pieces[step.from] else {
+        unreachable!()
+    };
+    let role = piece.piece_role;
+    let flags = move_flags(board, step);
+
+    let name = piece_name(role);
+    let check_string = check_suffix(&new_board);
+
+    // 处理王车易位
+    if flags.castle {
+        let castle = match castle_side(step) {
+            CastlingSide::KingSide => "O-O",
+            CastlingSide::QueenSide => "O-O-O",
+        };
+        return Some(format!("{}{}", castle, check_string))
+    }
+
+    // 处理升变
+    let promotion = match step.promotion {
+        Some(p) if flags.promotion => format!("={}", piece_name(p)),
+        _ => String::new(),
+    };
+
+    // 消除歧义。同种棋子可能走到同一格时，加出发格的行或列。
+    let mut ambiguous = vec![step.from];
+    for from in Square::all() {
+        if from == step.from || board.pieces[from] != Some(*piece) {
+            continue;
+        }
+        if try_move(board, Step { from, ..step }).is_some() {
+            ambiguous.push(from);
+        }
+    }
+    let disambiguate = if ambiguous.len() == 1 {
+        // 唯一的位置，兵吃子的情况需要消除歧义，否则不需要消除歧义
+        if flags.capture && role == PieceRole::Pawn {
+            step.from.file_char().to_string()
+        } else {
+            String::new()
+        }
+    } else if ambiguous.iter().filter(|sq| sq.file() == step.from.file()).count() == 1 {
+        // 唯一横坐标，使用出发格的横坐标 (a-j)
+        step.from.file_char().to_string()
+    } else if ambiguous.iter().filter(|sq| sq.rank() == step.from.rank()).count() == 1 {
+        // 唯一纵坐标，使用出发格的纵坐标 (1-10)
+        step.from.rank_number().to_string()
+    } else {
+        // 都不是，需要完整坐标 (如 a1)
+        step.from.to_string()
+    };
+
+    Some(format!(
+        "{}{}{}{}{}{}", 
+        name,
+        disambiguate,
+        if flags.capture { "x" } else { "" },
+        step.to,
+        promotion,
+        check_string,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::read_fen;
+
+    #[test]
+    fn promotion_san() {
+        let promotion = |from: &str, to: &str, role| Step::new(from.parse().unwrap(), to.parse().unwrap()).with_promotion(role);
+        for (fen, step, san) in [
+            ("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1", promotion("e7", "d8", PieceRole::Knight), "exd8=N"),
+            ("k7/8/8/8/8/8/1p6/6K1 b - - 0 1", promotion("b2", "b1", PieceRole::Rook), "b1=R+"),
+            ("1n5k/P7/8/8/8/8/8/4K3 w - - 0 1", promotion("a7", "b8", PieceRole::Bishop), "axb8=B"),
+            // 两个兵都能吃到 b8 升变，用出发列区分
+            ("1n5k/P1P5/8/8/8/8/8/4K3 w - - 0 1", promotion("a7", "b8", PieceRole::Queen), "axb8=Q+"),
+            ("1n5k/P1P5/8/8/8/8/8/4K3 w - - 0 1", promotion("c7", "b8", PieceRole::Queen), "cxb8=Q+"),
+        ] {
+            let board = read_fen(fen.to_string()).unwrap();
+            assert_eq!(write_step(&board, step).as_deref(), Some(san));
+            assert_eq!(read_step(&board, san.to_string()), Some(step));
+        }
+        let board = read_fen("1n5k/P1P5/8/8/8/8/8/4K3 w - - 0 1".to_string()).unwrap();
+        assert_eq!(read_step(&board, "xb8=Q+".to_string()), None);
+        assert_eq!(read_step(&board, "axb8=Q".to_string()), None);
+        // 没有写出升变的棋子时不是合法的写法
+        assert_eq!(read_step(&board, "axb8".to_string()), None);
+    }
+}
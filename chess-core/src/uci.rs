@@ -0,0 +1,546 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{mpsc::{self, Receiver, RecvTimeoutError}, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::{
+    board::*, engine::{Score, SearchLimit, SearchResult}, fen::{read_fen, write_fen}, piece::PieceColor, step::write_step,
+    variant::VariantKind,
+};
+
+// UCI 协议。作为客户端时驱动外部引擎：启动进程、握手、设置选项和局面、开始和停止搜索，并解析引擎的输出。
+// 作为引擎时（bevy_chess-uci）解析 position 和 go 命令，输出 info 行
+
+// 握手和等待 readyok 的最长时间
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 引擎 info 输出中的一条变例
+#[derive(Clone, Debug, PartialEq)]
+pub struct UciInfo {
+    pub depth: Option<usize>,
+    pub multipv: usize,         // 第几条变例，从 1 开始，引擎没有给出时为 1
+    pub score: Option<Score>,   // 从行动方的角度
+    pub nodes: Option<u64>,
+    pub pv: Vec<Step>,
+}
+
+// 引擎输出中需要处理的行
+#[derive(Clone, Debug, PartialEq)]
+pub enum UciEvent {
+    Info(UciInfo),
+    BestMove(Option<Step>),     // 没有合法着法时为 None
+}
+
+#[derive(Debug)]
+pub enum UciError {
+    Io(io::Error),
+    Timeout(&'static str),      // 等待的回应，如 "uciok"
+    Exited,
+    Unsupported(&'static str),  // 引擎不支持的变体，如 "Crazyhouse"
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UciError::Io(e) => write!(f, "{}", e),
+            UciError::Timeout(reply) => write!(f, "engine did not send \"{}\" in time", reply),
+            UciError::Exited => write!(f, "engine exited"),
+            UciError::Unsupported(variant) => write!(f, "engine does not support {}", variant),
+        }
+    }
+}
+
+impl std::error::Error for UciError {}
+
+impl From<io::Error> for UciError {
+    fn from(e: io::Error) -> Self {
+        UciError::Io(e)
+    }
+}
+
+// 解析引擎输出的一行。info string 和不含分数、变例的 info 行被忽略
+pub fn parse_line(line: &str) -> Option<UciEvent> {
+    let mut tokens = line.split_whitespace();
+    match tokens.next()? {
+        "bestmove" => Some(UciEvent::BestMove(tokens.next().and_then(|s| s.parse().ok()))),
+        "info" => {
+            let mut info = UciInfo {
+                depth: None,
+                multipv: 1,
+                score: None,
+                nodes: None,
+                pv: Vec::new(),
+            };
+            while let Some(token) = tokens.next() {
+                match token {
+                    "string" => return None,
+                    "depth" => info.depth = tokens.next()?.parse().ok(),
+                    "multipv" => info.multipv = tokens.next()?.parse().ok()?,
+                    "nodes" => info.nodes = tokens.next()?.parse().ok(),
+                    "score" => {
+                        let kind = tokens.next()?;
+                        let value = tokens.next()?.parse().ok()?;
+                        info.score = match kind {
+                            "cp" => Some(Score::Cp(value)),
+                            "mate" => Some(Score::Mate(value)),
+                            _ => None,
+                        };
+                    }
+                    // pv 总是在最后，遇到无法解析的着法时停止
+                    "pv" => {
+                        info.pv = tokens.by_ref().map_while(|s| s.parse().ok()).collect();
+                    }
+                    _ => {},
+                }
+            }
+            (info.score.is_some() || !info.pv.is_empty()).then_some(UciEvent::Info(info))
+        }
+        _ => None,
+    }
+}
+
+// 把变例写成 SAN，遇到不合法的着法时停止
+pub fn pv_san(board: &Board, pv: &[Step]) -> Vec<String> {
+    let mut board = board.clone();
+    let mut sans = Vec::new();
+    for &step in pv {
+        let (Some(san), Some(new_board)) = (write_step(&board, step), try_move(&board, step)) else {
+            break
+        };
+        sans.push(san);
+        board = new_board;
+    }
+    sans
+}
+
+// 变体在 UCI_Variant 选项中的名字，沿用 Fairy-Stockfish 等多变体引擎的写法。暗棋和双人四人象棋无法用 UCI 表示
+pub fn uci_variant(variant: VariantKind) -> Option<&'static str> {
+    match variant {
+        VariantKind::Standard => Some("chess"),
+        VariantKind::KingOfTheHill => Some("kingofthehill"),
+        VariantKind::ThreeCheck => Some("3check"),
+        VariantKind::RacingKings => Some("racingkings"),
+        VariantKind::Crazyhouse => Some("crazyhouse"),
+        VariantKind::Atomic => Some("atomic"),
+        VariantKind::Antichess => Some("antichess"),
+        VariantKind::Capablanca => Some("capablanca"),
+        VariantKind::Grand => Some("grand"),
+        VariantKind::DarkChess | VariantKind::Bughouse => None,
+    }
+}
+
+// 引擎选项的文本，每行一个 "名字 = 值"，忽略空行
+pub fn parse_options(text: &str) -> Vec<(String, String)> {
+    text.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+// UCI 的 go 命令。没有限制时为 go infinite，需要用 stop 停止
+pub fn go_command(limits: &[SearchLimit]) -> String {
+    if limits.is_empty() {
+        return "go infinite".to_string()
+    }
+    let mut command = "go".to_string();
+    for limit in limits {
+        command += &match limit {
+            SearchLimit::Depth(depth) => format!(" depth {}", depth),
+            SearchLimit::Nodes(nodes) => format!(" nodes {}", nodes),
+            SearchLimit::Time(time) => format!(" movetime {}", time.as_millis()),
+        };
+    }
+    command
+}
+
+// position 命令的参数：startpos 或 fen <FEN>，之后可以有 moves <着法>...
+// 返回走完所有着法后的局面和此前的所有盘面，FEN 或着法不合法时返回 None。
+// chess960 为 true 时（UCI_Chess960 选项）按 Chess960 的规则记录易位，即王走到车的格子上
+pub fn parse_position(args: &str, chess960: bool) -> Option<(Board, Vec<Board>)> {
+    let (setup, moves) = match args.split_once("moves") {
+        Some((setup, moves)) => (setup.trim(), moves),
+        None => (args.trim(), ""),
+    };
+    let mut board = match setup.split_once(' ') {
+        _ if setup == "startpos" => Board::default(),
+        Some(("fen", fen)) => read_fen(fen.trim().to_string()).ok()?,
+        _ => return None,
+    };
+    board.chess960 |= chess960;
+    let mut history = Vec::new();
+    for token in moves.split_whitespace() {
+        let new_board = try_move(&board, token.parse().ok()?)?;
+        history.push(std::mem::replace(&mut board, new_board));
+    }
+    Some((board, history))
+}
+
+// go 命令的参数
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct GoOptions {
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub time: [Option<Duration>; 2],        // 双方的剩余时间，按颜色的下标排列
+    pub increment: [Option<Duration>; 2],
+    pub movestogo: Option<u32>,
+    pub infinite: bool,
+}
+
+// 没有给出 movestogo 时，假设还要走这么多步
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+// 只有 go、没有任何限制时搜索的深度
+pub const DEFAULT_GO_DEPTH: usize = 6;
+
+impl GoOptions {
+    pub fn parse(args: &str) -> Self {
+        let mut go = GoOptions::default();
+        let mut tokens = args.split_whitespace();
+        let millis = |token: Option<&str>| token.and_then(|t| t.parse().ok()).map(Duration::from_millis);
+        while let Some(token) = tokens.next() {
+            match token {
+                "depth" => go.depth = tokens.next().and_then(|t| t.parse().ok()),
+                "nodes" => go.nodes = tokens.next().and_then(|t| t.parse().ok()),
+                "movetime" => go.movetime = millis(tokens.next()),
+                "wtime" => go.time[PieceColor::White.index()] = millis(tokens.next()),
+                "btime" => go.time[PieceColor::Black.index()] = millis(tokens.next()),
+                "winc" => go.increment[PieceColor::White.index()] = millis(tokens.next()),
+                "binc" => go.increment[PieceColor::Black.index()] = millis(tokens.next()),
+                "movestogo" => go.movestogo = tokens.next().and_then(|t| t.parse().ok()),
+                "infinite" => go.infinite = true,
+                _ => {},
+            }
+        }
+        go
+    }
+
+    // color 方的搜索限制。给出剩余时间时，每步用剩余时间的 1/movestogo 加上大部分加秒，且不超过剩余时间的一半。
+    // 没有任何限制时搜索到 DEFAULT_GO_DEPTH 层，保证会给出 bestmove
+    pub fn limits(&self, color: PieceColor) -> Vec<SearchLimit> {
+        let mut limits = Vec::new();
+        if self.infinite {
+            return limits
+        }
+        limits.extend(self.depth.map(SearchLimit::Depth));
+        limits.extend(self.nodes.map(SearchLimit::Nodes));
+        limits.extend(self.movetime.map(SearchLimit::Time));
+        if let Some(time) = self.time[color.index()] {
+            let increment = self.increment[color.index()].unwrap_or_default();
+            let share = time / self.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1) + increment * 3 / 4;
+            limits.push(SearchLimit::Time(share.min(time / 2)));
+        }
+        if limits.is_empty() {
+            limits.push(SearchLimit::Depth(DEFAULT_GO_DEPTH));
+        }
+        limits
+    }
+}
+
+// 分数的 UCI 写法，如 cp 35、mate -2
+pub fn score_text(score: Score) -> String {
+    match score {
+        Score::Cp(cp) => format!("cp {}", cp),
+        Score::Mate(n) => format!("mate {}", n),
+    }
+}
+
+// 搜索完一层后输出的 info 行，multipv 从 1 开始
+pub fn info_line(result: &SearchResult, multipv: usize, elapsed: Duration) -> String {
+    let millis = elapsed.as_millis().max(1) as u64;
+    let pv: Vec<String> = result.pv.iter().map(|step| step.to_string()).collect();
+    format!(
+        "info depth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+        result.depth, multipv, score_text(result.score), result.nodes, result.nodes * 1000 / millis, millis, pv.join(" "),
+    )
+}
+
+// 一个正在运行的外部引擎。引擎的输出由单独的线程逐行读取
+pub struct UciEngine {
+    pub name: String,
+    pub options: Vec<String>,   // 引擎支持的选项名
+    rules: (VariantKind, bool), // 引擎当前的变体和 UCI_Chess960 选项
+    child: Child,
+    stdin: ChildStdin,
+    // 放在 Mutex 中只是为了让 UciEngine 可以在线程之间共享。方法都取 &mut self，读取时不需要加锁
+    lines: Mutex<Receiver<String>>,
+}
+
+impl UciEngine {
+    // 启动引擎，完成握手并设置选项
+    pub fn start(path: &str, options: &[(String, String)]) -> Result<Self, UciError> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break
+                }
+            }
+        });
+
+        let mut engine = UciEngine {
+            name: path.to_string(),
+            options: Vec::new(),
+            rules: (VariantKind::Standard, false),
+            child,
+            stdin,
+            lines: Mutex::new(lines),
+        };
+        engine.send("uci")?;
+        for line in engine.wait_for("uciok", HANDSHAKE_TIMEOUT)? {
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if let Some(option) = line.strip_prefix("option name ") {
+                let name = option.split(" type ").next().unwrap_or(option);
+                engine.options.push(name.trim().to_string());
+            }
+        }
+        for (name, value) in options {
+            engine.set_option(name, value)?;
+        }
+        engine.is_ready()?;
+        Ok(engine)
+    }
+
+    pub fn send(&mut self, command: &str) -> Result<(), UciError> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), UciError> {
+        self.send(&format!("setoption name {} value {}", name, value))
+    }
+
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options.iter().any(|option| option == name)
+    }
+
+    // 按 board 的变体和是否为 Chess960 设置 UCI_Variant 和 UCI_Chess960，只在和上次不同时发送。
+    // 引擎没有提供需要的选项时返回 UciError::Unsupported
+    pub fn set_rules(&mut self, board: &Board) -> Result<(), UciError> {
+        let (variant, chess960) = self.rules;
+        if board.chess960 != chess960 {
+            if !self.has_option("UCI_Chess960") {
+                return Err(UciError::Unsupported("Chess960"))
+            }
+            self.set_option("UCI_Chess960", if board.chess960 { "true" } else { "false" })?;
+        }
+        if board.variant != variant {
+            let name = uci_variant(board.variant)
+                .filter(|_| self.has_option("UCI_Variant"))
+                .ok_or(UciError::Unsupported(board.variant.name()))?;
+            self.set_option("UCI_Variant", name)?;
+        }
+        self.rules = (board.variant, board.chess960);
+        Ok(())
+    }
+
+    // 等待引擎处理完之前的命令
+    pub fn is_ready(&mut self) -> Result<(), UciError> {
+        self.send("isready")?;
+        self.wait_for("readyok", HANDSHAKE_TIMEOUT).map(|_| ())
+    }
+
+    // 从 root 开始走 steps 得到的局面。同时给出着法而不只是当前局面，引擎才能判断重复局面。
+    // 变体和 Chess960 与之前的局面不同时先设置引擎的选项
+    pub fn set_position(&mut self, root: &Board, steps: &[Step]) -> Result<(), UciError> {
+        self.set_rules(root)?;
+        let mut command = format!("position fen {}", write_fen(root.clone()));
+        if !steps.is_empty() {
+            command += " moves";
+            for step in steps {
+                command += &format!(" {}", step);
+            }
+        }
+        self.send(&command)
+    }
+
+    pub fn go(&mut self, limits: &[SearchLimit]) -> Result<(), UciError> {
+        self.send(&go_command(limits))
+    }
+
+    pub fn stop(&mut self) -> Result<(), UciError> {
+        self.send("stop")
+    }
+
+    // 取出引擎已经输出的所有事件，不等待
+    pub fn poll(&mut self) -> Result<Vec<UciEvent>, UciError> {
+        let mut events = Vec::new();
+        loop {
+            match self.lines.get_mut().unwrap().try_recv() {
+                Ok(line) => events.extend(parse_line(&line)),
+                Err(mpsc::TryRecvError::Empty) => return Ok(events),
+                Err(mpsc::TryRecvError::Disconnected) => return Err(UciError::Exited),
+            }
+        }
+    }
+
+    // 等待 bestmove，返回最佳着法和此前的所有 info。超过 timeout 时返回错误
+    pub fn wait_best_move(&mut self, timeout: Duration) -> Result<(Option<Step>, Vec<UciInfo>), UciError> {
+        let mut infos = Vec::new();
+        let deadline = Instant::now() + timeout;
+        loop {
+            match parse_line(&self.recv_until(deadline, "bestmove")?) {
+                Some(UciEvent::Info(info)) => infos.push(info),
+                Some(UciEvent::BestMove(step)) => return Ok((step, infos)),
+                None => {},
+            }
+        }
+    }
+
+    // 读取到以 reply 开头的一行为止，返回此前的所有行
+    fn wait_for(&mut self, reply: &'static str, timeout: Duration) -> Result<Vec<String>, UciError> {
+        let deadline = Instant::now() + timeout;
+        let mut lines = Vec::new();
+        loop {
+            let line = self.recv_until(deadline, reply)?;
+            if line.trim() == reply {
+                return Ok(lines)
+            }
+            lines.push(line);
+        }
+    }
+
+    fn recv_until(&mut self, deadline: Instant, reply: &'static str) -> Result<String, UciError> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.lines.get_mut().unwrap().recv_timeout(timeout).map_err(|e| match e {
+            RecvTimeoutError::Timeout => UciError::Timeout(reply),
+            RecvTimeoutError::Disconnected => UciError::Exited,
+        })
+    }
+}
+
+// 退出时先请求引擎退出，再结束进程
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_engine_output() {
+        assert_eq!(
+            parse_line("info depth 12 seldepth 18 multipv 2 score cp -35 upperbound nodes 4096 nps 1000 pv e2e4 e7e5 g1f3"),
+            Some(UciEvent::Info(UciInfo {
+                depth: Some(12),
+                multipv: 2,
+                score: Some(Score::Cp(-35)),
+                nodes: Some(4096),
+                pv: vec!["e2e4".parse().unwrap(), "e7e5".parse().unwrap(), "g1f3".parse().unwrap()],
+            })),
+        );
+        let Some(UciEvent::Info(info)) = parse_line("info depth 5 score mate -3 pv h7h8q") else {
+            panic!()
+        };
+        assert_eq!((info.multipv, info.score), (1, Some(Score::Mate(-3))));
+        assert_eq!(parse_line("info string NNUE enabled"), None);
+        assert_eq!(parse_line("info depth 3 currmove e2e4 currmovenumber 1"), None);
+        assert_eq!(parse_line("bestmove e2e4 ponder e7e5"), Some(UciEvent::BestMove(Some("e2e4".parse().unwrap()))));
+        assert_eq!(parse_line("bestmove (none)"), Some(UciEvent::BestMove(None)));
+
+        let board = Board::default();
+        let pv: Vec<Step> = ["e2e4", "e7e5", "e1e3"].iter().map(|s| s.parse().unwrap()).collect();
+        assert_eq!(pv_san(&board, &pv), vec!["e4", "e5"]);
+        assert_eq!(parse_options("Threads = 2\n\nHash=64\n"), vec![
+            ("Threads".to_string(), "2".to_string()),
+            ("Hash".to_string(), "64".to_string()),
+        ]);
+        assert_eq!(go_command(&[SearchLimit::Depth(8), SearchLimit::Time(Duration::from_secs(2))]), "go depth 8 movetime 2000");
+        assert_eq!(go_command(&[]), "go infinite");
+    }
+
+    #[test]
+    fn server_commands() {
+        let (board, history) = parse_position("startpos moves e2e4 e7e5 g1f3", false).unwrap();
+        assert_eq!(write_fen(board), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+        assert_eq!(history.len(), 3);
+        let (board, history) = parse_position("fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves e1g1", false).unwrap();
+        assert_eq!(write_fen(board), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+        assert!(history[0] == read_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1".to_string()).unwrap());
+        assert!(parse_position("startpos moves e2e5", false).is_none());
+        assert!(parse_position("fen 8/8 w - - 0 1", false).is_none());
+        // UCI_Chess960 打开时易位写作王走到车上
+        let (board, _) = parse_position("startpos moves e2e4 e7e5 g1f3 b8c6 f1c4 g8f6", true).unwrap();
+        let castle: Step = "e1h1".parse().unwrap();
+        assert!(board.chess960 && all_move(&board).contains(&castle));
+        let (board, _) = parse_position("startpos moves e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 e1h1", true).unwrap();
+        assert_eq!(write_fen(board).split(' ').next(), Some("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1"));
+
+        let go = GoOptions::parse("wtime 60000 btime 30000 winc 1000 binc 1000");
+        assert_eq!(go.limits(PieceColor::White), vec![SearchLimit::Time(Duration::from_millis(2750))]);
+        assert_eq!(go.limits(PieceColor::Black), vec![SearchLimit::Time(Duration::from_millis(1750))]);
+        let go = GoOptions::parse("btime 1000 movestogo 1");
+        assert_eq!(go.limits(PieceColor::Black), vec![SearchLimit::Time(Duration::from_millis(500))]);
+        assert_eq!(GoOptions::parse("depth 6 movetime 100").limits(PieceColor::White), vec![
+            SearchLimit::Depth(6),
+            SearchLimit::Time(Duration::from_millis(100)),
+        ]);
+        assert!(GoOptions::parse("infinite").limits(PieceColor::White).is_empty());
+        assert_eq!(GoOptions::parse("").limits(PieceColor::White), vec![SearchLimit::Depth(DEFAULT_GO_DEPTH)]);
+
+        let result = SearchResult {
+            best: Some("e2e4".parse().unwrap()),
+            pv: vec!["e2e4".parse().unwrap(), "e7e5".parse().unwrap()],
+            score: Score::Mate(-2),
+            depth: 4,
+            nodes: 5000,
+        };
+        assert_eq!(info_line(&result, 1, Duration::from_millis(500)), "info depth 4 multipv 1 score mate -2 nodes 5000 nps 10000 time 500 pv e2e4 e7e5");
+    }
+
+    // 用 shell 脚本模拟一个引擎：收到 go 后输出两条 info 和 bestmove，并回显收到的局面
+    #[cfg(unix)]
+    #[test]
+    fn fake_engine() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = "#!/bin/sh\n\
+            while read -r line; do\n\
+              case \"$line\" in\n\
+                uci) echo 'id name Fake Engine'; echo 'option name MultiPV type spin default 1 min 1 max 4'; echo 'option name UCI_Variant type combo default chess var chess var crazyhouse'; echo uciok ;;\n\
+                isready) echo readyok ;;\n\
+                position*) echo \"info string $line\" ;;\n\
+                go*) echo 'info depth 1 score cp 20 pv e2e4'; echo 'info depth 2 score mate 3 pv e2e4 e7e5'; echo 'bestmove e2e4 ponder e7e5' ;;\n\
+                quit) exit 0 ;;\n\
+              esac\n\
+            done\n";
+        let path = std::env::temp_dir().join(format!("fake_uci_engine_{}.sh", std::process::id()));
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut engine = UciEngine::start(path.to_str().unwrap(), &[("MultiPV".to_string(), "2".to_string())]).unwrap();
+        assert_eq!(engine.name, "Fake Engine");
+        assert_eq!(engine.options, vec!["MultiPV", "UCI_Variant"]);
+        engine.set_position(&Board::default(), &["d2d4".parse().unwrap()]).unwrap();
+        engine.go(&[SearchLimit::Depth(2)]).unwrap();
+        let (best, infos) = engine.wait_best_move(HANDSHAKE_TIMEOUT).unwrap();
+        assert_eq!(best, Some("e2e4".parse().unwrap()));
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[1].score, Some(Score::Mate(3)));
+        assert_eq!(infos[1].depth, Some(2));
+        // 引擎有 UCI_Variant 选项，但没有 UCI_Chess960
+        engine.set_position(&VariantKind::Crazyhouse.rules().start_position(), &[]).unwrap();
+        assert!(matches!(engine.set_position(&VariantKind::DarkChess.rules().start_position(), &[]), Err(UciError::Unsupported("Dark Chess"))));
+        assert!(matches!(engine.set_position(&crate::chess960::start_position(0), &[]), Err(UciError::Unsupported("Chess960"))));
+        drop(engine);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(UciEngine::start("/nonexistent/engine", &[]), Err(UciError::Io(_))));
+    }
+}
@@ -0,0 +1,256 @@
+use std::fmt;
+use crate::{
+    board::*, movegen::Position, piece::*, square::*, zobrist,
+};
+
+// 局面中不可能出现的情况
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PositionIssue {
+    MissingKing(PieceColor),
+    TooManyKings(PieceColor, usize),
+    PawnOnBackRank(Square),
+    TooManyPawns(PieceColor, usize),
+    TooManyPromotedPieces(PieceColor),  // 多出的后、车、象、马超过了已经消失的兵数
+    OpponentInCheck,                    // 不行棋的一方正被将军
+    ImpossibleCheck,                    // 三个以上的将军，或者不可能由闪击形成的双将
+    InvalidCastling { color: PieceColor, kingside: bool },
+    InvalidEnPassant(Square),
+}
+
+impl PositionIssue {
+    // 致命的问题会让走法生成或胜负判断失去意义，宽松模式下也不能接受
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            PositionIssue::MissingKing(_)
+                | PositionIssue::TooManyKings(..)
+                | PositionIssue::PawnOnBackRank(_)
+                | PositionIssue::OpponentInCheck
+        )
+    }
+}
+
+fn color_name(color: PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "white",
+        PieceColor::Black => "black",
+    }
+}
+
+impl fmt::Display for PositionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PositionIssue::MissingKing(c) => write!(f, "{} has no king", color_name(c)),
+            PositionIssue::TooManyKings(c, n) => write!(f, "{} has {} kings", color_name(c), n),
+            PositionIssue::PawnOnBackRank(sq) => write!(f, "pawn on back rank at {}", sq),
+            PositionIssue::TooManyPawns(c, n) => write!(f, "{} has {} pawns", color_name(c), n),
+            PositionIssue::TooManyPromotedPieces(c) => write!(f, "{} has more promoted pieces than missing pawns", color_name(c)),
+            PositionIssue::OpponentInCheck => write!(f, "side not to move is in check"),
+            PositionIssue::ImpossibleCheck => write!(f, "impossible check"),
+            PositionIssue::InvalidCastling { color, kingside } => write!(
+                f, "{} cannot castle {}", color_name(color), if kingside { "kingside" } else { "queenside" },
+            ),
+            PositionIssue::InvalidEnPassant(sq) => write!(f, "impossible en passant square {}", sq),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum ValidationMode {
+    // 任何问题都拒绝
+    Strict,
+    // 只拒绝致命问题，不一致的易位权和吃过路兵格会被清除，不可能的子力和将军会被容忍
+    #[default]
+    Lenient,
+}
+
+// 列出局面的所有问题，合法局面返回空列表
+pub fn validate(board: &Board) -> Vec<PositionIssue> {
+    let position = Position::from_board(board);
+    let mut issues = Vec::new();
+
+    // 1. 王的数量。王不受保护的变体中王的数量不限
+    let rules = board.variant.rules();
+    let royal_colors = if rules.royal_king() { &PieceColor::ALL[..] } else { &[][..] };
+    for &color in royal_colors {
+        match position.pieces(color, PieceRole::King).count() {
+            0 => issues.push(PositionIssue::MissingKing(color)),
+            1 => {},
+            n => issues.push(PositionIssue::TooManyKings(color, n)),
+        }
+    }
+
+    // 2. 兵不能在底线
+    let pawns = position.pieces(PieceColor::White, PieceRole::Pawn) | position.pieces(PieceColor::Black, PieceRole::Pawn);
+    for sq in pawns.into_iter().map(Square::from_index) {
+        if sq.rank() == 0 || sq.rank() == board.size.ranks - 1 {
+            issues.push(PositionIssue::PawnOnBackRank(sq));
+        }
+    }
+
+    // 3. 子力数量：兵不超过列数，多出的棋子只能来自升变。疯狂屋中可以放下对方的棋子，不检查
+    let material_colors = if rules.has_pockets() { &[][..] } else { &PieceColor::ALL[..] };
+    for &color in material_colors {
+        let count = |role| position.pieces(color, role).count();
+        let pawn_count = count(PieceRole::Pawn);
+        let files = board.size.files;
+        if pawn_count > files {
+            issues.push(PositionIssue::TooManyPawns(color, pawn_count));
+            continue;
+        }
+        let promoted = count(PieceRole::Queen).saturating_sub(1)
+            + count(PieceRole::Rook).saturating_sub(2)
+            + count(PieceRole::Bishop).saturating_sub(2)
+            + count(PieceRole::Knight).saturating_sub(2)
+            + count(PieceRole::Archbishop).saturating_sub(1)
+            + count(PieceRole::Chancellor).saturating_sub(1);
+        if promoted > files - pawn_count {
+            issues.push(PositionIssue::TooManyPromotedPieces(color));
+        }
+    }
+
+    // 4. 将军：不行棋的一方不能被将军；双将中至少有一个是被闪出来的远程棋子
+    let active = board.active_color;
+    if rules.in_check(board, active.flip()) {
+        issues.push(PositionIssue::OpponentInCheck);
+    }
+    if rules.in_check(board, active) {
+        let checkers = position.checkers();
+        let sliders = checkers & !(position.pieces(active.flip(), PieceRole::Pawn) | position.pieces(active.flip(), PieceRole::Knight));
+        if checkers.count() > 2 || (checkers.count() == 2 && sliders.is_empty()) {
+            issues.push(PositionIssue::ImpossibleCheck);
+        }
+    }
+
+    // 5. 易位权：王在底线（标准规则下在中间，8 列的棋盘上为 e 列），对应的车在底线上王的同一侧
+    for (color, side, rook_x) in board.castling_availability.iter() {
+        let y = board.size.back_rank(color);
+        let has = |x: usize, role| board.pieces[x][y] == Some(Piece { piece_role: role, piece_color: color });
+        let king_x = (0..board.size.files).find(|&x| has(x, PieceRole::King));
+        let valid = king_x.is_some_and(|king_x| {
+            let on_side = match side {
+                CastlingSide::KingSide => rook_x > king_x,
+                CastlingSide::QueenSide => rook_x < king_x,
+            };
+            on_side && has(rook_x, PieceRole::Rook) && (board.chess960 || king_x == board.size.files / 2)
+        });
+        if !valid {
+            issues.push(PositionIssue::InvalidCastling { color, kingside: side == CastlingSide::KingSide });
+        }
+    }
+
+    // 6. 吃过路兵格：必须在对方刚走过两步的兵身后，且该格和兵的出发格都为空
+    if let Some(target) = board.en_passant_target {
+        // 对方兵的出发行再往前一行，8 行的棋盘上为第 6 行或第 3 行
+        let start = board.size.pawn_rank(active.flip());
+        let target_rank = match active {
+            PieceColor::White => start - 1,
+            PieceColor::Black => start + 1,
+        };
+        let pawn = target.offset(forward(active.flip()));
+        let origin = target.offset(forward(active));
+        let valid = target.rank() == target_rank
+            && pawn.is_some_and(|sq| board.pieces[sq] == Some(Piece { piece_role: PieceRole::Pawn, piece_color: active.flip() }))
+            && board.pieces[target].is_none()
+            && origin.is_some_and(|sq| board.pieces[sq].is_none());
+        if !valid {
+            issues.push(PositionIssue::InvalidEnPassant(target));
+        }
+    }
+
+    issues
+}
+
+// 按给定模式检查局面。严格模式下有任何问题都返回错误；宽松模式下只有致命问题返回错误，
+// 其他问题中不一致的易位权和吃过路兵格会被清除
+pub fn check_position(board: Board, mode: ValidationMode) -> Result<Board, Vec<PositionIssue>> {
+    let issues = validate(&board);
+    match mode {
+        ValidationMode::Strict => {
+            if issues.is_empty() { Ok(board) } else { Err(issues) }
+        }
+        ValidationMode::Lenient => {
+            let fatal: Vec<PositionIssue> = issues.iter().copied().filter(PositionIssue::is_fatal).collect();
+            if !fatal.is_empty() {
+                return Err(fatal);
+            }
+            let mut board = board;
+            for issue in issues {
+                match issue {
+                    PositionIssue::InvalidCastling { color, kingside } => {
+                        let side = if kingside { CastlingSide::KingSide } else { CastlingSide::QueenSide };
+                        board.castling_availability.set(color, side, None);
+                    },
+                    PositionIssue::InvalidEnPassant(_) => board.en_passant_target = None,
+                    _ => {},
+                }
+            }
+            board.zobrist = zobrist::compute(&board);
+            Ok(board)
+        }
+    }
+}
+
+// 把问题列表合成一行，用于界面显示
+pub fn describe_issues(issues: &[PositionIssue]) -> String {
+    issues.iter().map(|issue| issue.to_string()).collect::<Vec<_>>().join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::*;
+
+    fn issues(fen: &str) -> Vec<PositionIssue> {
+        validate(&read_fen(fen.to_string()).unwrap())
+    }
+
+    #[test]
+    fn legal_positions() {
+        for fen in [
+            INITIAL_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            // 车和象同时将军（闪击）
+            "4k3/8/8/1B6/8/8/8/4R1K1 b - - 0 1",
+            // 10 行的棋盘上兵从第 3 行或第 8 行出发，吃过路兵格在第 4 行或第 7 行
+            "r8r/1nbqkcabn1/ppppp1pppp/10/5p4/10/P9/1PPPPPPPPP/1NBQKCABN1/R8R w - f7 0 2",
+            "r8r/1nbqkcabn1/pppppppppp/10/10/4P5/10/PPPP1PPPPP/1NBQKCABN1/R8R b - e4 0 1",
+        ] {
+            assert_eq!(issues(fen), vec![], "{}", fen);
+        }
+    }
+
+    #[test]
+    fn detects_issues() {
+        let cases = [
+            ("8/8/8/8/8/8/8/4K3 w - - 0 1", vec![PositionIssue::MissingKing(PieceColor::Black)]),
+            ("4k3/8/8/8/8/8/8/K3K3 w - - 0 1", vec![PositionIssue::TooManyKings(PieceColor::White, 2)]),
+            ("4k3/8/8/8/8/8/8/P3K3 w - - 0 1", vec![PositionIssue::PawnOnBackRank(Square::new(0, 0))]),
+            ("4k3/8/8/8/8/8/8/QQQQKQQQ w - - 0 1", vec![]),
+            ("4k3/8/8/8/8/QQQ5/QQQ5/QQQQKQQQ w - - 0 1", vec![PositionIssue::TooManyPromotedPieces(PieceColor::White)]),
+            ("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1", vec![PositionIssue::OpponentInCheck]),
+            ("4k3/8/3N1N2/8/8/8/8/6K1 b - - 0 1", vec![PositionIssue::ImpossibleCheck]),
+            ("4k3/8/8/8/8/8/8/4K3 w K - 0 1", vec![PositionIssue::InvalidCastling { color: PieceColor::White, kingside: true }]),
+            ("4k3/8/8/8/8/8/8/4K3 w - e6 0 1", vec![PositionIssue::InvalidEnPassant(Square::new(4, 5))]),
+            (
+                "r8r/1nbqkcabn1/ppppp1pppp/10/5p4/10/P9/1PPPPPPPPP/1NBQKCABN1/R8R w - f9 0 2",
+                vec![PositionIssue::InvalidEnPassant(Square::new(5, 8))],
+            ),
+        ];
+        for (fen, expected) in cases {
+            assert_eq!(issues(fen), expected, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn lenient_mode_repairs_rights() {
+        let board = read_fen("4k3/8/8/8/8/8/8/4K3 w KQkq e6 0 1".to_string()).unwrap();
+        assert!(check_position(board.clone(), ValidationMode::Strict).is_err());
+        let repaired = check_position(board, ValidationMode::Lenient).unwrap();
+        assert_eq!(write_fen(repaired), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+
+        let board = read_fen("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1".to_string()).unwrap();
+        assert_eq!(check_position(board, ValidationMode::Lenient).err(), Some(vec![PositionIssue::OpponentInCheck]));
+    }
+}
@@ -0,0 +1,909 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::{
+    bitboard::{king_attacks, Bitboard}, board::*, fen::*, movegen::Position, piece::*, square::{BoardSize, Square},
+};
+
+// 三将军变体中获胜需要的将军次数
+pub const CHECKS_TO_WIN: u8 = 3;
+
+// 棋类变体的规则。默认实现为标准国际象棋，变体只需要覆盖不同的部分
+pub trait Variant: Sync {
+    fn kind(&self) -> VariantKind;
+
+    // 在 PGN 的 Variant 标签中使用的名字
+    fn name(&self) -> &'static str;
+
+    fn start_fen(&self) -> &'static str {
+        INITIAL_FEN
+    }
+
+    fn start_position(&self) -> Board {
+        let mut board = read_fen(self.start_fen().to_string()).expect("start FEN of a variant is valid");
+        board.set_variant(self.kind());
+        board
+    }
+
+    // 所有合法着法
+    fn legal_moves(&self, board: &Board) -> Vec<Step> {
+        Position::from_board(board).legal_moves()
+    }
+
+    // 从某格出发的所有合法着法
+    fn legal_moves_from(&self, board: &Board, from: Square) -> Vec<Step> {
+        Position::from_board(board).legal_moves_from(from)
+    }
+
+    // 放下手中棋子的所有合法着法，只有手中有棋子的变体需要实现
+    fn legal_drops(&self, _board: &Board) -> Vec<Step> {
+        Vec::new()
+    }
+
+    // 变体特有的终局条件，在将杀、逼和等通常的终局条件之前判断
+    fn variant_result(&self, _board: &Board) -> Option<BoardResult> {
+        None
+    }
+
+    // color 方的王是否正被将军，用于区分将死和逼和以及记谱中的将军标记
+    fn in_check(&self, board: &Board, color: PieceColor) -> bool {
+        let position = Position::from_board(board);
+        position.king_square(color).is_some_and(|king| position.is_attacked(king, color.flip()))
+    }
+
+    // 王是否受保护：不能被吃，每方必须正好有一个王
+    fn royal_king(&self) -> bool {
+        true
+    }
+
+    // 兵可以升变成的棋子
+    fn promotion_roles(&self) -> &'static [PieceRole] {
+        &PROMOTION_ROLES
+    }
+
+    // 双方是否都不可能获胜
+    fn insufficient_material(&self, board: &Board) -> bool {
+        insufficient_material(board)
+    }
+
+    // 走棋时是否统计将军次数，见 Board.checks
+    fn counts_checks(&self) -> bool {
+        false
+    }
+
+    // 被吃的棋子是否进入吃子一方的手中，见 Board.pockets
+    fn has_pockets(&self) -> bool {
+        false
+    }
+
+    // 吃子时是否爆炸，见 Board::make_move
+    fn explodes(&self) -> bool {
+        false
+    }
+
+    // 是否只显示己方能看到的格子，见 DarkChess::visible_squares
+    fn fog_of_war(&self) -> bool {
+        false
+    }
+
+    // 局面评估中子力分的符号，送吃变体中子力越少越好。见 engine::evaluate
+    fn material_sign(&self) -> i32 {
+        1
+    }
+
+    // 局面评估中 color 方离变体特有的获胜目标有多近，如王离中心的距离、已经将军的次数
+    fn goal_bonus(&self, _board: &Board, _color: PieceColor) -> i32 {
+        0
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum VariantKind {
+    #[default]
+    Standard,
+    KingOfTheHill,
+    ThreeCheck,
+    RacingKings,
+    Crazyhouse,
+    Atomic,
+    Antichess,
+    Capablanca,
+    Grand,
+    DarkChess,
+    Bughouse,
+}
+
+impl VariantKind {
+    pub const ALL: [VariantKind; 11] = [
+        VariantKind::Standard,
+        VariantKind::KingOfTheHill,
+        VariantKind::ThreeCheck,
+        VariantKind::RacingKings,
+        VariantKind::Crazyhouse,
+        VariantKind::Atomic,
+        VariantKind::Antichess,
+        VariantKind::Capablanca,
+        VariantKind::Grand,
+        VariantKind::DarkChess,
+        VariantKind::Bughouse,
+    ];
+
+    pub fn rules(self) -> &'static dyn Variant {
+        match self {
+            VariantKind::Standard => &Standard,
+            VariantKind::KingOfTheHill => &KingOfTheHill,
+            VariantKind::ThreeCheck => &ThreeCheck,
+            VariantKind::RacingKings => &RacingKings,
+            VariantKind::Crazyhouse => &Crazyhouse,
+            VariantKind::Atomic => &Atomic,
+            VariantKind::Antichess => &Antichess,
+            VariantKind::Capablanca => &Capablanca,
+            VariantKind::Grand => &Grand,
+            VariantKind::DarkChess => &DarkChess,
+            VariantKind::Bughouse => &Bughouse,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        self.rules().name()
+    }
+}
+
+impl fmt::Display for VariantKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ParseVariantError;
+
+impl fmt::Display for ParseVariantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown variant")
+    }
+}
+
+impl std::error::Error for ParseVariantError {}
+
+// 不区分大小写，忽略空格和连字符，因此 "King of the Hill" 和 "kingOfTheHill" 都可以识别
+impl FromStr for VariantKind {
+    type Err = ParseVariantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalize = |s: &str| s.chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .collect::<String>()
+            .to_lowercase();
+        let s = normalize(s);
+        if s == "chess" {
+            return Ok(VariantKind::Standard)
+        }
+        VariantKind::ALL.into_iter()
+            .find(|kind| normalize(kind.name()) == s)
+            .ok_or(ParseVariantError)
+    }
+}
+
+pub struct Standard;
+
+impl Variant for Standard {
+    fn kind(&self) -> VariantKind {
+        VariantKind::Standard
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard"
+    }
+}
+
+// 山丘之王：王走到中心的四个格子之一即获胜
+pub struct KingOfTheHill;
+
+impl KingOfTheHill {
+    // 中心的格子：每个方向正中的一列（行），长度为偶数时是正中的两列（行）。8×8 上为 d4、e4、d5、e5
+    pub fn is_hill(sq: Square, size: BoardSize) -> bool {
+        let center = |n: usize| (n - 1) / 2..=n / 2;
+        center(size.files).contains(&sq.file()) && center(size.ranks).contains(&sq.rank())
+    }
+
+    // 王走到中心还需要的步数
+    fn hill_distance(board: &Board, color: PieceColor) -> Option<usize> {
+        let king = board.size.squares().find(|&sq| board.pieces[sq] == Some(Piece { piece_role: PieceRole::King, piece_color: color }))?;
+        board.size.squares()
+            .filter(|&sq| Self::is_hill(sq, board.size))
+            .map(|sq| sq.file().abs_diff(king.file()).max(sq.rank().abs_diff(king.rank())))
+            .min()
+    }
+}
+
+impl Variant for KingOfTheHill {
+    fn kind(&self) -> VariantKind {
+        VariantKind::KingOfTheHill
+    }
+
+    fn name(&self) -> &'static str {
+        "King of the Hill"
+    }
+
+    fn variant_result(&self, board: &Board) -> Option<BoardResult> {
+        // 先判断刚走完棋的一方
+        let mover = board.active_color.flip();
+        [mover, mover.flip()].into_iter().find(|&color| {
+            board.size.squares().any(|sq| {
+                Self::is_hill(sq, board.size) && board.pieces[sq] == Some(Piece { piece_role: PieceRole::King, piece_color: color })
+            })
+        })
+        .map(|color| BoardResult::Winner(color, WinReason::KingOfTheHill))
+    }
+
+    // 只剩王时仍然可以走到中心
+    fn insufficient_material(&self, _board: &Board) -> bool {
+        false
+    }
+
+    // 离中心只差一两步的王比多一个轻子更重要
+    fn goal_bonus(&self, board: &Board, color: PieceColor) -> i32 {
+        const BONUS: [i32; 4] = [500, 250, 80, 20];
+        Self::hill_distance(board, color).and_then(|d| BONUS.get(d)).copied().unwrap_or(0)
+    }
+}
+
+// 三将军：将军三次即获胜。将军次数记录在 Board.checks 中，FEN 中写作剩余的将军次数，如 3+3
+pub struct ThreeCheck;
+
+impl Variant for ThreeCheck {
+    fn kind(&self) -> VariantKind {
+        VariantKind::ThreeCheck
+    }
+
+    fn name(&self) -> &'static str {
+        "Three-check"
+    }
+
+    fn start_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3+3 0 1"
+    }
+
+    fn variant_result(&self, board: &Board) -> Option<BoardResult> {
+        PieceColor::ALL.into_iter()
+            .find(|color| board.checks[color.index()] >= CHECKS_TO_WIN)
+            .map(|color| BoardResult::Winner(color, WinReason::ThreeChecks))
+    }
+
+    // 只要还有王以外的棋子就可能将军
+    fn insufficient_material(&self, board: &Board) -> bool {
+        Square::all().all(|sq| board.pieces[sq].is_none_or(|p| p.piece_role == PieceRole::King))
+    }
+
+    fn counts_checks(&self) -> bool {
+        true
+    }
+
+    fn goal_bonus(&self, board: &Board, color: PieceColor) -> i32 {
+        const BONUS: [i32; CHECKS_TO_WIN as usize] = [0, 150, 400];
+        BONUS.get(board.checks[color.index()] as usize).copied().unwrap_or(0)
+    }
+}
+
+// 王的赛跑：没有兵，不允许将军，王先走到第 8 行的一方获胜。
+// 白方先到达时，如果黑方能在紧接着的一步也到达第 8 行则为和棋
+pub struct RacingKings;
+
+impl RacingKings {
+    fn gives_check(board: &Board, step: Step) -> bool {
+        let mut b = board.clone();
+        b.make_move(step);
+        Position::from_board(&b).checkers().any()
+    }
+
+    fn king_on_goal(board: &Board, color: PieceColor) -> bool {
+        let goal = board.size.ranks - 1;
+        (0..board.size.files).any(|x| board.pieces[x][goal] == Some(Piece { piece_role: PieceRole::King, piece_color: color }))
+    }
+}
+
+impl Variant for RacingKings {
+    fn kind(&self) -> VariantKind {
+        VariantKind::RacingKings
+    }
+
+    fn name(&self) -> &'static str {
+        "Racing Kings"
+    }
+
+    fn start_fen(&self) -> &'static str {
+        "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1"
+    }
+
+    fn legal_moves(&self, board: &Board) -> Vec<Step> {
+        let mut moves = Standard.legal_moves(board);
+        moves.retain(|&step| !Self::gives_check(board, step));
+        moves
+    }
+
+    fn legal_moves_from(&self, board: &Board, from: Square) -> Vec<Step> {
+        let mut moves = Standard.legal_moves_from(board, from);
+        moves.retain(|&step| !Self::gives_check(board, step));
+        moves
+    }
+
+    // 王每往前走一行都有分
+    fn goal_bonus(&self, board: &Board, color: PieceColor) -> i32 {
+        let king = Piece { piece_role: PieceRole::King, piece_color: color };
+        board.size.squares()
+            .find(|&sq| board.pieces[sq] == Some(king))
+            .map_or(0, |sq| sq.rank() as i32 * 60)
+    }
+
+    fn variant_result(&self, board: &Board) -> Option<BoardResult> {
+        let white = Self::king_on_goal(board, PieceColor::White);
+        let black = Self::king_on_goal(board, PieceColor::Black);
+        match (white, black) {
+            (true, true) => Some(BoardResult::Draw(DrawReason::RaceTied)),
+            (false, true) => Some(BoardResult::Winner(PieceColor::Black, WinReason::RaceFinished)),
+            (true, false) => {
+                // 白方刚到达，黑方还有一步机会
+                let goal = board.size.ranks - 1;
+                let black_can_reach = board.active_color == PieceColor::Black
+                    && self.legal_moves(board).into_iter().any(|step| {
+                        step.to.rank() == goal
+                            && board.pieces[step.from].is_some_and(|p| p.piece_role == PieceRole::King)
+                    });
+                if black_can_reach {
+                    None
+                } else {
+                    Some(BoardResult::Winner(PieceColor::White, WinReason::RaceFinished))
+                }
+            },
+            (false, false) => None,
+        }
+    }
+
+    fn insufficient_material(&self, _board: &Board) -> bool {
+        false
+    }
+}
+
+// 疯狂屋：被吃的棋子进入吃子一方的手中，之后可以代替走棋放到任意空格上。兵不能放在第 1 行和第 8 行
+pub struct Crazyhouse;
+
+impl Variant for Crazyhouse {
+    fn kind(&self) -> VariantKind {
+        VariantKind::Crazyhouse
+    }
+
+    fn name(&self) -> &'static str {
+        "Crazyhouse"
+    }
+
+    fn start_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1"
+    }
+
+    fn legal_moves(&self, board: &Board) -> Vec<Step> {
+        let mut moves = Standard.legal_moves(board);
+        moves.extend(self.legal_drops(board));
+        moves
+    }
+
+    fn legal_drops(&self, board: &Board) -> Vec<Step> {
+        let pocket = board.pockets[board.active_color.index()];
+        if pocket.is_empty() {
+            return Vec::new()
+        }
+        let targets = Position::from_board(board).drop_targets();
+        let mut drops = Vec::new();
+        for role in pocket.roles() {
+            for to in targets {
+                let to = Square::from_index(to);
+                if role == PieceRole::Pawn && (to.rank() == 0 || to.rank() == board.size.ranks - 1) {
+                    continue;
+                }
+                drops.push(Step::new_drop(role, to));
+            }
+        }
+        drops
+    }
+
+    // 手中的棋子随时可以放回棋盘
+    fn insufficient_material(&self, board: &Board) -> bool {
+        board.pockets.iter().all(Pocket::is_empty) && insufficient_material(board)
+    }
+
+    fn has_pockets(&self) -> bool {
+        true
+    }
+}
+
+// 双人四人象棋中的一盘棋，规则与疯狂屋相同，但被吃的棋子交给另一盘的队友，见 bughouse::BughouseGame
+pub struct Bughouse;
+
+impl Variant for Bughouse {
+    fn kind(&self) -> VariantKind {
+        VariantKind::Bughouse
+    }
+
+    fn name(&self) -> &'static str {
+        "Bughouse"
+    }
+
+    fn start_fen(&self) -> &'static str {
+        Crazyhouse.start_fen()
+    }
+
+    fn legal_moves(&self, board: &Board) -> Vec<Step> {
+        Crazyhouse.legal_moves(board)
+    }
+
+    fn legal_drops(&self, board: &Board) -> Vec<Step> {
+        Crazyhouse.legal_drops(board)
+    }
+
+    // 队友随时可能送来棋子
+    fn insufficient_material(&self, _board: &Board) -> bool {
+        false
+    }
+
+    fn has_pockets(&self) -> bool {
+        true
+    }
+}
+
+// 原子象棋：吃子时吃子的棋子、被吃的棋子和目标格周围除兵以外的棋子都被炸掉，炸掉对方的王即获胜。
+// 王不能吃子，不能让己方的王被炸掉。两个王相邻时不算将军，因为吃掉对方的王会炸掉自己的王
+pub struct Atomic;
+
+impl Atomic {
+    // 走完一步后，走棋的一方 mover 的王仍在，并且对方的王已被炸掉或者己方没有被将军
+    fn legal_after(board: &Board, mover: PieceColor) -> bool {
+        let position = Position::from_board(board);
+        position.king_square(mover).is_some()
+            && (position.king_square(mover.flip()).is_none() || !Atomic.in_check(board, mover))
+    }
+}
+
+impl Variant for Atomic {
+    fn kind(&self) -> VariantKind {
+        VariantKind::Atomic
+    }
+
+    fn name(&self) -> &'static str {
+        "Atomic"
+    }
+
+    fn legal_moves(&self, board: &Board) -> Vec<Step> {
+        let mover = board.active_color;
+        let mut b = board.clone();
+        let mut moves = Position::from_board(board).pseudo_legal_moves();
+        moves.retain(|&step| {
+            let king_captures = board.pieces[step.from].is_some_and(|p| p.piece_role == PieceRole::King)
+                && move_flags(board, step).capture;
+            if king_captures {
+                return false
+            }
+            let mv = b.make_move(step);
+            let legal = Self::legal_after(&b, mover);
+            b.unmake_move(&mv);
+            legal
+        });
+        moves
+    }
+
+    fn legal_moves_from(&self, board: &Board, from: Square) -> Vec<Step> {
+        let mut moves = self.legal_moves(board);
+        moves.retain(|step| step.from == from);
+        moves
+    }
+
+    fn variant_result(&self, board: &Board) -> Option<BoardResult> {
+        let position = Position::from_board(board);
+        PieceColor::ALL.into_iter()
+            .find(|&color| position.king_square(color).is_none())
+            .map(|color| BoardResult::Winner(color.flip(), WinReason::KingExploded))
+    }
+
+    fn in_check(&self, board: &Board, color: PieceColor) -> bool {
+        let position = Position::from_board(board);
+        let Some(king) = position.king_square(color) else {
+            return false
+        };
+        let their_king = position.pieces(color.flip(), PieceRole::King);
+        (king_attacks(king) & their_king).is_empty()
+            && (position.attackers(king, color.flip(), position.occupied()) & !their_king).any()
+    }
+
+    // 王以外最多只剩一个马或象时无法获胜
+    fn insufficient_material(&self, board: &Board) -> bool {
+        let others: Vec<PieceRole> = Square::all()
+            .filter_map(|sq| board.pieces[sq])
+            .map(|p| p.piece_role)
+            .filter(|&role| role != PieceRole::King)
+            .collect();
+        match others[..] {
+            [] => true,
+            [role] => role == PieceRole::Knight || role == PieceRole::Bishop,
+            _ => false,
+        }
+    }
+
+    fn explodes(&self) -> bool {
+        true
+    }
+}
+
+// 自杀象棋：能吃子时必须吃子，王是普通的棋子，没有将军和易位，兵可以升变成王。
+// 失去所有棋子或者被逼和的一方获胜
+pub struct Antichess;
+
+impl Variant for Antichess {
+    fn kind(&self) -> VariantKind {
+        VariantKind::Antichess
+    }
+
+    fn name(&self) -> &'static str {
+        "Antichess"
+    }
+
+    fn start_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1"
+    }
+
+    fn legal_moves(&self, board: &Board) -> Vec<Step> {
+        let mut moves = Position::from_board(board).pseudo_legal_moves();
+        moves.retain(|&step| !is_castle(board, step));
+        if moves.iter().any(|&step| move_flags(board, step).capture) {
+            moves.retain(|&step| move_flags(board, step).capture);
+        }
+        moves
+    }
+
+    fn legal_moves_from(&self, board: &Board, from: Square) -> Vec<Step> {
+        let mut moves = self.legal_moves(board);
+        moves.retain(|step| step.from == from);
+        moves
+    }
+
+    fn variant_result(&self, board: &Board) -> Option<BoardResult> {
+        let color = board.active_color;
+        let reason = if Square::all().all(|sq| board.pieces[sq].is_none_or(|p| p.piece_color != color)) {
+            WinReason::AllPiecesLost
+        } else if self.legal_moves(board).is_empty() {
+            WinReason::Stalemated
+        } else {
+            return None
+        };
+        Some(BoardResult::Winner(color, reason))
+    }
+
+    fn in_check(&self, _board: &Board, _color: PieceColor) -> bool {
+        false
+    }
+
+    fn royal_king(&self) -> bool {
+        false
+    }
+
+    fn promotion_roles(&self) -> &'static [PieceRole] {
+        &[PieceRole::Queen, PieceRole::Rook, PieceRole::Bishop, PieceRole::Knight, PieceRole::King]
+    }
+
+    fn material_sign(&self) -> i32 {
+        -1
+    }
+
+    // 双方都只剩象并且在不同颜色的格子上时，谁也吃不到对方
+    fn insufficient_material(&self, board: &Board) -> bool {
+        let mut squares = [[false; 2]; 2];
+        for sq in Square::all() {
+            let Some(p) = board.pieces[sq] else {
+                continue;
+            };
+            if p.piece_role != PieceRole::Bishop {
+                return false
+            }
+            squares[p.piece_color.index()][(sq.file() + sq.rank()) % 2] = true;
+        }
+        let [white, black] = squares;
+        white != black && white.iter().filter(|&&b| b).count() == 1 && black.iter().filter(|&&b| b).count() == 1
+    }
+}
+
+// 大象棋中兵可以升变成的棋子，包括大主教和总理大臣
+const FAIRY_PROMOTION_ROLES: [PieceRole; 6] = [
+    PieceRole::Queen, PieceRole::Rook, PieceRole::Bishop, PieceRole::Knight, PieceRole::Archbishop, PieceRole::Chancellor,
+];
+
+// 卡帕布兰卡象棋：10×8 的棋盘，每方多一个大主教（象 + 马）和一个总理大臣（车 + 马）。
+// 王在 f 列，易位后王在 c 列或 i 列，车在王的内侧
+pub struct Capablanca;
+
+impl Variant for Capablanca {
+    fn kind(&self) -> VariantKind {
+        VariantKind::Capablanca
+    }
+
+    fn name(&self) -> &'static str {
+        "Capablanca"
+    }
+
+    fn start_fen(&self) -> &'static str {
+        "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1"
+    }
+
+    fn promotion_roles(&self) -> &'static [PieceRole] {
+        &FAIRY_PROMOTION_ROLES
+    }
+}
+
+// 大象棋：10×10 的棋盘，车在角上，其余棋子在第 2 行，兵在第 3 行，没有王车易位。
+// 这里简化了升变规则：兵只在最后一行升变，可以升变成任意棋子
+pub struct Grand;
+
+impl Variant for Grand {
+    fn kind(&self) -> VariantKind {
+        VariantKind::Grand
+    }
+
+    fn name(&self) -> &'static str {
+        "Grand"
+    }
+
+    fn start_fen(&self) -> &'static str {
+        "r8r/1nbqkcabn1/pppppppppp/10/10/10/10/PPPPPPPPPP/1NBQKCABN1/R8R w - - 0 1"
+    }
+
+    fn promotion_roles(&self) -> &'static [PieceRole] {
+        &FAIRY_PROMOTION_ROLES
+    }
+}
+
+// 暗棋：每方只能看到己方棋子所在、能走到和能攻击的格子。没有将军的规则，王可以走到被攻击的格子，
+// 吃掉对方的王即获胜
+pub struct DarkChess;
+
+impl DarkChess {
+    pub fn visible_squares(board: &Board, color: PieceColor) -> Bitboard {
+        Position::from_board(board).visible_squares(color)
+    }
+}
+
+impl Variant for DarkChess {
+    fn kind(&self) -> VariantKind {
+        VariantKind::DarkChess
+    }
+
+    fn name(&self) -> &'static str {
+        "Dark Chess"
+    }
+
+    fn legal_moves(&self, board: &Board) -> Vec<Step> {
+        Position::from_board(board).pseudo_legal_moves()
+    }
+
+    fn legal_moves_from(&self, board: &Board, from: Square) -> Vec<Step> {
+        let mut moves = self.legal_moves(board);
+        moves.retain(|step| step.from == from);
+        moves
+    }
+
+    fn variant_result(&self, board: &Board) -> Option<BoardResult> {
+        let color = board.active_color;
+        let has_king = Square::all().any(|sq| board.pieces[sq] == Some(Piece { piece_role: PieceRole::King, piece_color: color }));
+        (!has_king).then_some(BoardResult::Winner(color.flip(), WinReason::KingCaptured))
+    }
+
+    fn in_check(&self, _board: &Board, _color: PieceColor) -> bool {
+        false
+    }
+
+    fn royal_king(&self) -> bool {
+        false
+    }
+
+    // 王可以被吃，只剩两个王时也可能分出胜负
+    fn insufficient_material(&self, _board: &Board) -> bool {
+        false
+    }
+
+    fn fog_of_war(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bitboard::square, step::read_step};
+
+    // 按 variant 的规则读取 FEN，置换键也随变体更新
+    fn variant_fen(variant: VariantKind, fen: &str) -> Board {
+        let mut board = read_fen(fen.to_string()).unwrap();
+        board.set_variant(variant);
+        board
+    }
+
+    fn play(variant: VariantKind, sans: &[&str]) -> Board {
+        let mut board = variant.rules().start_position();
+        for san in sans {
+            let step = read_step(&board, san.to_string()).unwrap_or_else(|| panic!("{}", san));
+            board = try_move(&board, step).unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for kind in VariantKind::ALL {
+            assert_eq!(kind.name().parse(), Ok(kind));
+        }
+        assert_eq!("kingOfTheHill".parse(), Ok(VariantKind::KingOfTheHill));
+        assert_eq!("threecheck".parse(), Ok(VariantKind::ThreeCheck));
+        assert_eq!("Crazy".parse::<VariantKind>(), Err(ParseVariantError));
+    }
+
+    #[test]
+    fn king_of_the_hill() {
+        let board = play(VariantKind::KingOfTheHill, &["e4", "d5", "exd5", "Kd7", "Ke2", "Kd6", "Ke3"]);
+        assert!(end_game(&board).is_none());
+        let board = try_move(&board, read_step(&board, "Kxd5".to_string()).unwrap()).unwrap();
+        assert!(end_game(&board) == Some(BoardResult::Winner(PieceColor::Black, WinReason::KingOfTheHill)));
+        // 只剩王时不算子力不足
+        let fen = "4k3/8/8/8/8/8/8/4K3 w - - 0 1";
+        assert!(end_game(&variant_fen(VariantKind::KingOfTheHill, fen)).is_none());
+        assert!(end_game(&read_fen(fen.to_string()).unwrap()).is_some());
+        // 中心随棋盘大小变化，10×10 上为 e5 到 f6
+        let hill: Vec<String> = BoardSize::GRAND.squares()
+            .filter(|&sq| KingOfTheHill::is_hill(sq, BoardSize::GRAND))
+            .map(|sq| sq.to_string())
+            .collect();
+        assert_eq!(hill, ["e5", "f5", "e6", "f6"]);
+        let board = variant_fen(VariantKind::KingOfTheHill, "k9/10/10/10/10/10/3K6/10/10/10 b - - 0 1");
+        assert!(end_game(&board).is_none());
+        let board = variant_fen(VariantKind::KingOfTheHill, "k9/10/10/10/10/5K4/10/10/10/10 b - - 0 1");
+        assert!(end_game(&board) == Some(BoardResult::Winner(PieceColor::White, WinReason::KingOfTheHill)));
+    }
+
+    #[test]
+    fn three_check() {
+        let board = play(VariantKind::ThreeCheck, &["e4", "e5", "Bc4", "Nc6", "Bxf7+", "Kxf7", "Qh5+", "g6"]);
+        assert_eq!(board.checks, [2, 0]);
+        assert_eq!(write_fen(board.clone()), "r1bq1bnr/pppp1k1p/2n3p1/4p2Q/4P3/8/PPPP1PPP/RNB1K1NR w KQ - 1+3 0 5");
+        assert!(end_game(&board).is_none());
+        let board = try_move(&board, read_step(&board, "Qxg6+".to_string()).unwrap()).unwrap();
+        assert!(end_game(&board) == Some(BoardResult::Winner(PieceColor::White, WinReason::ThreeChecks)));
+
+        // 同样的棋子位置，将军次数不同时不是同一个局面
+        let a = read_fen("4k3/8/8/8/8/8/8/4K3 w - - 3+3 0 1".to_string()).unwrap();
+        let b = read_fen("4k3/8/8/8/8/8/8/4K3 w - - 2+3 0 1".to_string()).unwrap();
+        assert!(a != b);
+        assert_eq!(a.variant, VariantKind::ThreeCheck);
+    }
+
+    #[test]
+    fn racing_kings() {
+        let board = VariantKind::RacingKings.rules().start_position();
+        assert_eq!(all_move(&board).len(), 21);
+        // 不能将军
+        let board = variant_fen(VariantKind::RacingKings, "8/8/8/8/8/8/k7/6RK w - - 0 1");
+        assert!(try_move(&board, Step::new("g1".parse().unwrap(), "a1".parse().unwrap())).is_none());
+        assert!(try_move(&board, Step::new("g1".parse().unwrap(), "g3".parse().unwrap())).is_some());
+
+        // 白方先到达第 8 行，黑方能跟上时为和棋，否则白方获胜
+        let board = variant_fen(VariantKind::RacingKings, "6K1/1k6/8/8/8/8/8/8 b - - 0 1");
+        assert!(end_game(&board).is_none());
+        let tied = try_move(&board, read_step(&board, "Kb8".to_string()).unwrap()).unwrap();
+        assert!(end_game(&tied) == Some(BoardResult::Draw(DrawReason::RaceTied)));
+        let board = variant_fen(VariantKind::RacingKings, "6K1/8/1k6/8/8/8/8/8 b - - 0 1");
+        assert!(end_game(&board) == Some(BoardResult::Winner(PieceColor::White, WinReason::RaceFinished)));
+    }
+
+    #[test]
+    fn crazyhouse() {
+        let board = play(VariantKind::Crazyhouse, &["e4", "d5", "exd5", "Qxd5", "Nc3", "Qa5"]);
+        assert_eq!(write_fen(board.clone()), "rnb1kbnr/ppp1pppp/8/q7/8/2N5/PPPP1PPP/R1BQKBNR[Pp] w KQkq - 2 4");
+        assert!(read_step(&board, "N@d4".to_string()).is_none());
+        let drop = read_step(&board, "@d4".to_string()).unwrap();
+        assert_eq!(drop, Step::new_drop(PieceRole::Pawn, "d4".parse().unwrap()));
+        assert_eq!(drop.to_string(), "P@d4");
+        assert_eq!("P@d4".parse(), Ok(drop));
+        assert_eq!(crate::step::write_step(&board, drop).unwrap(), "P@d4");
+        let after = try_move(&board, drop).unwrap();
+        assert!(after.pockets[0].is_empty());
+        // 兵不能放在底线
+        assert!(all_move(&board).iter().filter(|s| s.drop.is_some()).all(|s| (1..7).contains(&s.to.rank())));
+
+        // 被将军时只能放在王和将军的棋子之间
+        let board = read_fen("4k3/8/8/8/8/8/8/r3K3[Nq] w - - 0 1".to_string()).unwrap();
+        let drops: Vec<Step> = all_move(&board).into_iter().filter(|s| s.drop.is_some()).collect();
+        assert_eq!(drops.len(), 3);
+
+        // 升变来的棋子被吃后变回兵
+        let board = read_fen("r3k3/8/8/8/8/8/8/Q~3K3[] b - - 0 1".to_string()).unwrap();
+        let board = try_move(&board, read_step(&board, "Rxa1+".to_string()).unwrap()).unwrap();
+        assert_eq!(write_fen(board), "4k3/8/8/8/8/8/8/r3K3[p] w - - 0 2");
+    }
+
+    #[test]
+    fn atomic() {
+        let board = play(VariantKind::Atomic, &["e4", "d5", "exd5"]);
+        assert_eq!(write_fen(board), "rnbqkbnr/ppp1pppp/8/8/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2");
+        // 马吃 f7 炸掉了周围的王、象和马，兵留下
+        let board = play(VariantKind::Atomic, &["Nf3", "a6", "Ng5", "a5", "Nxf7"]);
+        assert_eq!(write_fen(board.clone()), "rnbq3r/1pppp1pp/8/p7/8/8/PPPPPPPP/RNBQKB1R b KQ - 0 3");
+        assert!(end_game(&board) == Some(BoardResult::Winner(PieceColor::White, WinReason::KingExploded)));
+
+        // 王可以相邻，相邻时不算将军
+        let board = variant_fen(VariantKind::Atomic, "8/8/8/8/8/3k4/8/4K3 w - - 0 1");
+        assert!(try_move(&board, Step::new("e1".parse().unwrap(), "e2".parse().unwrap())).is_some());
+        let board = variant_fen(VariantKind::Atomic, "8/8/8/8/8/3k4/r3K3/8 w - - 0 1");
+        assert!(!Atomic.in_check(&board, PieceColor::White));
+        assert!(Standard.in_check(&board, PieceColor::White));
+        // 王不能吃子，吃子不能炸掉自己的王
+        let board = variant_fen(VariantKind::Atomic, "4k3/8/8/8/8/8/R2n4/4K3 w - - 0 1");
+        assert!(try_move(&board, Step::new("a2".parse().unwrap(), "d2".parse().unwrap())).is_none());
+        assert!(try_move(&board, Step::new("e1".parse().unwrap(), "d2".parse().unwrap())).is_none());
+    }
+
+    #[test]
+    fn antichess() {
+        let board = play(VariantKind::Antichess, &["e3", "b5"]);
+        // 必须吃子
+        assert_eq!(all_move(&board), vec![read_step(&board, "Bxb5".to_string()).unwrap()]);
+
+        // 兵可以升变成王，没有将军
+        let board = variant_fen(VariantKind::Antichess, "8/4P3/8/8/8/8/8/k7 w - - 0 1");
+        assert_eq!(all_move(&board).len(), 5);
+        let step = read_step(&board, "e8=K".to_string()).unwrap();
+        assert_eq!(step.to_string(), "e7e8k");
+        assert_eq!("e7e8k".parse(), Ok(step));
+
+        // 失去所有棋子或者被逼和的一方获胜
+        let board = variant_fen(VariantKind::Antichess, "8/8/8/8/8/8/1p6/R7 b - - 0 1");
+        let board = try_move(&board, read_step(&board, "bxa1=Q".to_string()).unwrap()).unwrap();
+        assert!(end_game(&board) == Some(BoardResult::Winner(PieceColor::White, WinReason::AllPiecesLost)));
+        let board = variant_fen(VariantKind::Antichess, "8/8/8/8/8/p7/P7/8 w - - 0 1");
+        assert!(end_game(&board) == Some(BoardResult::Winner(PieceColor::White, WinReason::Stalemated)));
+        let board = variant_fen(VariantKind::Antichess, "8/8/8/8/8/8/B7/b7 w - - 0 1");
+        assert!(end_game(&board) == Some(BoardResult::Draw(DrawReason::InsufficientMaterial)));
+    }
+
+    #[test]
+    fn dark_chess() {
+        let board = DarkChess.start_position();
+        // 开局时只能看到己方的前四行
+        let visible = DarkChess::visible_squares(&board, PieceColor::White);
+        assert_eq!(visible.count(), 32);
+        assert!(visible.contains(square(4, 3)) && !visible.contains(square(4, 4)));
+        let board = play(VariantKind::DarkChess, &["e4", "f6"]);
+        let visible = DarkChess::visible_squares(&board, PieceColor::White);
+        assert!(visible.contains(square(0, 5)) && visible.contains(square(7, 4)) && !visible.contains(square(4, 6)));
+
+        // 没有将军，被攻击的一方可以不应将，吃掉王即获胜
+        let board = play(VariantKind::DarkChess, &["e4", "f6", "Qh5", "a6"]);
+        assert!(end_game(&board).is_none());
+        let board = try_move(&board, read_step(&board, "Qxe8".to_string()).unwrap()).unwrap();
+        assert!(end_game(&board) == Some(BoardResult::Winner(PieceColor::White, WinReason::KingCaptured)));
+    }
+
+    #[test]
+    fn large_boards() {
+        let board = VariantKind::Capablanca.rules().start_position();
+        assert_eq!(board.size, crate::square::BoardSize::CAPABLANCA);
+        assert_eq!(write_fen(board.clone()), Capablanca.start_fen());
+        assert_eq!(all_move(&board).len(), 28);
+        assert_eq!(crate::perft::perft(&board, 2), 784);
+        // 总理大臣可以像马一样跳出去，王侧易位后王在 i 列
+        let board = play(VariantKind::Capablanca, &["Cg3", "e6", "Nh3", "d6", "f3", "c6", "Bf2", "b6", "O-O"]);
+        assert_eq!(write_fen(board.clone()), "rnabqkbcnr/p4ppppp/1pppp5/10/10/5PCN2/PPPPPBPPPP/RNABQ2RK1 b kq - 1 5");
+
+        let board = VariantKind::Grand.rules().start_position();
+        assert_eq!(write_fen(board.clone()), Grand.start_fen());
+        let board = play(VariantKind::Grand, &["e5", "e6", "Ce4", "Af7"]);
+        assert_eq!(board.pieces["e4".parse::<Square>().unwrap()].map(|p| p.piece_role), Some(PieceRole::Chancellor));
+        // 第 10 行的格子和两位数的行号
+        let board = variant_fen(VariantKind::Grand, "10/4P5/10/10/10/10/10/10/k9/9K w - - 0 1");
+        let step = read_step(&board, "e10=C".to_string()).unwrap();
+        assert_eq!(step.to_string(), "e9e10c");
+        assert_eq!(crate::step::write_step(&board, step).unwrap(), "e10=C");
+    }
+}
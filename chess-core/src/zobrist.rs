@@ -0,0 +1,237 @@
+use crate::{
+    board::*, piece::*, square::{BoardSize, Square}, variant::{VariantKind, CHECKS_TO_WIN},
+};
+
+// 随机数的排列：12 种棋子 × 64 格、4 个易位权、8 个过路兵列、1 个行棋方，
+// 之后是三将军变体中双方已经将军 1 到 3 次的键，疯狂屋中双方手中每种棋子有 1 到 16 个的键，
+// 8×8 以外的格子、大象棋棋子和第 i、j 列过路兵的键，以及变体和棋盘大小的键。
+// 数值由固定种子的 SplitMix64 生成，键值只在本程序内部使用，与 Polyglot 开局库的键值不兼容
+const PIECE_OFFSET: usize = 0;
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const TURN_OFFSET: usize = 780;
+const CHECKS_OFFSET: usize = 781;
+const POCKET_OFFSET: usize = CHECKS_OFFSET + 2 * CHECKS_TO_WIN as usize;
+const POCKET_LIMIT: usize = 16;
+const EXTRA_PIECE_OFFSET: usize = POCKET_OFFSET + 2 * 5 * POCKET_LIMIT;
+const EXTRA_EN_PASSANT_OFFSET: usize = EXTRA_PIECE_OFFSET + 2 * 8 * SQUARE_COUNT;
+const VARIANT_OFFSET: usize = EXTRA_EN_PASSANT_OFFSET + BOARD_SIZE_I - 8;
+const SIZE_OFFSET: usize = VARIANT_OFFSET + VariantKind::ALL.len();
+const KEY_COUNT: usize = SIZE_OFFSET + 2 * (BOARD_SIZE_I + 1);
+
+const fn generate_keys() -> [u64; KEY_COUNT] {
+    let mut keys = [0; KEY_COUNT];
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut i = 0;
+    while i < KEY_COUNT {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+static KEYS: [u64; KEY_COUNT] = generate_keys();
+
+// 棋子编号：黑兵 0、白兵 1、黑马 2、白马 3，依次为象、车、后、王，之后是大主教和总理大臣
+fn piece_kind(piece: Piece) -> usize {
+    let role = match piece.piece_role {
+        PieceRole::Pawn => 0,
+        PieceRole::Knight => 1,
+        PieceRole::Bishop => 2,
+        PieceRole::Rook => 3,
+        PieceRole::Queen => 4,
+        PieceRole::King => 5,
+        PieceRole::Archbishop => 6,
+        PieceRole::Chancellor => 7,
+    };
+    let color = match piece.piece_color {
+        PieceColor::White => 1,
+        PieceColor::Black => 0,
+    };
+    2 * role + color
+}
+
+// 标准棋子在 8×8 范围内的格子上使用前 768 个键，其余使用附加的键
+pub fn piece_key(piece: Piece, sq: Square) -> u64 {
+    let kind = piece_kind(piece);
+    if kind < 12 && sq.file() < 8 && sq.rank() < 8 {
+        KEYS[PIECE_OFFSET + 64 * kind + 8 * sq.rank() + sq.file()]
+    } else {
+        KEYS[EXTRA_PIECE_OFFSET + SQUARE_COUNT * kind + sq.index()]
+    }
+}
+
+// index 为 2 * 颜色 + 方向，即白王侧、白后侧、黑王侧、黑后侧
+pub fn castling_key(index: usize) -> u64 {
+    KEYS[CASTLING_OFFSET + index]
+}
+
+// 所有易位权对应的键。只区分方向，不区分车所在的列
+pub fn castling_keys(rights: CastlingRights) -> u64 {
+    rights.iter()
+        .fold(0, |key, (color, side, _)| key ^ castling_key(2 * color.index() + side.index()))
+}
+
+pub fn en_passant_key(file: usize) -> u64 {
+    if file < 8 {
+        KEYS[EN_PASSANT_OFFSET + file]
+    } else {
+        KEYS[EXTRA_EN_PASSANT_OFFSET + file - 8]
+    }
+}
+
+// 白方行棋时异或此键
+pub fn turn_key() -> u64 {
+    KEYS[TURN_OFFSET]
+}
+
+// 某一方将军 count 次对应的键，没有将军时为 0
+pub fn checks_key(color: PieceColor, count: u8) -> u64 {
+    match count {
+        0 => 0,
+        count => KEYS[CHECKS_OFFSET + CHECKS_TO_WIN as usize * color.index() + count as usize - 1],
+    }
+}
+
+// 某一方手中有 count 个某种棋子对应的键，没有时为 0。超过 16 个的数量共用同一个键
+pub fn pocket_key(color: PieceColor, role: PieceRole, count: u8) -> u64 {
+    match count {
+        0 => 0,
+        count => {
+            let count = (count as usize).min(POCKET_LIMIT);
+            KEYS[POCKET_OFFSET + POCKET_LIMIT * (5 * color.index() + role.index()) + count - 1]
+        },
+    }
+}
+
+// 过路兵格只有在当前行动方能吃时才计入键值，这样键值相同当且仅当局面重复（见 same_position）。
+// 只有相邻的兵、但吃过路兵会暴露己方王时不计入
+pub fn en_passant_term(board: &Board) -> u64 {
+    match board.en_passant_target {
+        Some(sq) if en_passant_capturable(board) => en_passant_key(sq.file()),
+        _ => 0,
+    }
+}
+
+// 变体的键，标准国际象棋为 0。走子时变体不变，改变变体见 Board::set_variant
+pub fn variant_key(variant: VariantKind) -> u64 {
+    match variant {
+        VariantKind::Standard => 0,
+        variant => KEYS[VARIANT_OFFSET + variant as usize],
+    }
+}
+
+// 棋盘大小的键，8×8 为 0
+pub fn size_key(size: BoardSize) -> u64 {
+    if size == BoardSize::STANDARD {
+        return 0
+    }
+    KEYS[SIZE_OFFSET + size.files] ^ KEYS[SIZE_OFFSET + BOARD_SIZE_I + 1 + size.ranks]
+}
+
+// 从头计算盘面的键值。走子时键值由 Board::make_move 增量维护
+pub fn compute(board: &Board) -> u64 {
+    let mut key = variant_key(board.variant) ^ size_key(board.size);
+    for sq in Square::all() {
+        if let Some(piece) = board.pieces[sq] {
+            key ^= piece_key(piece, sq);
+        }
+    }
+    key ^= castling_keys(board.castling_availability);
+    for color in PieceColor::ALL {
+        key ^= checks_key(color, board.checks[color.index()]);
+        for role in POCKET_ROLES {
+            key ^= pocket_key(color, role, board.pockets[color.index()].count(role));
+        }
+    }
+    key ^= en_passant_term(board);
+    if board.active_color == PieceColor::White {
+        key ^= turn_key();
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::*;
+    use std::collections::HashSet;
+
+    fn board(fen: &str) -> Board {
+        read_fen(fen.to_string()).unwrap()
+    }
+
+    // 沿所有走法走到给定深度，检查增量维护的键值与从头计算的一致
+    fn check_incremental(board: &Board, depth: usize) {
+        assert_eq!(board.zobrist, compute(board), "{}", write_fen(board.clone()));
+        if depth == 0 {
+            return;
+        }
+        for step in all_move(board) {
+            check_incremental(&try_move(board, step).unwrap(), depth - 1);
+        }
+    }
+
+    #[test]
+    fn incremental_matches_full_computation() {
+        for fen in [
+            INITIAL_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R[Qn] w KQkq - 0 1",
+        ] {
+            check_incremental(&board(fen), 3);
+        }
+    }
+
+    #[test]
+    fn transpositions_are_equal() {
+        let play = |sans: &[&str]| {
+            let mut b = Board::default();
+            for san in sans {
+                let step = crate::step::read_step(&b, san.to_string()).unwrap();
+                b = try_move(&b, step).unwrap();
+            }
+            b
+        };
+        let a = play(&["Nf3", "Nf6", "Nc3", "Nc6"]);
+        let b = play(&["Nc3", "Nc6", "Nf3", "Nf6"]);
+        assert!(a == b);
+        assert_eq!(a.zobrist, b.zobrist);
+
+        let set: HashSet<Board> = [a, b, Board::default()].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn variant_and_size_are_part_of_the_position() {
+        let standard = board(INITIAL_FEN);
+        let mut crazyhouse = standard.clone();
+        crazyhouse.set_variant(VariantKind::Crazyhouse);
+        assert!(standard != crazyhouse);
+        assert_ne!(standard.zobrist, crazyhouse.zobrist);
+        assert_eq!(crazyhouse.zobrist, compute(&crazyhouse));
+        assert_eq!(standard.zobrist, compute(&VariantKind::Standard.rules().start_position()));
+        // 同样的棋子放在更大的棋盘上是不同的局面
+        let small = board("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        let wide = Board { size: BoardSize::CAPABLANCA, ..small.clone() };
+        assert!(small != wide);
+        assert_ne!(compute(&small), compute(&wide));
+    }
+
+    #[test]
+    fn en_passant_only_counts_when_capturable() {
+        // 没有能吃过路兵的兵，过路兵格不影响局面
+        assert!(board("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1") == board("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1"));
+        // 可以吃过路兵时是不同的局面
+        assert!(board("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1") != board("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1"));
+        // 吃过路兵会暴露己方王，也不影响局面
+        assert!(board("8/8/8/8/k2pP2Q/8/8/4K3 b - e3 0 1") == board("8/8/8/8/k2pP2Q/8/8/4K3 b - - 0 1"));
+    }
+}
@@ -1,4 +1,4 @@
-use::bevy::prelude::*;
+use bevy::prelude::*;
 
 use crate::{Game, UpdateBoard};
 
@@ -13,6 +13,15 @@ pub fn handle_delete_variation_events(
     mut update_board_events: EventWriter<UpdateBoard>,
 ) {
     for event in delete_events.read() {
-        game.tree.handle_delete_variation(event, &mut update_board_events);
+        if event.node_to_delete == game.tree.root() {
+            warn!("Try to delete game tree root");
+            continue;
+        }
+        // 焦点被删除时焦点回到根，需要更新棋盘
+        if game.tree.delete_variation(event.node_to_delete) {
+            update_board_events.write(UpdateBoard {
+                new_board: game.tree.board()
+            });
+        }
     }
 }
\ No newline at end of file
@@ -1,45 +1,61 @@
 use bevy::{
-    color::palettes::css::WHITE, input::common_conditions::{input_just_pressed, input_just_released}, prelude::*, render::view::RenderLayers, sprite::Anchor, transform, window::{PrimaryWindow, WindowLevel}
+    color::palettes::css::WHITE, input::common_conditions::{input_just_pressed, input_just_released}, prelude::*, render::view::RenderLayers, sprite::Anchor, window::PrimaryWindow
 };
 use bevy_egui::{
-    egui, EguiContext, EguiContexts, EguiGlobalSettings, EguiPlugin, EguiPrimaryContextPass,
+    EguiGlobalSettings, EguiPlugin, EguiPrimaryContextPass,
     PrimaryEguiContext,
 };
 
-use crate::{
+use chess_core::{
+    bitboard::Bitboard,
     piece::*,
     board::*,
     fen::*,
-    ui_fen::*,
     game_tree::*,
+    odds::play_extra_move,
+    perft::divide,
+    square::{BoardSize, Square},
+    validate::{check_position, describe_issues, ValidationMode},
+    variant::DarkChess,
+};
+use crate::{
+    ui_fen::*,
     ui_game_tree::*,
     menu::*,
     event::*,
+    ui_promotion::*,
+    ui_hot_seat::*,
+    ui_bughouse::*,
+    ui_computer::*,
+    ui_analysis::*,
 };
 
-mod fen;
-mod piece;
-mod board;
-mod step;
-mod pgn;
 mod menu;
 mod ui_fen;
-mod game_tree;
 mod ui_game_tree;
+mod ui_promotion;
+mod ui_hot_seat;
+mod ui_bughouse;
+mod ui_computer;
+mod ui_analysis;
 mod event;
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
-enum GameState {
-    #[default]
-    Playing,
-    GameOver,
-}
-
 const BACKGROUND_COLOR: Color = Color::srgb(0.0, 0.0, 0.0);
 const BLACKCELL_COLOR: Color = Color::srgb(181.0/256.0, 136.0/256.0, 99.0/256.0);
 const WHITECELL_COLOR: Color = Color::srgb(240.0/256.0, 217.0/256.0, 181.0/256.0);
+const CAPTURE_COLOR: Color = Color::srgba(0.9, 0.1, 0.1, 0.6);
+const EXPLOSION_COLOR: Color = Color::srgba(1.0, 0.6, 0.0, 0.8);
+const EFFECT_SECONDS: f32 = 0.6;
+const FOG_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.75);
+const COVER_COLOR: Color = Color::srgb(0.1, 0.1, 0.1);
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).is_some_and(|cmd| cmd == "perft") {
+        run_perft_command(&args[2..]);
+        return;
+    }
+
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -52,34 +68,74 @@ fn main() {
         .init_resource::<Game>()
         .init_resource::<UiMenuState>()
         .init_resource::<UiFenState>()
+        .init_resource::<ComputerPlayer>()
+        .init_resource::<UciConfig>()
+        .init_resource::<Analysis>()
         .insert_resource(ClearColor(BACKGROUND_COLOR))
         .insert_resource(CursorWorldPos(None))
-        .init_state::<GameState>()
         .add_event::<UpdateBoard>()
         .add_event::<DeleteVariationEvent>()
-        .add_systems(Startup, setup)
+        .add_systems(Startup, (setup, setup_eval_bar, setup_bughouse_clocks))
         .add_systems(
             Update, 
             (
                 get_cursor_world_pos,
                 (
-                    start_drag.run_if(input_just_pressed(MouseButton::Left)),
+                    start_drag.run_if(input_just_pressed(MouseButton::Left).and(not(resource_exists::<PendingPromotion>))),
                     end_drag.run_if(input_just_released(MouseButton::Left)),
                     drag.run_if(resource_exists::<DragOperation>),
                 ),
+                start_computer_move,
+                finish_computer_move,
                 update_board.run_if(on_event::<UpdateBoard>),
+                update_eval_bar,
+                fade_effects,
+                piece_sprite_fallback,
+                tick_bughouse_clocks,
+                update_bughouse_clocks,
             ).chain(),
         )
         .add_systems(
             EguiPrimaryContextPass, 
             (
-                (ui_fen_system, ui_game_tree, ui_menu),
+                (ui_fen_system, ui_game_tree, ui_menu, ui_promotion, ui_hot_seat, ui_bughouse, ui_computer, ui_analysis),
                 handle_delete_variation_events
             ).chain(),
         )
         .run();
 }
 
+// 命令行 `bevy_chess perft <depth> [fen]`：按 UCI 记法逐步输出 divide 结果和总数
+fn run_perft_command(args: &[String]) {
+    let Some(depth) = args.first().and_then(|d| d.parse::<usize>().ok()) else {
+        eprintln!("usage: bevy_chess perft <depth> [fen]");
+        return
+    };
+    let fen = if args.len() > 1 { args[1..].join(" ") } else { INITIAL_FEN.to_string() };
+    let board = match read_fen(fen) {
+        Ok(board) => board,
+        Err(e) => {
+            eprintln!("invalid FEN: {}", e);
+            return
+        }
+    };
+    let board = match check_position(board, ValidationMode::Lenient) {
+        Ok(board) => board,
+        Err(issues) => {
+            eprintln!("illegal position: {}", describe_issues(&issues));
+            return
+        }
+    };
+
+    let mut total = 0;
+    for (step, nodes) in divide(&board, depth) {
+        println!("{}: {}", step, nodes);
+        total += nodes;
+    }
+    println!();
+    println!("Nodes searched: {}", total);
+}
+
 /// The projected 2D world coordinates of the cursor (if it's within primary window bounds).
 #[derive(Resource)]
 struct CursorWorldPos(Option<Vec2>);
@@ -90,33 +146,106 @@ struct MainCamera;
 /// The current drag operation including the offset with which we grabbed the Bevy logo.
 #[derive(Resource)]
 struct DragOperation {
+    board: usize,               // 双人四人象棋中拖动的棋子所在的棋盘
     dragged_entity: Entity,
     start_cell: Entity,
+    origin: Vec2,               // 拖动失败时棋子回到的位置
+    drop: Option<PieceRole>,    // 从手中拖出的棋子，此时 start_cell 为手中棋子的实体
+}
+
+// 吃子或爆炸时在格子上短暂显示的色块，逐渐变淡后消失
+#[derive(Component)]
+struct FadeEffect {
+    timer: Timer,
+    alpha: f32,
+}
+
+// 棋盘旁边显示的手中棋子（疯狂屋）
+#[derive(Component)]
+struct PocketCom {
+    piece: Piece,
+    board: usize,
+}
+
+// 棋子图片还没有确认能否读取。没有图片的棋子（如大象棋中的大主教和大臣）改为色块加字母
+#[derive(Component)]
+struct PieceSprite {
+    piece: Piece,
 }
 
 #[derive(Component)]
 struct PieceCom {
-    x: usize,
-    y: usize,
     entity: Option<Entity>,
 }
 
 #[derive(Component, Clone)]
 struct CellCom {
-    x: usize,
-    y: usize,
+    square: Square,
+    board: usize,
     entity: Option<Entity>,
 }
 
+// 一个棋盘的显示：格子、棋子、迷雾和手中棋子的实体。双人四人象棋同时显示两个棋盘
+#[derive(Default)]
+struct BoardView {
+    index: usize,               // 在双人四人象棋中是第几盘，其它对局为 0
+    board: Board,               // 正在显示的盘面
+    cells: Vec<Vec<CellCom>>,
+    pieces: Vec<Vec<Option<PieceCom>>>,
+    shown: Pieces,              // 棋盘上显示的棋子，暗棋中不包括看不到的对方棋子
+    leftdown: (f32, f32),
+    fog_entities: Vec<Entity>,
+    pocket_entities: Vec<Entity>,
+}
+
 #[derive(Resource, Default)]
 struct Game {
     board: Board,
     tree: GameTree,
-    result: Option<BoardResult>,
-    cells: Vec<Vec<CellCom>>,
-    pieces: Vec<Vec<Option<PieceCom>>>,
-    leftdown: (f32, f32),
+    view: BoardView,
+    covered: bool,              // 暗棋中轮到另一方时遮住整个棋盘，等待交换座位
     info: Option<Entity>,
+    bughouse: Option<BughouseState>,    // 双人四人象棋，此时 board 为 A 盘，棋局树不使用
+    partner_view: BoardView,            // 双人四人象棋的 B 盘
+    extra_moves: usize,                 // 让子棋中被让方还可以连走的步数
+    result: Option<BoardResult>,        // 当前局面的棋局结果，在 update_board 中计算一次
+}
+
+impl Game {
+    // 第 index 盘的盘面
+    fn board_at(&self, index: usize) -> &Board {
+        match &self.bughouse {
+            Some(bughouse) => &bughouse.game.boards[index],
+            None => &self.board,
+        }
+    }
+
+    fn view_at(&self, index: usize) -> &BoardView {
+        if index == 0 { &self.view } else { &self.partner_view }
+    }
+
+    // 棋盘中心的横坐标。双人四人象棋的两个棋盘左右排列
+    fn center_x(&self, index: usize) -> f32 {
+        if self.bughouse.is_some() { BUGHOUSE_CENTER_X[index] } else { 0.0 }
+    }
+
+    fn result(&self) -> Option<BoardResult> {
+        self.result
+    }
+
+    fn hides_position(&self) -> bool {
+        hides_position(&self.board, self.result())
+    }
+}
+
+// 暗棋在终局之前只能看到行动方看得到的格子，FEN、棋局树和分析都不能显示完整的盘面
+fn hides_position(board: &Board, result: Option<BoardResult>) -> bool {
+    board.variant.rules().fog_of_war() && result.is_none()
+}
+
+// FEN 窗口中显示的当前局面，暗棋进行中为 None
+fn current_fen(board: &Board, result: Option<BoardResult>) -> Option<String> {
+    (!hides_position(board, result)).then(|| write_fen(board.clone()))
 }
 
 #[derive(Event)]
@@ -127,6 +256,7 @@ struct UpdateBoard {
 const CELL_SIZE_I: f32 = 50.0;
 const CELL_SIZE_J: f32 = 50.0;
 const CELL_SIZE: Vec2 = Vec2::new(CELL_SIZE_I, CELL_SIZE_J);
+const BUGHOUSE_CENTER_X: [f32; 2] = [-200.0, 320.0];
 
 fn setup(
     mut commands: Commands, 
@@ -137,94 +267,236 @@ fn setup(
 ) {
     egui_global_settings.auto_create_primary_context = false;
 
-    commands.spawn((Camera2d::default(), MainCamera));
+    commands.spawn((Camera2d, MainCamera));
 
-    ui_state.current_fen = INITIAL_FEN.to_string();
+    ui_state.current_fen = Some(INITIAL_FEN.to_string());
 
     // setup game tree
     game.tree = GameTree::new(game.board.clone());
 
+    game.partner_view.index = 1;
+    let board = game.board.clone();
+    refresh_view(&mut commands, &mut game.view, &board, 0.0, Bitboard::ALL, false, &asset_server);
+
+    // game info
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let text_font = TextFont {
+        font: font.clone(),
+        font_size: 50.0,
+        ..default()
+    };
+    game.info = Some(commands.spawn((
+        Text2d::new("White play"),
+        text_font.clone(),
+        TextColor(WHITE.into()),
+        Transform::from_xyz(0.0, info_y(game.board.size), 0.0),
+        Anchor::Center, 
+    )).id());
+
+    // Egui camera.
+    commands.spawn((
+        PrimaryEguiContext,
+        Camera2d,
+        RenderLayers::none(),
+        Camera {
+            order: 1,
+            ..default()
+        },
+    ));
+}
+
+// 棋盘左下角的坐标，使棋盘的中心在 (center_x, 0)
+fn board_leftdown(size: BoardSize, center_x: f32) -> (f32, f32) {
+    let center_y = 0.0;
+    (center_x - size.files as f32 / 2.0 * CELL_SIZE_I, center_y - size.ranks as f32 / 2.0 * CELL_SIZE_J)
+}
+
+// 局面信息显示在棋盘上方
+fn info_y(size: BoardSize) -> f32 {
+    size.ranks as f32 / 2.0 * CELL_SIZE_J + 50.0
+}
+
+// 把棋盘显示为 new，只有 visible 中的格子可以看到。covered 为 true 时遮住整个棋盘
+#[allow(clippy::too_many_arguments)]
+fn refresh_view(
+    commands: &mut Commands,
+    view: &mut BoardView,
+    new: &Board,
+    center_x: f32,
+    visible: Bitboard,
+    covered: bool,
+    asset_server: &AssetServer,
+) {
+    // 棋盘大小或位置改变时重新生成整个棋盘
+    if view.cells.is_empty() || board_leftdown(new.size, center_x) != view.leftdown {
+        view.board = new.clone();
+        spawn_board(commands, view, center_x);
+    }
+
+    // 新盘面由旧盘面走一步得到时，标出被吃和被炸掉的棋子
+    let (squares, color) = capture_feedback(&view.board, new);
+    for sq in squares.into_iter().filter(|sq| visible.contains(sq.index())) {
+        let (leftdown_x, leftdown_y) = view.leftdown;
+        let (x, y) = (leftdown_x + (sq.file() as f32 + 0.5) * CELL_SIZE_I, leftdown_y + (sq.rank() as f32 + 0.5) * CELL_SIZE_J);
+        commands.spawn((
+            Sprite::from_color(color, CELL_SIZE),
+            Transform::from_xyz(x, y, 2.0),
+            FadeEffect {
+                timer: Timer::from_seconds(EFFECT_SECONDS, TimerMode::Once),
+                alpha: color.alpha(),
+            },
+        ));
+    }
+
+    let mut shown = new.pieces;
+    for sq in new.size.squares().filter(|sq| !visible.contains(sq.index())) {
+        shown[sq] = None;
+    }
+    view.board = new.clone();
+    sync_pieces(commands, view, asset_server, shown);
+    spawn_fog(commands, view, visible, covered);
+    spawn_pockets(commands, view, asset_server);
+}
+
+// 删除棋盘的所有实体，如离开双人四人象棋时的 B 盘
+fn clear_view(commands: &mut Commands, view: &mut BoardView) {
+    let entities = view.cells.iter().flatten().filter_map(|cell| cell.entity)
+        .chain(view.pieces.iter().flatten().flatten().filter_map(|piece| piece.entity))
+        .chain(view.fog_entities.drain(..))
+        .chain(view.pocket_entities.drain(..));
+    for entity in entities {
+        commands.entity(entity).despawn();
+    }
+    *view = BoardView {
+        index: view.index,
+        ..default()
+    };
+}
+
+// 按 view.board 的大小重新生成格子，并清空棋子
+fn spawn_board(commands: &mut Commands, view: &mut BoardView, center_x: f32) {
+    for entity in view.cells.iter().flatten().filter_map(|cell| cell.entity) {
+        commands.entity(entity).despawn();
+    }
+    for entity in view.pieces.iter().flatten().flatten().filter_map(|piece| piece.entity) {
+        commands.entity(entity).despawn();
+    }
+
+    let size = view.board.size;
+    let (leftdown_x, leftdown_y) = board_leftdown(size, center_x);
+    view.leftdown = (leftdown_x, leftdown_y);
+
     // setup cells
-    game.cells = (0..BOARD_SIZE_I)
-            .map(|i| (0..BOARD_SIZE_J).map(|j| CellCom {
-                x: i,
-                y: j,
+    view.cells = (0..size.files)
+            .map(|i| (0..size.ranks).map(|j| CellCom {
+                square: Square::new(i, j),
+                board: view.index,
                 entity: None,
             }).collect())
             .collect();
 
-    let (center_x, center_y) = (0.0, 0.0);
-    let (leftdown_x, leftdown_y) = (center_x - BOARD_SIZE_I as f32 / 2.0 * CELL_SIZE_I, center_y - BOARD_SIZE_J as f32 / 2.0 * CELL_SIZE_J);
-    game.leftdown = (leftdown_x, leftdown_y);
-
-    for i in 0..BOARD_SIZE_I {
-        for j in 0..BOARD_SIZE_J {
+    for i in 0..size.files {
+        for j in 0..size.ranks {
             let color = if (i+j) % 2 == 0 {
                 BLACKCELL_COLOR
             } else {
                 WHITECELL_COLOR
             };
             let (x, y) = (leftdown_x + (i as f32 + 0.5) * CELL_SIZE_I, leftdown_y + (j as f32 + 0.5) * CELL_SIZE_J);
-            game.cells[i][j].entity = Some(commands.spawn((
+            view.cells[i][j].entity = Some(commands.spawn((
                 Sprite::from_color(color, CELL_SIZE),
                 Transform::from_xyz(x, y, 0.0),
-                game.cells[i][j].clone(),
+                view.cells[i][j].clone(),
             )).id());
         }
     }
 
-    // setup pieces
-    game.pieces = (0..BOARD_SIZE_I)
-            .map(|_| (0..BOARD_SIZE_J).map(|_| None)
+    // 棋子由 sync_pieces 生成
+    view.pieces = (0..size.files)
+            .map(|_| (0..size.ranks).map(|_| None)
             .collect())
             .collect();
+    view.shown = Pieces::new();
+}
 
-    for i in 0..BOARD_SIZE_I {
-        for j in 0..BOARD_SIZE_J {
-            if let Some(ref mut piece) = game.board.pieces[i][j] {
+// 按 shown 更新棋盘上显示的棋子，只重新生成改变了的格子
+fn sync_pieces(commands: &mut Commands, view: &mut BoardView, asset_server: &AssetServer, shown: Pieces) {
+    let size = view.board.size;
+    let (leftdown_x, leftdown_y) = view.leftdown;
+    for i in 0..size.files {
+        for j in 0..size.ranks {
+            if view.shown[i][j] == shown[i][j] {
+                continue;
+            }
+            if let Some(old_piece) = view.pieces[i][j].take() {
+                commands.entity(old_piece.entity.unwrap()).despawn();
+            }
+            if let Some(piece) = shown[i][j] {
                 let (x, y) = (leftdown_x + (i as f32 + 0.5) * CELL_SIZE_I, leftdown_y + (j as f32 + 0.5) * CELL_SIZE_J);
                 let e = commands.spawn((
-                    {
-                        let mut sprite = Sprite::from_image(asset_server.load(format!("chess_pieces/{}.png", piece.to_string())));
-                        sprite.custom_size = Some(CELL_SIZE);
-                        sprite
-                    },
+                    piece_sprite(asset_server, piece),
                     Transform::from_xyz(x, y, 1.0),
                 )).id();
-                game.pieces[i][j] = Some(PieceCom {
-                    x: i,
-                    y: j,
+                view.pieces[i][j] = Some(PieceCom {
                     entity: Some(e),
                 });
             }
         }
     }
+    view.shown = shown;
+}
 
-    // game info
-    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-    let text_font = TextFont {
-        font: font.clone(),
-        font_size: 50.0,
-        ..default()
-    };
-    game.info = Some(commands.spawn((
-        Text2d::new("White play"),
-        text_font.clone(),
-        TextColor(WHITE.into()),
-        Transform::from_xyz(0.0, 250.0, 0.0),
-        Anchor::Center, 
-    )).id());
+fn piece_sprite(asset_server: &AssetServer, piece: Piece) -> (Sprite, PieceSprite) {
+    let mut sprite = Sprite::from_image(asset_server.load(format!("chess_pieces/{}.png", piece)));
+    sprite.custom_size = Some(CELL_SIZE);
+    (sprite, PieceSprite { piece })
+}
 
-    // Egui camera.
-    commands.spawn((
-        PrimaryEguiContext,
-        Camera2d,
-        RenderLayers::none(),
-        Camera {
-            order: 1,
-            ..default()
-        },
-    ));
+// 棋子图片读取失败时，换成棋子颜色的色块，上面写棋子的字母
+fn piece_sprite_fallback(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut q_piece: Query<(Entity, &PieceSprite, &mut Sprite)>,
+) {
+    for (entity, piece_sprite, mut sprite) in q_piece.iter_mut() {
+        if asset_server.is_loaded(sprite.image.id()) {
+            commands.entity(entity).remove::<PieceSprite>();
+            continue;
+        }
+        if !asset_server.load_state(sprite.image.id()).is_failed() {
+            continue;
+        }
+        let piece = piece_sprite.piece;
+        let (fill, text) = match piece.piece_color {
+            PieceColor::White => (Color::srgb(0.95, 0.95, 0.95), Color::BLACK),
+            PieceColor::Black => (Color::srgb(0.15, 0.15, 0.15), Color::WHITE),
+        };
+        *sprite = Sprite::from_color(fill, CELL_SIZE * 0.7);
+        commands.entity(entity).remove::<PieceSprite>().with_child((
+            Text2d::new(piece_letter(piece.piece_role)),
+            TextFont {
+                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 30.0,
+                ..default()
+            },
+            TextColor(text),
+            Transform::from_xyz(0.0, 0.0, 0.1),
+            Anchor::Center,
+        ));
+    }
+}
+
+fn piece_letter(role: PieceRole) -> &'static str {
+    match role {
+        PieceRole::Pawn => "P",
+        PieceRole::Rook => "R",
+        PieceRole::Knight => "N",
+        PieceRole::Bishop => "B",
+        PieceRole::Queen => "Q",
+        PieceRole::King => "K",
+        PieceRole::Archbishop => "A",
+        PieceRole::Chancellor => "C",
+    }
 }
 
 /// Project the cursor into the world coordinates and store it in a resource for easy use
@@ -257,7 +529,7 @@ fn cursor_cell(
             return Some(entity)
         }
     }
-    return None
+    None
 }
 
 fn start_drag(
@@ -266,12 +538,34 @@ fn start_drag(
     cursor_world_pos: Res<CursorWorldPos>,
     q_cell: Query<(&Sprite, &Transform, Entity), With<CellCom>>,
     cells: Query<&CellCom>,
+    q_pocket: Query<(&Transform, &PocketCom, Entity)>,
+    computer: Res<ComputerPlayer>,
 ) {
     // If the cursor is not within the primary window skip this system
     let Some(cursor_world_pos) = cursor_world_pos.0 else {
         return;
     };
 
+    if game.covered || computer.blocks_input(&game) {
+        return;
+    }
+
+    // 从手中拖出棋子，只能拖行动方的棋子
+    for (transform, pocket, entity) in q_pocket.iter() {
+        let origin = transform.translation.truncate();
+        if pocket.piece.piece_color == game.board_at(pocket.board).active_color
+            && (cursor_world_pos - origin).abs().cmple(CELL_SIZE / 2.0).all() {
+            commands.insert_resource(DragOperation {
+                board: pocket.board,
+                dragged_entity: entity,
+                start_cell: entity,
+                origin,
+                drop: Some(pocket.piece.piece_role),
+            });
+            return;
+        }
+    }
+
     let Some(cell) = cursor_cell(cursor_world_pos, q_cell) else {
         return;
     };
@@ -281,13 +575,16 @@ fn start_drag(
         return;
     };
 
-    let x = cell_com.x;
-    let y = cell_com.y;
+    let square = cell_com.square;
 
-    if let Some(piece) = &game.pieces[x][y] {
+    if let Some(piece) = &game.view_at(cell_com.board).pieces[square.file()][square.rank()]
+        && let Ok((_sprite, transform, _entity)) = q_cell.get(cell) {
         commands.insert_resource(DragOperation {
+            board: cell_com.board,
             dragged_entity: piece.entity.unwrap(),
             start_cell: cell,
+            origin: transform.translation.truncate(),
+            drop: None,
         });
     }
 }
@@ -318,7 +615,7 @@ fn update_board(
     mut commands: Commands, 
     mut game: ResMut<Game>,
     asset_server: Res<AssetServer>,
-    mut q_text: Query<&mut Text2d>,
+    mut q_text: Query<(&mut Text2d, &mut Transform)>,
     mut ui_state: ResMut<UiFenState>,
 ) {
     let mut new_board = None;
@@ -330,39 +627,187 @@ fn update_board(
         return;
     };
     
-    for i in 0..BOARD_SIZE_I {
-        for j in 0..BOARD_SIZE_J {
-            if game.board.pieces[i][j] != new_board.pieces[i][j] {
-                if let Some(old_piece) = &game.pieces[i][j] {
-                    commands.entity(old_piece.entity.unwrap()).despawn();
-                }
-                if let Some(new_piece) = &new_board.pieces[i][j] {
-                    let (leftdown_x, leftdown_y) = game.leftdown;
-                    let (x, y) = (leftdown_x + (i as f32 + 0.5) * CELL_SIZE_I, leftdown_y + (j as f32 + 0.5) * CELL_SIZE_J);
-                    let e = commands.spawn((
-                        {
-                            let mut sprite = Sprite::from_image(asset_server.load(format!("chess_pieces/{}.png", new_piece.to_string())));
-                            sprite.custom_size = Some(CELL_SIZE);
-                            sprite
-                        },
-                        Transform::from_xyz(x, y, 1.0),
-                    )).id();
-                    game.pieces[i][j] = Some(PieceCom {
-                        x: i,
-                        y: j,
-                        entity: Some(e),
-                    });
-                }
-                
+    // 判断终局要检查整个棋局历史中的重复局面，只在盘面变化时计算
+    let result = game.tree.result();
+    game.result = result;
+
+    // 暗棋中只显示行动方能看到的格子。换人走棋时先遮住整个棋盘，终局后显示全部棋子
+    let fog = hides_position(new_board, result);
+    if !fog {
+        game.covered = false;
+    } else if new_board.active_color != game.board.active_color {
+        game.covered = true;
+    }
+    let visible = if !fog {
+        Bitboard::ALL
+    } else if game.covered {
+        Bitboard::EMPTY
+    } else {
+        DarkChess::visible_squares(new_board, new_board.active_color)
+    };
+
+    let game = &mut *game;
+    let center_x = game.center_x(0);
+    refresh_view(&mut commands, &mut game.view, new_board, center_x, visible, game.covered, &asset_server);
+    game.board = new_board.clone();
+
+    // 双人四人象棋的 B 盘随 A 盘一起更新
+    let info = match &game.bughouse {
+        Some(bughouse) => {
+            let partner = bughouse.game.boards[1].clone();
+            refresh_view(&mut commands, &mut game.partner_view, &partner, BUGHOUSE_CENTER_X[1], Bitboard::ALL, false, &asset_server);
+            bughouse_info(&bughouse.game)
+        }
+        None => {
+            clear_view(&mut commands, &mut game.partner_view);
+            game_info(&game.board, result)
+        }
+    };
+
+    let (mut text, mut transform) = q_text.get_mut(game.info.unwrap()).unwrap();
+    text.0 = info;
+    transform.translation.x = (game.center_x(0) + game.center_x(1)) / 2.0;
+    transform.translation.y = info_y(game.board.size);
+    ui_state.current_fen = current_fen(new_board, result);
+}
+
+// 在第 index 盘走一步并更新棋盘。双人四人象棋由 BughouseGame 处理，其它对局同时记入棋局树
+fn play_step(game: &mut Game, index: usize, step: Step, event_writer: &mut EventWriter<UpdateBoard>) -> bool {
+    let new_board = match &mut game.bughouse {
+        Some(bughouse) => {
+            if !bughouse.game.play(index, step) {
+                return false
             }
+            bughouse.game.boards[0].clone()
+        }
+        // 让子棋中被让方连走的着法不记入棋局树，棋局从连走之后的局面开始
+        None if game.extra_moves > 0 => {
+            let Some(new_board) = play_extra_move(&game.board, step) else {
+                return false
+            };
+            game.extra_moves -= 1;
+            game.tree = GameTree::new(new_board.clone());
+            new_board
         }
+        // 棋局结束（如协议和棋）后不能再走棋
+        None => {
+            let Some(new_board) = try_move(&game.board, step).filter(|_| game.tree.try_move(step)) else {
+                return false
+            };
+            new_board
+        }
+    };
+    event_writer.write(UpdateBoard {
+        new_board,
+    });
+    true
+}
+
+// 找出从 old 走到 new 的一步，返回被吃的格子和显示的颜色。原子象棋中包括爆炸的中心和被炸掉的棋子。
+// new 不是由 old 走一步得到的（如读取 FEN、在棋局树中后退）时返回空列表
+fn capture_feedback(old: &Board, new: &Board) -> (Vec<Square>, Color) {
+    let explodes = old.variant.rules().explodes();
+    let color = if explodes { EXPLOSION_COLOR } else { CAPTURE_COLOR };
+    if old.active_color == new.active_color {
+        return (Vec::new(), color);
     }
-    game.board = new_board.clone();
+    let mv = all_move(old).into_iter().find_map(|step| {
+        let mut board = old.clone();
+        let mv = board.make_move(step);
+        (board == *new).then_some(mv)
+    });
+    let Some(mv) = mv.filter(|mv| mv.flags.capture) else {
+        return (Vec::new(), color);
+    };
+    let mut squares = vec![mv.captured_square];
+    if explodes {
+        squares.push(mv.step.to);
+        squares.extend(mv.exploded.iter().flatten().map(|&(sq, _)| sq));
+        squares.dedup();
+    }
+    (squares, color)
+}
+
+fn fade_effects(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_effect: Query<(Entity, &mut FadeEffect, &mut Sprite)>,
+) {
+    for (entity, mut effect, mut sprite) in q_effect.iter_mut() {
+        effect.timer.tick(time.delta());
+        if effect.timer.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_alpha(effect.alpha * effect.timer.fraction_remaining());
+        }
+    }
+}
 
-    q_text.get_mut(game.info.unwrap()).unwrap().0 = game_info(&game.board);
-    ui_state.current_fen = write_fen(new_board.clone());
+// 遮住 visible 以外的格子。每次更新棋盘时重新生成
+fn spawn_fog(commands: &mut Commands, view: &mut BoardView, visible: Bitboard, covered: bool) {
+    for entity in view.fog_entities.drain(..) {
+        commands.entity(entity).despawn();
+    }
+    let color = if covered { COVER_COLOR } else { FOG_COLOR };
+    let (leftdown_x, leftdown_y) = view.leftdown;
+    for sq in view.board.size.squares().filter(|sq| !visible.contains(sq.index())) {
+        let (x, y) = (leftdown_x + (sq.file() as f32 + 0.5) * CELL_SIZE_I, leftdown_y + (sq.rank() as f32 + 0.5) * CELL_SIZE_J);
+        let entity = commands.spawn((
+            Sprite::from_color(color, CELL_SIZE),
+            Transform::from_xyz(x, y, 0.5),
+        )).id();
+        view.fog_entities.push(entity);
+    }
 }
 
+// 在棋盘右侧显示双方手中的棋子和数量，白方在下，黑方在上。每次更新棋盘时重新生成
+fn spawn_pockets(commands: &mut Commands, view: &mut BoardView, asset_server: &AssetServer) {
+    for entity in view.pocket_entities.drain(..) {
+        commands.entity(entity).despawn();
+    }
+    if !view.board.variant.rules().has_pockets() {
+        return;
+    }
+
+    let (leftdown_x, leftdown_y) = view.leftdown;
+    let size = view.board.size;
+    let x = leftdown_x + (size.files as f32 + 1.0) * CELL_SIZE_I;
+    let text_font = TextFont {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 20.0,
+        ..default()
+    };
+    for color in PieceColor::ALL {
+        let pocket = view.board.pockets[color.index()];
+        for (k, role) in POCKET_ROLES.into_iter().enumerate() {
+            let count = pocket.count(role);
+            if count == 0 {
+                continue;
+            }
+            let row = match color {
+                PieceColor::White => k as f32 + 0.5,
+                PieceColor::Black => size.ranks as f32 - k as f32 - 0.5,
+            };
+            let y = leftdown_y + row * CELL_SIZE_J;
+            let piece = Piece { piece_role: role, piece_color: color };
+            let sprite = commands.spawn((
+                piece_sprite(asset_server, piece),
+                Transform::from_xyz(x, y, 1.0),
+                PocketCom { piece, board: view.index },
+            )).id();
+            let text = commands.spawn((
+                Text2d::new(count.to_string()),
+                text_font.clone(),
+                TextColor(WHITE.into()),
+                Transform::from_xyz(x + CELL_SIZE_I * 0.6, y - CELL_SIZE_J * 0.3, 1.0),
+                Anchor::Center,
+            )).id();
+            view.pocket_entities.extend([sprite, text]);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn end_drag(
     drag_operation: Option<Res<DragOperation>>,
     mut commands: Commands, 
@@ -380,39 +825,59 @@ fn end_drag(
     let entity = drag_operation.dragged_entity;
 
     let mut moved = false;
-    if let Some(cursor_world_pos) = cursor_world_pos.0 {
-        if let Some(to_cell) = cursor_cell(cursor_world_pos, q_cell) {
-            let from_x = cells.get(drag_operation.start_cell).unwrap().x;
-            let from_y = cells.get(drag_operation.start_cell).unwrap().y;
-            let to_x = cells.get(to_cell).unwrap().x;
-            let to_y = cells.get(to_cell).unwrap().y;
-            let step = Step {
-                from: (from_x, from_y),
-                to: (to_x, to_y), 
-            };
+    if let Some(cursor_world_pos) = cursor_world_pos.0
+        && let Some(to_cell) = cursor_cell(cursor_world_pos, q_cell)
+        && cells.get(to_cell).unwrap().board == drag_operation.board {
+        let index = drag_operation.board;
+        let to = cells.get(to_cell).unwrap().square;
+        let step = match drag_operation.drop {
+            Some(role) => Step::new_drop(role, to),
+            None => Step::new(cells.get(drag_operation.start_cell).unwrap().square, to),
+        };
 
-            if let Some(new_board) = try_move(&game.board, step) {
+        let board = game.board_at(index);
+        if is_promotion(board, step) {
+            // 升变需要先选择棋子，棋子暂时停在目标格
+            if try_move(board, step.with_promotion(PieceRole::Queen)).is_some() {
                 moved = true;
-                event_writer.write(UpdateBoard {
-                    new_board: new_board,
+                if let (Ok((_sprite, to_trans, _entity)), Ok(mut transform)) = (q_cell.get(to_cell), transforms.get_mut(entity)) {
+                    transform.translation.x = to_trans.translation.x;
+                    transform.translation.y = to_trans.translation.y;
+                }
+                commands.insert_resource(PendingPromotion {
+                    board: index,
+                    step,
+                    dragged_entity: entity,
+                    start_cell: drag_operation.start_cell,
                 });
-                game.tree.try_move(step);
             }
+        } else if play_step(&mut game, index, step, &mut event_writer) {
+            moved = true;
         }
     }
     
-    if !moved {
-        let (x, y) = if let Ok((_sprite, trans, _entity)) = q_cell.get(drag_operation.start_cell) {
-            (trans.translation.x, trans.translation.y)
-        } else {
-            error!("piece move failed and cannot find the start cell");
-            (cursor_world_pos.0.unwrap().x, cursor_world_pos.0.unwrap().y)
-        };
-        if let Ok(mut transform) = transforms.get_mut(entity) {
-            transform.translation.x = x;
-            transform.translation.y = y;
-        }
+    if !moved && let Ok(mut transform) = transforms.get_mut(entity) {
+        transform.translation.x = drag_operation.origin.x;
+        transform.translation.y = drag_operation.origin.y;
     }
 
     commands.remove_resource::<DragOperation>();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chess_core::variant::VariantKind;
+
+    #[test]
+    fn dark_chess_hides_fen() {
+        let board = VariantKind::DarkChess.rules().start_position();
+        assert!(hides_position(&board, None));
+        assert_eq!(current_fen(&board, None), None);
+        // 终局后显示完整的盘面
+        let result = Some(BoardResult::Draw(DrawReason::Agreement));
+        assert_eq!(current_fen(&board, result), Some(write_fen(board.clone())));
+        let board = Board::default();
+        assert_eq!(current_fen(&board, None), Some(INITIAL_FEN.to_string()));
+    }
+}
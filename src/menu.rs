@@ -1,16 +1,47 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
+use chess_core::{
+    board::*, bughouse::BughouseGame, chess960, game_tree::GameTree, odds::Odds, piece::PieceColor,
+    validate::ValidationMode, variant::VariantKind,
+};
 use crate::{
-    fen::*, game_tree::GameTree, Game, UpdateBoard
+    BughouseState, ComputerPlayer, Game, UpdateBoard
 };
 
 #[derive(Default, Resource)]
 pub struct UiMenuState {
     load_pgn: String,
+    load_pgn_error: String,
     load_tree: String, 
     load_tree_error: String,
+    load_bpgn: String,
+    load_bpgn_error: String,
     pub fen_window_open: bool,
     pub tree_window_open: bool,
+    pub analysis_window_open: bool,
+    pub strict_validation: bool,    // 读取 FEN、棋局树和 PGN 时拒绝所有不合法的局面，否则只拒绝致命问题
+    pub variant: VariantKind,       // New Game 和读取 FEN 时使用的变体
+    chess960_fixed: bool,           // 为 true 时 New 960 Game 使用 chess960_sp 指定的初始局面，否则随机选取
+    chess960_sp: usize,
+    odds: Odds,                     // New Odds Game 使用的让子方式
+    odds_giver: PieceColor,         // 让子的一方
+}
+
+impl UiMenuState {
+    pub fn validation_mode(&self) -> ValidationMode {
+        if self.strict_validation { ValidationMode::Strict } else { ValidationMode::Lenient }
+    }
+}
+
+// 从 variant 的初始局面开始新的棋局，双人四人象棋同时开始两盘棋
+pub fn new_game(game: &mut Game, variant: VariantKind, event_writer: &mut EventWriter<UpdateBoard>) {
+    let new_board = variant.rules().start_position();
+    game.bughouse = (variant == VariantKind::Bughouse).then(|| BughouseState::new(BughouseGame::new()));
+    event_writer.write(UpdateBoard {
+        new_board: new_board.clone(),
+    });
+    game.tree = GameTree::new(new_board);
+    game.extra_moves = 0;
 }
 
 pub fn ui_menu(
@@ -18,6 +49,7 @@ pub fn ui_menu(
     mut contexts: EguiContexts,
     mut event_writer: EventWriter<UpdateBoard>,
     mut game: ResMut<Game>,
+    mut computer: ResMut<ComputerPlayer>,
 ) -> Result {
     let ctx = contexts.ctx_mut()?;
 
@@ -30,20 +62,99 @@ pub fn ui_menu(
 
             ui.checkbox(&mut ui_state.fen_window_open, "show FEN window");
             ui.checkbox(&mut ui_state.tree_window_open, "show game tree");
+            ui.checkbox(&mut ui_state.analysis_window_open, "show engine analysis");
+            ui.checkbox(&mut ui_state.strict_validation, "strict position validation");
 
             ui.separator();
 
+            egui::ComboBox::from_label("Variant")
+                .selected_text(ui_state.variant.name())
+                .show_ui(ui, |ui| {
+                    for variant in VariantKind::ALL {
+                        ui.selectable_value(&mut ui_state.variant, variant, variant.name());
+                    }
+                });
+
             if ui.button("New Game").clicked() {
-                let new_board = read_fen(INITIAL_FEN.to_string());
+                new_game(&mut game, ui_state.variant, &mut event_writer);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("New 960 Game").clicked() {
+                    let sp = if ui_state.chess960_fixed {
+                        ui_state.chess960_sp
+                    } else {
+                        chess960::random_start_position()
+                    };
+                    ui_state.chess960_sp = sp;
+                    let new_board = chess960::start_position(sp);
+                    game.bughouse = None;
+                    event_writer.write(UpdateBoard {
+                        new_board: new_board.clone(),
+                    });
+                    game.tree = GameTree::new(new_board);
+                    game.extra_moves = 0;
+                }
+                ui.checkbox(&mut ui_state.chess960_fixed, "SP");
+                ui.add_enabled(
+                    ui_state.chess960_fixed,
+                    egui::DragValue::new(&mut ui_state.chess960_sp).range(0..=chess960::START_POSITION_COUNT - 1),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("odds")
+                    .selected_text(ui_state.odds.name())
+                    .show_ui(ui, |ui| {
+                        for odds in Odds::ALL {
+                            ui.selectable_value(&mut ui_state.odds, odds, odds.name());
+                        }
+                    });
+                ui.label("given by");
+                egui::ComboBox::from_id_salt("odds_giver")
+                    .selected_text(format!("{:?}", ui_state.odds_giver))
+                    .show_ui(ui, |ui| {
+                        for color in PieceColor::ALL {
+                            ui.selectable_value(&mut ui_state.odds_giver, color, format!("{:?}", color));
+                        }
+                    });
+            });
+            if ui.button("New Odds Game").clicked() {
+                let new_board = ui_state.odds.start_position(ui_state.odds_giver);
+                game.bughouse = None;
                 event_writer.write(UpdateBoard {
                     new_board: new_board.clone(),
                 });
                 game.tree = GameTree::new(new_board);
+                game.extra_moves = ui_state.odds.extra_moves();
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("New game vs computer").clicked() {
+                    computer.window_open = true;
+                }
+                // 停止后双方都由人走棋
+                if let Some(color) = computer.color {
+                    ui.label(format!("computer plays {:?}", color));
+                    if ui.button("Stop").clicked() {
+                        computer.color = None;
+                    }
+                }
+            });
+
+            let finished = game.result().is_some() || game.bughouse.as_ref().is_some_and(|b| b.game.result.is_some());
+            if ui.add_enabled(!finished, egui::Button::new("Agree to draw")).clicked() {
+                match &mut game.bughouse {
+                    Some(bughouse) => bughouse.game.result = Some((0, BoardResult::Draw(DrawReason::Agreement))),
+                    None => game.tree.set_result(BoardResult::Draw(DrawReason::Agreement)),
+                }
+                event_writer.write(UpdateBoard { new_board: game.board.clone() });
             }
 
             ui.separator();
 
-            if ui.button("Copy current game tree").clicked() {
+            // 暗棋进行中的棋局树包含完整的盘面
+            if ui.add_enabled(!game.hides_position(), egui::Button::new("Copy current game tree")).clicked() {
                 ctx.copy_text(game.tree.to_string());
             }
 
@@ -55,24 +166,64 @@ pub fn ui_menu(
             });
             ui.horizontal(|ui| {
                 if ui.button("Load").clicked() {
-                    if let Some(tree) = GameTree::from_string(ui_state.load_tree.clone()) {
-                        game.tree = tree;
-                        event_writer.write(UpdateBoard { new_board: game.tree.board() });
-                    } else {
-                        ui_state.load_tree_error = "invalid tree text".to_string();
+                    match GameTree::from_string(ui_state.load_tree.clone(), ui_state.validation_mode()) {
+                        Ok(tree) => {
+                            ui_state.load_tree_error.clear();
+                            game.tree = tree;
+                            game.extra_moves = 0;
+                            game.bughouse = None;
+                            event_writer.write(UpdateBoard { new_board: game.tree.board() });
+                        }
+                        Err(e) => ui_state.load_tree_error = e.to_string(),
                     }
                 }
-                ui.label(ui_state.load_tree_error.clone());
+                ui.colored_label(ui.visuals().error_fg_color, ui_state.load_tree_error.clone());
             });
 
             ui.horizontal(|ui| {
                 ui.label("Load PGN: ");
-                ui.text_edit_singleline(&mut ui_state.load_pgn);
+                egui::TextEdit::multiline(&mut ui_state.load_pgn)
+                    .desired_rows(2)
+                    .show(ui);
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Load").clicked() {
+                    match GameTree::from_pgn(ui_state.load_pgn.clone(), ui_state.validation_mode()) {
+                        Ok(tree) => {
+                            ui_state.load_pgn_error.clear();
+                            game.tree = tree;
+                            game.extra_moves = 0;
+                            game.bughouse = None;
+                            event_writer.write(UpdateBoard { new_board: game.tree.board() });
+                        }
+                        Err(e) => ui_state.load_pgn_error = e.to_string(),
+                    }
+                }
+                ui.colored_label(ui.visuals().error_fg_color, ui_state.load_pgn_error.clone());
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Load BPGN: ");
+                egui::TextEdit::multiline(&mut ui_state.load_bpgn)
+                    .desired_rows(2)
+                    .show(ui);
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Load").clicked() {
+                    match BughouseGame::from_bpgn(&ui_state.load_bpgn) {
+                        Ok(bughouse) => {
+                            ui_state.load_bpgn_error.clear();
+                            let new_board = bughouse.boards[0].clone();
+                            game.tree = GameTree::new(new_board.clone());
+                            game.extra_moves = 0;
+                            game.bughouse = Some(BughouseState::new(bughouse));
+                            event_writer.write(UpdateBoard { new_board });
+                        }
+                        Err(e) => ui_state.load_bpgn_error = e.to_string(),
+                    }
+                }
+                ui.colored_label(ui.visuals().error_fg_color, ui_state.load_bpgn_error.clone());
             });
-            if ui.button("Load").clicked() {
-                game.tree = GameTree::from_pgn(ui_state.load_pgn.clone());
-                event_writer.write(UpdateBoard { new_board: game.tree.board() });
-            }
         });
 
     Ok(())
@@ -0,0 +1,457 @@
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex};
+use bevy::prelude::*;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use bevy_egui::{egui, EguiContexts};
+use chess_core::{
+    board::*, engine::{Engine, Score, SearchResult}, piece::PieceColor,
+    uci::{parse_options, pv_san, uci_variant, UciEngine, UciEvent, UciInfo},
+};
+use crate::{
+    Game, UpdateBoard, CELL_SIZE_I, CELL_SIZE_J, menu::*,
+};
+
+// 外部 UCI 引擎的路径和选项，分析面板和人机对局共用。路径的默认值取自环境变量 BEVY_CHESS_ENGINE
+#[derive(Resource)]
+pub struct UciConfig {
+    pub path: String,
+    pub options: String,    // 每行一个 "名字 = 值"
+}
+
+impl Default for UciConfig {
+    fn default() -> Self {
+        UciConfig {
+            path: std::env::var("BEVY_CHESS_ENGINE").unwrap_or_default(),
+            options: String::new(),
+        }
+    }
+}
+
+impl UciConfig {
+    // 在后台任务中启动引擎，握手最多要等 HANDSHAKE_TIMEOUT，不能阻塞界面
+    pub fn start(&self) -> Task<Result<UciEngine, String>> {
+        let path = self.path.trim().to_string();
+        let options = parse_options(&self.options);
+        AsyncComputeTaskPool::get().spawn(async move {
+            UciEngine::start(&path, &options).map_err(|e| e.to_string())
+        })
+    }
+
+    // 路径和选项的编辑框
+    pub fn edit(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Engine: ");
+            ui.text_edit_singleline(&mut self.path);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Options: ");
+            egui::TextEdit::multiline(&mut self.options)
+                .desired_rows(2)
+                .hint_text("Threads = 2")
+                .show(ui);
+        });
+    }
+}
+
+// 内置引擎在后台任务中分析，每搜索完一层把所有变例写入 lines
+struct BuiltInAnalysis {
+    engine: Option<Box<Engine>>,    // 分析时交给后台任务，停止后再取回
+    stop: Arc<AtomicBool>,
+    lines: Arc<Mutex<Vec<SearchResult>>>,
+    task: Option<Task<Box<Engine>>>,
+}
+
+impl BuiltInAnalysis {
+    fn new() -> Self {
+        let engine = Engine::new();
+        BuiltInAnalysis {
+            stop: engine.stop_flag(),
+            engine: Some(Box::new(engine)),
+            lines: Arc::new(Mutex::new(Vec::new())),
+            task: None,
+        }
+    }
+
+    // 停止之前的分析，开始分析 board
+    fn start(&mut self, board: Board, history: Vec<Board>, multipv: usize) {
+        if let Some(task) = self.task.take() {
+            self.stop.store(true, Ordering::Relaxed);
+            self.engine = Some(block_on(task));
+        }
+        let Some(mut engine) = self.engine.take() else {
+            return
+        };
+        self.stop.store(false, Ordering::Relaxed);
+        self.lines.lock().unwrap().clear();
+        let lines = self.lines.clone();
+        self.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+            engine.analyze(&board, &history, &[], multipv, |results| {
+                *lines.lock().unwrap() = results.to_vec();
+            });
+            engine
+        }));
+    }
+}
+
+// 丢弃任务不会中断正在进行的搜索，需要先停止
+impl Drop for BuiltInAnalysis {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// 分析使用的引擎
+enum Analyzer {
+    BuiltIn(BuiltInAnalysis),
+    Uci(UciEngine),
+}
+
+// 持续分析棋局树中当前节点的局面，可以使用内置引擎或外部引擎
+#[derive(Resource)]
+pub struct Analysis {
+    external: bool,                     // 使用 UciConfig 中的外部引擎
+    engine: Option<Analyzer>,
+    starting: Option<Task<Result<UciEngine, String>>>,   // 正在启动的外部引擎
+    multipv: usize,
+    position: Option<(usize, Board)>,   // 正在分析的局面和它在棋局树中的节点
+    stale: usize,                       // 外部引擎已经停止但还没有收到 bestmove 的搜索数，这些搜索的输出被忽略
+    lines: Vec<UciInfo>,                // 每条变例最新的结果，按 multipv 排列
+    moves: Vec<Vec<String>>,            // 每条变例带回合数的 SAN，和 lines 一一对应
+    error: String,
+}
+
+impl Default for Analysis {
+    fn default() -> Self {
+        Analysis {
+            external: false,
+            engine: None,
+            starting: None,
+            multipv: 1,
+            position: None,
+            stale: 0,
+            lines: Vec::new(),
+            moves: Vec::new(),
+            error: String::new(),
+        }
+    }
+}
+
+impl Analysis {
+    // 内置引擎立即开始分析，外部引擎在后台启动，完成后由 update 取回
+    fn start(&mut self, config: &UciConfig) {
+        self.error.clear();
+        if self.external {
+            self.starting = Some(config.start());
+        } else {
+            self.set_engine(Analyzer::BuiltIn(BuiltInAnalysis::new()));
+        }
+    }
+
+    fn set_engine(&mut self, engine: Analyzer) {
+        self.engine = Some(engine);
+        self.position = None;
+        self.stale = 0;
+        self.lines.clear();
+        self.moves.clear();
+    }
+
+    // 停止分析，放弃正在启动的引擎
+    fn stop(&mut self) {
+        self.engine = None;
+        self.starting = None;
+    }
+
+    // 正在分析的局面
+    fn board(&self) -> Option<&Board> {
+        self.position.as_ref().map(|(_, board)| board)
+    }
+
+    // 第一条变例的分数，从白方的角度
+    fn white_score(&self) -> Option<Score> {
+        let score = self.lines.first()?.score?;
+        match self.board()?.active_color {
+            PieceColor::White => Some(score),
+            PieceColor::Black => Some(match score {
+                Score::Cp(cp) => Score::Cp(-cp),
+                Score::Mate(n) => Score::Mate(-n),
+            }),
+        }
+    }
+
+    // 焦点或局面变化时停止之前的搜索，开始分析新的局面；然后读取引擎的输出
+    fn update(&mut self, game: &Game) -> Result<(), String> {
+        if self.starting.as_ref().is_some_and(|task| task.is_finished()) {
+            let mut engine = block_on(self.starting.take().unwrap())?;
+            if engine.has_option("MultiPV") {
+                let _ = engine.set_option("MultiPV", &self.multipv.to_string());
+            }
+            self.set_engine(Analyzer::Uci(engine));
+        }
+        let Some(engine) = &mut self.engine else {
+            return Ok(())
+        };
+        // 焦点和盘面都没有变化时不需要重新开始。game.board 在 update_board 中才更新，只用来比较，局面从棋局树中取出
+        let focus = game.tree.focus();
+        let unchanged = self.position.as_ref().is_some_and(|(analysed, board)| *analysed == focus && *board == game.board);
+        let mut changed = false;
+        if !unchanged {
+            // 双人四人象棋的 A 盘不在棋局树中，只给出当前局面
+            let (board, root, steps, history) = match game.bughouse {
+                Some(_) => (game.board.clone(), game.board.clone(), Vec::new(), Vec::new()),
+                None => (game.tree.board(), game.tree.root_board(), game.tree.steps(), game.tree.history()),
+            };
+            match engine {
+                Analyzer::BuiltIn(engine) => engine.start(board.clone(), history, self.multipv),
+                Analyzer::Uci(engine) => {
+                    if self.position.is_some() {
+                        engine.stop().map_err(|e| e.to_string())?;
+                        self.stale += 1;
+                    }
+                    engine.set_position(&root, &steps).and_then(|_| engine.go(&[])).map_err(|e| e.to_string())?;
+                }
+            }
+            self.position = Some((focus, board));
+            self.lines.clear();
+            changed = true;
+        }
+        match engine {
+            Analyzer::BuiltIn(engine) => {
+                let lines = engine.lines.lock().unwrap();
+                // 搜索完一层才会更新，大多数帧中没有变化
+                let same = lines.len() == self.lines.len()
+                    && lines.iter().zip(&self.lines).all(|(result, info)| {
+                        info.depth == Some(result.depth) && info.score == Some(result.score) && info.pv == result.pv
+                    });
+                if !same {
+                    self.lines = lines.iter().enumerate().map(|(i, result)| UciInfo {
+                        depth: Some(result.depth),
+                        multipv: i + 1,
+                        score: Some(result.score),
+                        nodes: Some(result.nodes),
+                        pv: result.pv.clone(),
+                    }).collect();
+                    changed = true;
+                }
+            }
+            Analyzer::Uci(engine) => {
+                for event in engine.poll().map_err(|e| e.to_string())? {
+                    match event {
+                        UciEvent::BestMove(_) => self.stale = self.stale.saturating_sub(1),
+                        UciEvent::Info(info) if self.stale == 0 && !info.pv.is_empty() => {
+                            let index = info.multipv.max(1) - 1;
+                            if self.lines.len() <= index {
+                                self.lines.resize(index + 1, info.clone());
+                            }
+                            self.lines[index] = info;
+                            changed = true;
+                        }
+                        UciEvent::Info(_) => {},
+                    }
+                }
+            }
+        }
+        // 变例变化时才转换为 SAN，每帧绘制时直接使用
+        if changed && let Some((_, board)) = &self.position {
+            self.moves = self.lines.iter().map(|info| pv_moves(board, &pv_san(board, &info.pv))).collect();
+        }
+        Ok(())
+    }
+}
+
+// 变例中每一步带回合数的写法，如 "12...Nf6"、"13.Bg5"、"h6"
+pub fn pv_moves(board: &Board, sans: &[String]) -> Vec<String> {
+    let mut fullmove = board.fullmove;
+    let mut color = board.active_color;
+    let mut moves = Vec::new();
+    for (i, san) in sans.iter().enumerate() {
+        match color {
+            PieceColor::White => moves.push(format!("{}.{}", fullmove, san)),
+            PieceColor::Black if i == 0 => moves.push(format!("{}...{}", fullmove, san)),
+            PieceColor::Black => moves.push(san.clone()),
+        }
+        if color == PieceColor::Black {
+            fullmove += 1;
+        }
+        color = color.flip();
+    }
+    moves
+}
+
+// 把变例的前几步加入棋局树，作为当前节点之后的变着，并把焦点移到最后一步
+fn insert_line(game: &mut Game, steps: &[Step], event_writer: &mut EventWriter<UpdateBoard>) {
+    for &step in steps {
+        if !game.tree.try_move(step) {
+            break
+        }
+    }
+    event_writer.write(UpdateBoard {
+        new_board: game.tree.board(),
+    });
+}
+
+pub fn ui_analysis(
+    mut contexts: EguiContexts,
+    mut analysis: ResMut<Analysis>,
+    mut config: ResMut<UciConfig>,
+    mut ui_menu: ResMut<UiMenuState>,
+    mut game: ResMut<Game>,
+    mut event_writer: EventWriter<UpdateBoard>,
+) -> Result {
+    // 关闭面板时停止分析
+    if !ui_menu.analysis_window_open {
+        analysis.stop();
+        return Ok(())
+    }
+    // 和电脑对局一样，暗棋进行中不能分析，否则变例和分数会暴露对方的棋子
+    let hidden = game.hides_position();
+    if hidden {
+        analysis.stop();
+        analysis.position = None;
+        analysis.lines.clear();
+        analysis.moves.clear();
+    }
+    if let Err(e) = analysis.update(&game) {
+        analysis.stop();
+        analysis.error = e;
+    }
+    let ctx = contexts.ctx_mut()?;
+
+    // 点击变例中的一步时，把到这一步为止的着法加入棋局树。双人四人象棋和让子棋连走时没有棋局树可以加入
+    let can_insert = game.bughouse.is_none() && game.extra_moves == 0;
+    // 暗棋和双人四人象棋无法告诉外部引擎
+    let variant = game.board.variant;
+    let uci_supported = uci_variant(variant).is_some();
+    if !uci_supported && analysis.engine.is_none() && analysis.starting.is_none() {
+        analysis.external = false;
+    }
+    let mut clicked = None;
+    egui::Window::new("Analysis")
+        .vscroll(true)
+        .open(&mut ui_menu.analysis_window_open)
+        .show(ctx, |ui| {
+            let idle = analysis.engine.is_none() && analysis.starting.is_none();
+            ui.add_enabled_ui(idle, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut analysis.external, false, "built-in engine");
+                    if ui.add_enabled(uci_supported, egui::RadioButton::new(analysis.external, "UCI engine")).clicked() {
+                        analysis.external = true;
+                    }
+                });
+                if !uci_supported {
+                    ui.label(format!("UCI engines cannot analyse {}", variant));
+                }
+                if analysis.external {
+                    config.edit(ui);
+                }
+                ui.add(egui::DragValue::new(&mut analysis.multipv).range(1..=5).prefix("lines: "));
+            });
+            ui.horizontal(|ui| {
+                match &analysis.engine {
+                    None if analysis.starting.is_some() => {
+                        ui.spinner();
+                        ui.label("Starting engine...");
+                        if ui.button("Stop").clicked() {
+                            analysis.stop();
+                        }
+                    }
+                    None => if ui.add_enabled(!hidden, egui::Button::new("Start")).clicked() {
+                        analysis.start(&config);
+                    },
+                    Some(engine) => {
+                        let name = match engine {
+                            Analyzer::BuiltIn(_) => "built-in engine".to_string(),
+                            Analyzer::Uci(engine) => engine.name.clone(),
+                        };
+                        ui.label(name);
+                        if ui.button("Stop").clicked() {
+                            analysis.stop();
+                        }
+                    }
+                }
+            });
+            ui.colored_label(ui.visuals().error_fg_color, analysis.error.clone());
+            if hidden {
+                ui.label("Analysis is not available until the game ends");
+            }
+
+            ui.separator();
+            for (i, (info, moves)) in analysis.lines.iter().zip(&analysis.moves).enumerate() {
+                let score = info.score.map_or(String::new(), |score| score.to_string());
+                let depth = info.depth.map_or(String::new(), |depth| depth.to_string());
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(format!("{} [{}]", score, depth));
+                    for (k, text) in moves.iter().enumerate() {
+                        if ui.add_enabled(can_insert, egui::Button::new(text).small().frame(false)).clicked() {
+                            clicked = Some((i, k));
+                        }
+                    }
+                });
+            }
+        });
+
+    // 分析的局面和棋局树的焦点一致时才能加入
+    if let Some((i, k)) = clicked
+        && analysis.board() == Some(&game.tree.board())
+    {
+        let steps = analysis.lines[i].pv[..=k].to_vec();
+        insert_line(&mut game, &steps, &mut event_writer);
+    }
+
+    Ok(())
+}
+
+// 棋盘左侧的评估条，白方的部分从下往上增长
+#[derive(Component)]
+pub enum EvalBar {
+    Background,
+    White,
+}
+
+const EVAL_BAR_WIDTH: f32 = 16.0;
+const EVAL_BAR_BLACK: Color = Color::srgb(0.25, 0.25, 0.25);
+const EVAL_BAR_WHITE: Color = Color::srgb(0.95, 0.95, 0.95);
+
+pub fn setup_eval_bar(mut commands: Commands) {
+    for (bar, color, z) in [(EvalBar::Background, EVAL_BAR_BLACK, 0.0), (EvalBar::White, EVAL_BAR_WHITE, 0.1)] {
+        commands.spawn((
+            Sprite::from_color(color, Vec2::new(EVAL_BAR_WIDTH, 0.0)),
+            Transform::from_xyz(0.0, 0.0, z),
+            Visibility::Hidden,
+            bar,
+        ));
+    }
+}
+
+// 白方在评估条中所占的比例。将杀时占满或为空，其它情况按胜率曲线换算
+fn white_share(score: Score) -> f32 {
+    match score {
+        Score::Cp(cp) => 1.0 / (1.0 + 10f32.powf(-cp as f32 / 400.0)),
+        Score::Mate(n) => if n > 0 { 1.0 } else { 0.0 },
+    }
+}
+
+// 有分析结果时在棋盘左侧显示评估条，高度与棋盘相同
+pub fn update_eval_bar(
+    analysis: Res<Analysis>,
+    ui_menu: Res<UiMenuState>,
+    game: Res<Game>,
+    mut q_bar: Query<(&EvalBar, &mut Sprite, &mut Transform, &mut Visibility)>,
+) {
+    let score = analysis.white_score().filter(|_| ui_menu.analysis_window_open && !game.hides_position());
+    let (leftdown_x, leftdown_y) = game.view.leftdown;
+    let height = game.view.board.size.ranks as f32 * CELL_SIZE_J;
+    let x = leftdown_x - CELL_SIZE_I * 0.5;
+    for (bar, mut sprite, mut transform, mut visibility) in q_bar.iter_mut() {
+        let Some(score) = score else {
+            *visibility = Visibility::Hidden;
+            continue
+        };
+        let bar_height = match bar {
+            EvalBar::Background => height,
+            EvalBar::White => height * white_share(score),
+        };
+        *visibility = Visibility::Visible;
+        sprite.custom_size = Some(Vec2::new(EVAL_BAR_WIDTH, bar_height));
+        transform.translation.x = x;
+        transform.translation.y = leftdown_y + bar_height / 2.0;
+    }
+}
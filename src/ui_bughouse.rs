@@ -0,0 +1,147 @@
+use bevy::{color::palettes::css::{WHITE, YELLOW}, prelude::*, sprite::Anchor};
+use bevy_egui::{egui, EguiContexts};
+use chess_core::{
+    board::game_info, bughouse::{BughouseGame, BOARD_NAMES}, piece::PieceColor,
+};
+use crate::{
+    Game, CELL_SIZE_I, CELL_SIZE_J,
+};
+
+// 双人四人象棋每方的用时（秒）
+pub const BUGHOUSE_SECONDS: f32 = 180.0;
+
+// 双人四人象棋的棋局和两盘棋各自的棋钟
+pub struct BughouseState {
+    pub game: BughouseGame,
+    pub clocks: [[f32; 2]; 2],  // clocks[盘][颜色] 为剩余的秒数
+}
+
+impl BughouseState {
+    pub fn new(game: BughouseGame) -> Self {
+        BughouseState {
+            game,
+            clocks: [[BUGHOUSE_SECONDS; 2]; 2],
+        }
+    }
+}
+
+// 棋盘上方显示的局面信息：进行中时显示两盘的行动方，结束时显示先结束的一盘的结果
+pub fn bughouse_info(game: &BughouseGame) -> String {
+    match game.result {
+        Some((index, result)) => format!("{}: {}", BOARD_NAMES[index], game_info(&game.boards[index], Some(result))),
+        None => format!("A: {}   B: {}", game_info(&game.boards[0], None), game_info(&game.boards[1], None)),
+    }
+}
+
+// 两盘的棋钟各自为行动方计时，用完时间的一方判负。盘面不变，只有棋钟和局面信息需要更新，见 update_bughouse_clocks
+pub fn tick_bughouse_clocks(
+    time: Res<Time>,
+    mut game: ResMut<Game>,
+) {
+    let Some(bughouse) = &mut game.bughouse else {
+        return
+    };
+    if bughouse.game.result.is_some() {
+        return
+    }
+    for index in 0..2 {
+        let color = bughouse.game.boards[index].active_color;
+        let clock = &mut bughouse.clocks[index][color.index()];
+        *clock = (*clock - time.delta_secs()).max(0.0);
+        if *clock == 0.0 {
+            bughouse.game.flag(index, color);
+            return
+        }
+    }
+}
+
+// 显示在每个棋盘下方的棋钟，白方在左，黑方在右
+#[derive(Component)]
+pub struct BughouseClock {
+    board: usize,
+    color: PieceColor,
+}
+
+pub fn setup_bughouse_clocks(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let text_font = TextFont {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 24.0,
+        ..default()
+    };
+    for board in 0..2 {
+        for color in PieceColor::ALL {
+            commands.spawn((
+                Text2d::new(""),
+                text_font.clone(),
+                TextColor(WHITE.into()),
+                Transform::default(),
+                Anchor::Center,
+                Visibility::Hidden,
+                BughouseClock { board, color },
+            ));
+        }
+    }
+}
+
+// 每帧更新棋钟的读数，正在计时的一方用黄色显示。棋钟走完时局面信息中的结果也在这里更新，不需要重画棋盘
+pub fn update_bughouse_clocks(
+    game: Res<Game>,
+    mut q_clock: Query<(&BughouseClock, &mut Text2d, &mut TextColor, &mut Transform, &mut Visibility)>,
+    mut q_info: Query<&mut Text2d, Without<BughouseClock>>,
+) {
+    for (clock, mut text, mut text_color, mut transform, mut visibility) in q_clock.iter_mut() {
+        let Some(bughouse) = &game.bughouse else {
+            *visibility = Visibility::Hidden;
+            continue
+        };
+        let board = &bughouse.game.boards[clock.board];
+        let offset = match clock.color {
+            PieceColor::White => -1.5,
+            PieceColor::Black => 1.5,
+        };
+        let running = bughouse.game.result.is_none() && board.active_color == clock.color;
+        *visibility = Visibility::Visible;
+        text.0 = format!("{:?} {}", clock.color, format_clock(bughouse.clocks[clock.board][clock.color.index()]));
+        text_color.0 = if running { YELLOW.into() } else { WHITE.into() };
+        transform.translation.x = game.center_x(clock.board) + offset * CELL_SIZE_I;
+        transform.translation.y = -(board.size.ranks as f32 / 2.0 * CELL_SIZE_J) - 25.0;
+    }
+
+    if let Some(bughouse) = &game.bughouse
+        && let Some(mut text) = game.info.and_then(|info| q_info.get_mut(info).ok())
+    {
+        let info = bughouse_info(&bughouse.game);
+        if text.0 != info {
+            text.0 = info;
+        }
+    }
+}
+
+fn format_clock(seconds: f32) -> String {
+    let tenths = (seconds * 10.0).ceil() as u32;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+pub fn ui_bughouse(
+    mut contexts: EguiContexts,
+    game: Res<Game>,
+) -> Result {
+    let Some(bughouse) = &game.bughouse else {
+        return Ok(())
+    };
+    let ctx = contexts.ctx_mut()?;
+
+    egui::Window::new("Bughouse")
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(format!("Result: {}", bughouse.game.result_tag()));
+            if ui.button("Copy BPGN").clicked() {
+                ctx.copy_text(bughouse.game.to_bpgn());
+            }
+        });
+
+    Ok(())
+}
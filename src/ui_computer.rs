@@ -0,0 +1,239 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use bevy_egui::{egui, EguiContexts};
+use chess_core::{
+    board::*, engine::{Engine, SearchLimit, Strength}, game_tree::GameTree, piece::PieceColor,
+    uci::{UciEngine, HANDSHAKE_TIMEOUT}, variant::VariantKind,
+};
+use crate::{
+    play_step, Game, UciConfig, UiMenuState, UpdateBoard,
+};
+
+// 电脑使用的引擎
+enum Opponent {
+    BuiltIn(Box<Engine>),
+    Uci(UciEngine),
+}
+
+// 电脑走棋所需的局面信息，交给后台任务
+struct ComputerPosition {
+    root: Board,
+    steps: Vec<Step>,
+    history: Vec<Board>,
+    board: Board,
+}
+
+impl Opponent {
+    // 外部引擎只按思考时间走棋，等级只对内置引擎有效。外部引擎出错时返回 None
+    fn play(&mut self, position: &ComputerPosition, strength: Strength, time: Duration) -> Option<Step> {
+        match self {
+            Opponent::BuiltIn(engine) => engine.play(&position.board, &position.history, strength, time),
+            Opponent::Uci(engine) => {
+                engine.set_position(&position.root, &position.steps).ok()?;
+                engine.go(&[SearchLimit::Time(time)]).ok()?;
+                engine.wait_best_move(time + HANDSHAKE_TIMEOUT).ok()?.0
+            }
+        }
+    }
+}
+
+// 人机对局的设置和正在进行的搜索
+#[derive(Resource)]
+pub struct ComputerPlayer {
+    pub color: Option<PieceColor>,  // 电脑执的颜色，None 时双方都由人走棋
+    pub window_open: bool,
+    human_color: PieceColor,        // 对话框中选择的人执的颜色
+    level: usize,                   // 从 1 开始的等级，对应 Strength::LEVELS
+    seconds: f32,                   // 每步最多的思考时间
+    external: bool,                 // 使用 UciConfig 中的外部引擎
+    error: String,
+    engine: Option<Opponent>,       // 搜索时交给后台任务，搜索完再取回
+    task: Option<Task<(Opponent, Option<Step>, Board)>>,
+    starting: Option<Task<Result<UciEngine, String>>>,  // 正在启动的外部引擎
+}
+
+impl Default for ComputerPlayer {
+    fn default() -> Self {
+        ComputerPlayer {
+            color: None,
+            window_open: false,
+            human_color: PieceColor::White,
+            level: 3,
+            seconds: 2.0,
+            external: false,
+            error: String::new(),
+            engine: Some(Opponent::BuiltIn(Box::new(Engine::new()))),
+            task: None,
+            starting: None,
+        }
+    }
+}
+
+impl ComputerPlayer {
+    // 电脑正在思考或轮到电脑走棋，此时不接受棋盘上的操作
+    pub fn blocks_input(&self, game: &Game) -> bool {
+        self.task.is_some() || self.to_move(game)
+    }
+
+    fn to_move(&self, game: &Game) -> bool {
+        self.color == Some(game.board.active_color)
+            && game.bughouse.is_none()
+            && !game.board.variant.rules().fog_of_war()
+    }
+}
+
+// 轮到电脑走棋时在后台线程中搜索
+pub fn start_computer_move(
+    mut computer: ResMut<ComputerPlayer>,
+    game: Res<Game>,
+) {
+    if computer.task.is_some() || !computer.to_move(&game) || game.result().is_some() {
+        return
+    }
+    let Some(mut engine) = computer.engine.take() else {
+        return
+    };
+    let position = ComputerPosition {
+        root: game.tree.root_board(),
+        steps: game.tree.steps(),
+        history: game.tree.history(),
+        board: game.board.clone(),
+    };
+    let strength = Strength::LEVELS[computer.level - 1];
+    let time = Duration::from_secs_f32(computer.seconds);
+    computer.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        let step = engine.play(&position, strength, time);
+        (engine, step, position.board)
+    }));
+}
+
+// 搜索完成后，如果盘面在思考期间没有变化，像拖动棋子一样走这一步。电脑没有给出着法时停止人机对局
+pub fn finish_computer_move(
+    mut computer: ResMut<ComputerPlayer>,
+    mut game: ResMut<Game>,
+    mut event_writer: EventWriter<UpdateBoard>,
+) {
+    if !computer.task.as_ref().is_some_and(|task| task.is_finished()) {
+        return
+    }
+    let (engine, step, board) = block_on(computer.task.take().unwrap());
+    computer.engine = Some(engine);
+    if board != game.board || !computer.to_move(&game) {
+        return
+    }
+    match step {
+        Some(step) => {
+            play_step(&mut game, 0, step, &mut event_writer);
+        }
+        None => {
+            warn!("the computer did not return a move");
+            computer.color = None;
+        }
+    }
+}
+
+// 电脑用 opponent 执 human_color 的另一方，从 new_board 开始新的对局
+fn new_game(
+    computer: &mut ComputerPlayer,
+    opponent: Opponent,
+    new_board: Board,
+    game: &mut Game,
+    event_writer: &mut EventWriter<UpdateBoard>,
+) {
+    // 放弃正在进行的搜索
+    computer.task = None;
+    computer.engine = Some(opponent);
+    game.bughouse = None;
+    event_writer.write(UpdateBoard {
+        new_board: new_board.clone(),
+    });
+    game.tree = GameTree::new(new_board);
+    game.extra_moves = 0;
+    computer.color = Some(computer.human_color.flip());
+    computer.window_open = false;
+}
+
+pub fn ui_computer(
+    mut contexts: EguiContexts,
+    mut computer: ResMut<ComputerPlayer>,
+    ui_state: Res<UiMenuState>,
+    mut config: ResMut<UciConfig>,
+    mut game: ResMut<Game>,
+    mut event_writer: EventWriter<UpdateBoard>,
+) -> Result {
+    if !computer.window_open {
+        return Ok(())
+    }
+    let ctx = contexts.ctx_mut()?;
+
+    let mut open = true;
+    let variant = ui_state.variant;
+    // 双人四人象棋和暗棋不能和电脑对局
+    let supported = variant != VariantKind::Bughouse && !variant.rules().fog_of_war();
+
+    // 外部引擎启动完成后开始对局，引擎需要支持这个变体
+    if computer.starting.as_ref().is_some_and(|task| task.is_finished()) {
+        let new_board = variant.rules().start_position();
+        let opponent = block_on(computer.starting.take().unwrap()).and_then(|mut engine| {
+            engine.set_rules(&new_board).map_err(|e| e.to_string())?;
+            Ok(engine)
+        });
+        match opponent {
+            Ok(_) if !supported => {},
+            Ok(engine) => new_game(&mut computer, Opponent::Uci(engine), new_board, &mut game, &mut event_writer),
+            Err(e) => computer.error = e,
+        }
+    }
+
+    egui::Window::new("New game vs computer")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Play as");
+                for color in PieceColor::ALL {
+                    ui.radio_value(&mut computer.human_color, color, format!("{:?}", color));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut computer.external, false, "built-in engine");
+                ui.radio_value(&mut computer.external, true, "UCI engine");
+            });
+            if computer.external {
+                config.edit(ui);
+            } else {
+                ui.add(egui::Slider::new(&mut computer.level, 1..=Strength::LEVELS.len()).text("level"));
+            }
+            ui.add(egui::Slider::new(&mut computer.seconds, 0.1..=30.0).text("seconds per move"));
+            ui.label(format!("Variant: {}", variant.name()));
+            ui.horizontal(|ui| {
+                let starting = computer.starting.is_some();
+                if ui.add_enabled(supported && !starting, egui::Button::new("Start")).clicked() {
+                    computer.error.clear();
+                    if computer.external {
+                        computer.starting = Some(config.start());
+                    } else {
+                        // 内置引擎保留置换表，正在思考时换一个新的
+                        let opponent = match computer.engine.take() {
+                            Some(Opponent::BuiltIn(engine)) => engine,
+                            _ => Box::new(Engine::new()),
+                        };
+                        new_game(&mut computer, Opponent::BuiltIn(opponent), variant.rules().start_position(), &mut game, &mut event_writer);
+                    }
+                }
+                if starting {
+                    ui.spinner();
+                    ui.label("Starting engine...");
+                }
+            });
+            ui.colored_label(ui.visuals().error_fg_color, computer.error.clone());
+        });
+    // 关闭对话框时放弃正在启动的引擎
+    if !open {
+        computer.window_open = false;
+        computer.starting = None;
+    }
+
+    Ok(())
+}
@@ -1,12 +1,15 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
+use chess_core::{
+    fen::*, game_tree::GameTree, validate::{check_position, describe_issues}, variant::VariantKind,
+};
 use crate::{
-    fen::*, game_tree::GameTree, Game, UpdateBoard, menu::*,
+    Game, UpdateBoard, menu::*,
 };
 
 #[derive(Default, Resource)]
 pub struct UiFenState {
-    pub current_fen: String,
+    pub current_fen: Option<String>,    // 暗棋进行中为 None，不显示完整的盘面
     pub load_fen: String,
     pub error_info: String,
 }
@@ -19,6 +22,8 @@ pub fn ui_fen_system(
     mut ui_menu: ResMut<UiMenuState>,
 ) -> Result {
     let ctx = contexts.ctx_mut()?;
+    let mode = ui_menu.validation_mode();
+    let variant = ui_menu.variant;
 
     egui::Window::new("FEN")
         .vscroll(true)
@@ -26,10 +31,12 @@ pub fn ui_fen_system(
         .show(ctx, |ui| {
             ui.label(format!(
                 "Current FEN: {}",
-                ui_state.current_fen,
+                ui_state.current_fen.as_deref().unwrap_or("hidden until the game ends"),
             ));
-            if ui.button("Copy").clicked() {
-                ui.ctx().copy_text(ui_state.current_fen.clone());
+            if ui.add_enabled(ui_state.current_fen.is_some(), egui::Button::new("Copy")).clicked()
+                && let Some(fen) = &ui_state.current_fen
+            {
+                ui.ctx().copy_text(fen.clone());
             }
 
             ui.horizontal(|ui| {
@@ -38,20 +45,38 @@ pub fn ui_fen_system(
             });
             ui.horizontal(|ui| {
                 if ui.button("Load").clicked() {
-                    let new_board = read_fen(ui_state.load_fen.clone());
-                    event_writer.write(UpdateBoard {
-                        new_board: new_board.clone(),
-                    });
-                    game.tree = GameTree::new(new_board);
+                    // FEN 中没有变体特有的字段时使用菜单中选择的变体
+                    let loaded = read_fen(ui_state.load_fen.clone())
+                        .map(|mut board| {
+                            if board.variant == VariantKind::Standard {
+                                board.set_variant(variant);
+                            }
+                            board
+                        })
+                        .map_err(|e| e.to_string())
+                        // 一个 FEN 只能描述一盘棋，双人四人象棋需要读取 BPGN
+                        .and_then(|board| match board.variant {
+                            VariantKind::Bughouse => Err("a FEN cannot describe a Bughouse game, load a BPGN instead".to_string()),
+                            _ => Ok(board),
+                        })
+                        .and_then(|board| check_position(board, mode).map_err(|issues| describe_issues(&issues)));
+                    match loaded {
+                        Ok(new_board) => {
+                            ui_state.error_info.clear();
+                            event_writer.write(UpdateBoard {
+                                new_board: new_board.clone(),
+                            });
+                            game.tree = GameTree::new(new_board);
+                            game.extra_moves = 0;
+                            game.bughouse = None;
+                        }
+                        Err(e) => ui_state.error_info = e,
+                    }
                 }
-                ui.label(ui_state.error_info.clone());
+                ui.colored_label(ui.visuals().error_fg_color, ui_state.error_info.clone());
             });
             if ui.button("New Game").clicked() {
-                let new_board = read_fen(INITIAL_FEN.to_string());
-                event_writer.write(UpdateBoard {
-                    new_board: new_board.clone(),
-                });
-                game.tree = GameTree::new(new_board);
+                new_game(&mut game, variant, &mut event_writer);
             }
         });
 
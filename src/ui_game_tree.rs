@@ -1,7 +1,10 @@
 use bevy::prelude::*;
-use bevy_egui::{egui::{self, Ui, Grid}, EguiContexts};
+use bevy_egui::{egui::{self, text::Fonts, Align2, FontId, Grid, Label, RichText, Sense, Ui}, EguiContexts};
+use chess_core::{
+    game_tree::*, piece::PieceColor,
+};
 use crate::{
-    event::DeleteVariationEvent, game_tree::*, menu::UiMenuState, Game, UpdateBoard
+    event::DeleteVariationEvent, menu::UiMenuState, Game, UpdateBoard
 };
 
 pub fn ui_game_tree(
@@ -12,15 +15,21 @@ pub fn ui_game_tree(
     mut ew_dv: EventWriter<DeleteVariationEvent>,
 ) -> Result {
     let ctx = contexts.ctx_mut()?;
+    let hidden = game.hides_position();
 
     egui::Window::new("Game Tree")
         .open(&mut ui_menu.tree_window_open)
         .show(ctx, |ui| {
+            // 暗棋进行中的着法和之前的局面会暴露对方的棋子
+            if hidden {
+                ui.label("Moves are hidden until the game ends");
+                return
+            }
             egui::ScrollArea::vertical()
                 .max_height(400.0)
                 .show(ui, |ui| {
                     // ui.label("empty game tree");
-                    game.tree.display_egui(ui, &mut event_writer, &mut ew_dv);
+                    display_game_tree(&mut game.tree, ui, &mut event_writer, &mut ew_dv);
                 });
             
             ui.separator();
@@ -79,4 +88,331 @@ pub fn ui_game_tree(
         });
 
     Ok(())
-}
\ No newline at end of file
+}
+
+fn show_context_menu(
+    tree: &mut GameTree,
+    current: usize,
+    response: &egui::Response,
+    ew_dv: &mut EventWriter<DeleteVariationEvent>,
+) {
+    egui::Popup::context_menu(response)
+        .show(|ui| {
+        ui.set_min_width(120.0);
+        
+        if ui.button("Promote Variation").clicked() {
+            tree.promote_variation(current);
+        }
+        
+        if ui.button("Set as mainline").clicked() {
+            tree.set_as_mainline(current);
+        }
+        
+        if ui.button("Delete Variation").clicked() {
+            ew_dv.write(DeleteVariationEvent {
+                node_to_delete: current,
+            });
+        }
+        
+        if ui.button("Copy PGN").clicked() {
+            ui.ctx().copy_text(tree.pgn(current));
+        }
+    });
+}
+
+fn get_text_width(s: &str, f: &Fonts, font_id: &FontId) -> f32 {
+    let mut res: f32 = 0.0;
+    for c in s.chars() {
+        res += f.glyph_width(font_id, c);
+    }
+    res
+}
+
+fn show_labels_horizontal(
+    tree: &mut GameTree, 
+    ui: &mut egui::Ui, 
+    prefix: String, 
+    labels: Vec<(String, usize)>,
+    event_writer: &mut EventWriter<UpdateBoard>,
+    ew_dv: &mut EventWriter<DeleteVariationEvent>,
+) {
+    let font_id = egui::FontId::default();
+    let mono_font = FontId::monospace(14.0);
+
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(prefix.clone()).font(mono_font.clone()));
+        for (s, idx) in labels.iter() {
+            let new_width = ui.fonts(|f| get_text_width(s, f, &font_id));
+            let response = ui.allocate_response(
+                egui::Vec2::new(
+                    new_width,
+                    ui.text_style_height(&egui::TextStyle::Body),
+                ),
+                egui::Sense::click(), 
+            );
+            if *idx == tree.focus() {
+                let rect = response.rect;
+                // 使用默认悬停颜色
+                ui.painter().rect_filled(rect, 2.0, ui.visuals().widgets.hovered.bg_fill);
+            }
+            if response.hovered() {
+                let rect = response.rect;
+                // 使用默认悬停颜色
+                ui.painter().rect_filled(rect, 2.0, ui.visuals().widgets.hovered.bg_fill);
+            }
+            ui.painter().text(
+                response.rect.left_center(),
+                egui::Align2::LEFT_CENTER,
+                s,
+                font_id.clone(),
+                ui.visuals().text_color(),
+            );
+            if response.clicked() {
+                tree.move_to_node(*idx);
+                event_writer.write(UpdateBoard {
+                    new_board: tree.board()
+                });
+            }
+            show_context_menu(tree, *idx, &response, ew_dv);
+        }
+    });
+}
+
+// 展示一些步，自动换行，换行前使用 header_prefix 作为前缀，换行后使用 prefix 作为前缀
+fn show_labels(
+    tree: &mut GameTree, 
+    ui: &mut egui::Ui, 
+    header_prefix: String, 
+    prefix: String, 
+    labels: Vec<(String, usize)>,
+    event_writer: &mut EventWriter<UpdateBoard>,
+    ew_dv: &mut EventWriter<DeleteVariationEvent>,
+) {
+    let font_id = egui::FontId::default();
+    let mono_font = FontId::monospace(14.0);
+    let initial_width = ui.available_width();
+    let mut width = initial_width;
+    width -= ui.fonts(|f| get_text_width(&header_prefix, f, &mono_font));
+    let mut last = 0;
+    let mut first_line = true;
+    for (idx, (s, _)) in labels.iter().enumerate() {
+        let new_width = ui.fonts(|f| get_text_width(s, f, &font_id));
+        if width < new_width + ui.spacing().item_spacing.x {
+            show_labels_horizontal(tree, ui, 
+                if first_line { header_prefix.clone() } else { prefix.clone() }, 
+                Vec::from(&labels[last..idx]), 
+                event_writer,
+                ew_dv,
+            );
+            last = idx;
+            first_line = false;
+            width = initial_width;
+            width -= ui.fonts(|f| get_text_width(&prefix, f, &mono_font));
+        }
+        width -= new_width + ui.spacing().item_spacing.x;
+    }
+    show_labels_horizontal(tree, ui, 
+        if first_line { header_prefix.clone() } else { prefix.clone() }, 
+        Vec::from(&labels[last..]), 
+        event_writer,
+        ew_dv,
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn dfs_branch(
+    tree: &mut GameTree, 
+    current: usize, 
+    mut labels: Vec<(String, usize)>,
+    header_prefix: String,
+    prefix: String,
+    ui: &mut egui::Ui,
+    event_writer: &mut EventWriter<UpdateBoard>,
+    ew_dv: &mut EventWriter<DeleteVariationEvent>,
+) {
+    let son_num = tree.sons(current).len();
+    if son_num > 1 {
+        show_labels(tree, ui, header_prefix.clone(), prefix.clone(), labels, event_writer, ew_dv);
+
+        let new_header = format!("{prefix}{PRE1}");
+        let new_pre = format!("{prefix}{PRE3}");
+        let last_header = format!("{prefix}{PRE2}");
+        let last_pre = format!("{prefix}{PRE4}");
+
+        for i in 0..son_num {
+            let (_step, son, move_data) = &tree.sons(current)[i];
+            let son = *son;
+            let san = match move_data.color {
+                PieceColor::White => format!("{}.{}", move_data.ply, move_data.san),
+                PieceColor::Black => format!("{}...{}", move_data.ply, move_data.san),
+            };
+            
+            dfs_branch(
+                tree,
+                son,
+                vec![(san, son)],
+                if i == son_num - 1 { last_header.clone() } else { new_header.clone() },
+                if i == son_num - 1 { last_pre.clone() } else { new_pre.clone() },
+                ui,
+                event_writer,
+                ew_dv,
+            );
+        }
+    } else if son_num == 1 {
+        let (_step, son, move_data) = &tree.sons(current)[0];
+        let son = *son;
+        let san = match move_data.color {
+            PieceColor::White => format!("{}.{}", move_data.ply, move_data.san),
+            PieceColor::Black => move_data.san.clone(),
+        };
+        dfs_branch(
+            tree,
+            son,
+            {
+                labels.push((san, son));
+                labels
+            },
+            header_prefix, 
+            prefix, 
+            ui, 
+            event_writer,
+            ew_dv);
+    } else {
+        show_labels(tree, ui, header_prefix, prefix, labels, event_writer, ew_dv);
+    }
+}
+
+fn dfs_mainline(
+    tree: &mut GameTree, 
+    current: usize,
+    ui: &mut egui::Ui,
+    event_writer: &mut EventWriter<UpdateBoard>,
+    ew_dv: &mut EventWriter<DeleteVariationEvent>,
+) {
+    let son_num = tree.sons(current).len();
+    let total_width = ui.available_width();
+
+    if son_num >= 1 {
+        // 有支线时先展示支线
+        for i in 1..son_num {
+            let (_step, son, move_data) = &tree.sons(current)[i];
+            let son = *son;
+            let san = match move_data.color {
+                PieceColor::White => format!("{}.{}", move_data.ply, move_data.san),
+                PieceColor::Black => format!("{}...{}", move_data.ply, move_data.san),
+            };
+            
+            dfs_branch(
+                tree,
+                son,
+                vec![(san, son)],
+                if i == son_num - 1 { String::from(PRE2) } else { String::from(PRE1) },
+                if i == son_num - 1 { String::from(PRE4) } else { String::from(PRE3) },
+                ui,
+                event_writer,
+                ew_dv,
+            );
+        }
+        let (_step, son, move_data) = tree.sons(current)[0].clone();
+        match move_data.color {
+            PieceColor::White => {
+                ui.horizontal(|ui| {
+                    ui.add_sized([total_width * 0.15, 0.0], Label::new(move_data.ply.to_string()));
+                    let response = ui.add_sized(
+                        [total_width * 0.40, 0.0],
+                        Label::new(move_data.san.clone()).sense(Sense::click()),
+                    );
+                    // if response.secondary_clicked() {
+                    //     self.context_menu = Some(son);
+                    // }
+                    if son == tree.focus() {
+                        let rect = response.rect;
+                        // 使用默认悬停颜色
+                        ui.painter().rect_filled(rect, 2.0, ui.visuals().widgets.hovered.bg_fill);
+                    }
+                    if response.hovered() {
+                        let rect = response.rect;
+                        // 使用默认悬停颜色
+                        ui.painter().rect_filled(rect, 2.0, ui.visuals().widgets.hovered.bg_fill);
+                    }
+                    // 如果文字被颜色覆盖，重新绘制文字
+                    if son == tree.focus() || response.hovered() {
+                        let rect = response.rect;
+                        ui.painter().text(
+                            rect.center(),
+                            Align2::CENTER_CENTER,
+                            move_data.san.clone(),
+                            egui::FontId::default(),
+                            ui.visuals().text_color(),
+                        );
+                    }
+                    if response.clicked() {
+                        tree.move_to_node(son);
+                        event_writer.write(UpdateBoard {
+                            new_board: tree.board()
+                        });
+                    }
+                    show_context_menu(tree, son, &response, ew_dv);
+                    ui.add_sized([total_width * 0.40, 0.0], Label::new("..."));
+                });
+            },
+            PieceColor::Black => {
+                ui.horizontal(|ui| {
+                    ui.add_sized([total_width * 0.15, 0.0], Label::new(move_data.ply.to_string()));
+                    ui.add_sized([total_width * 0.40, 0.0], Label::new("..."));
+                    let response = ui.add_sized(
+                        [total_width * 0.40, 0.0],
+                        Label::new(move_data.san.clone()).sense(Sense::click()),
+                    );
+                    // if response.secondary_clicked() {
+                    //     self.context_menu = Some(son);
+                    // }
+                    if son == tree.focus() {
+                        let rect = response.rect;
+                        // 使用默认悬停颜色
+                        ui.painter().rect_filled(rect, 2.0, ui.visuals().widgets.hovered.bg_fill);
+                    }
+                    if response.hovered() {
+                        let rect = response.rect;
+                        // 使用默认悬停颜色
+                        ui.painter().rect_filled(rect, 2.0, ui.visuals().widgets.hovered.bg_fill);
+                    }
+                    // 如果文字被颜色覆盖，重新绘制文字
+                    if son == tree.focus() || response.hovered() {
+                        let rect = response.rect;
+                        ui.painter().text(
+                            rect.center(),
+                            Align2::CENTER_CENTER,
+                            move_data.san.clone(),
+                            egui::FontId::default(),
+                            ui.visuals().text_color(),
+                        );
+                    }
+                    if response.clicked() {
+                        tree.move_to_node(son);
+                        event_writer.write(UpdateBoard {
+                            new_board: tree.board()
+                        });
+                    }
+                    show_context_menu(tree, son, &response, ew_dv);
+                });
+            },
+        }
+        dfs_mainline(tree, son, ui, event_writer, ew_dv);
+    }
+}
+
+pub fn display_game_tree(
+    tree: &mut GameTree, 
+    ui: &mut egui::Ui,
+    event_writer: &mut EventWriter<UpdateBoard>,
+    ew_dv: &mut EventWriter<DeleteVariationEvent>,
+) {
+    let root = tree.root();
+    dfs_mainline(tree, root, ui, event_writer, ew_dv);
+}
+
+const PRE1: &str = "├─";
+const PRE2: &str = "└─";
+const PRE3: &str = "| ";
+const PRE4: &str = "  ";
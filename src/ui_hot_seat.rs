@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use chess_core::piece::PieceColor;
+use crate::{
+    Game, UpdateBoard,
+};
+
+// 暗棋中棋盘被遮住时，提示交换座位。行动方准备好后再显示他能看到的格子
+pub fn ui_hot_seat(
+    mut contexts: EguiContexts,
+    mut game: ResMut<Game>,
+    mut event_writer: EventWriter<UpdateBoard>,
+) -> Result {
+    if !game.covered {
+        return Ok(())
+    }
+    let ctx = contexts.ctx_mut()?;
+
+    let player = match game.board.active_color {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black",
+    };
+    egui::Window::new("Hot Seat")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label(format!("Pass the board to {}", player));
+            if ui.button("Ready").clicked() {
+                game.covered = false;
+                event_writer.write(UpdateBoard {
+                    new_board: game.board.clone(),
+                });
+            }
+        });
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use chess_core::board::*;
+use crate::{
+    play_step, CellCom, Game, UpdateBoard,
+};
+
+// 兵被拖到底线后，等待玩家选择升变棋子
+#[derive(Resource)]
+pub struct PendingPromotion {
+    pub board: usize,
+    pub step: Step,
+    pub dragged_entity: Entity,
+    pub start_cell: Entity,
+}
+
+pub fn ui_promotion(
+    mut commands: Commands,
+    pending: Option<Res<PendingPromotion>>,
+    mut contexts: EguiContexts,
+    mut game: ResMut<Game>,
+    mut event_writer: EventWriter<UpdateBoard>,
+    q_cell: Query<&Transform, With<CellCom>>,
+    mut transforms: Query<&mut Transform, Without<CellCom>>,
+) -> Result {
+    let Some(pending) = pending else {
+        return Ok(())
+    };
+    let ctx = contexts.ctx_mut()?;
+
+    let mut chosen = None;
+    let mut cancelled = false;
+    egui::Window::new("Promotion")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for &role in game.board_at(pending.board).variant.rules().promotion_roles() {
+                    if ui.button(role.to_string()).clicked() {
+                        chosen = Some(role);
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    cancelled = true;
+                }
+            });
+        });
+
+    if let Some(role) = chosen {
+        let step = pending.step.with_promotion(role);
+        if !play_step(&mut game, pending.board, step, &mut event_writer) {
+            cancelled = true;
+        }
+    }
+
+    if cancelled {
+        // 取消升变，棋子回到出发格
+        if let (Ok(start), Ok(mut transform)) = (q_cell.get(pending.start_cell), transforms.get_mut(pending.dragged_entity)) {
+            transform.translation.x = start.translation.x;
+            transform.translation.y = start.translation.y;
+        }
+    }
+
+    if chosen.is_some() || cancelled {
+        commands.remove_resource::<PendingPromotion>();
+    }
+
+    Ok(())
+}